@@ -1,7 +1,36 @@
-use crate::envycontrol::GpuInfo;
+use crate::config::{Config, Profile};
+use crate::distro::{self, Immutable};
+use crate::envycontrol::{
+    self, GpuInfo, GpuProcess, InstallMethod, Phase, SwitchChanges, SwitchOptions,
+};
+use crate::host::{self, Sandbox};
+use crate::icons::Icons;
+use crate::input::{InputWidget, TextInput};
+use crate::keymap::{self, Action, Keymap};
+use crate::logging;
+#[cfg(feature = "monitor")]
+use crate::monitor::{GameMode, History};
+#[cfg(feature = "overclock")]
+use crate::overclock::OverclockPanel;
+use crate::rules::Output;
+use crate::system::{
+    self, DgpuPower, InitramfsTool, KernelParam, ModprobeEntry, PciGpu, Provider, ServiceState,
+    ServiceStatus,
+};
+use crate::task::Held;
+use crate::theme::ThemeEditor;
+use crate::validate::Problem;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
 use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+const FLASH_DURATION: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum GraphicsMode {
     Integrated,
     Hybrid,
@@ -18,6 +47,19 @@ impl fmt::Display for GraphicsMode {
     }
 }
 
+impl FromStr for GraphicsMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "integrated" => Ok(GraphicsMode::Integrated),
+            "hybrid" => Ok(GraphicsMode::Hybrid),
+            "nvidia" => Ok(GraphicsMode::Nvidia),
+            other => Err(format!("Unknown graphics mode: {}", other)),
+        }
+    }
+}
+
 impl GraphicsMode {
     pub fn description(&self) -> &str {
         match self {
@@ -33,21 +75,11 @@ impl GraphicsMode {
         }
     }
 
-    pub fn icon(&self) -> &str {
-        match self {
-            GraphicsMode::Integrated => "󰍹",
-            GraphicsMode::Hybrid => "󰢮",
-            GraphicsMode::Nvidia => "󰾲",
-        }
-    }
-
-    pub fn all() -> Vec<GraphicsMode> {
-        vec![
-            GraphicsMode::Integrated,
-            GraphicsMode::Hybrid,
-            GraphicsMode::Nvidia,
-        ]
-    }
+    pub const ALL: [GraphicsMode; 3] = [
+        GraphicsMode::Integrated,
+        GraphicsMode::Hybrid,
+        GraphicsMode::Nvidia,
+    ];
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -79,14 +111,168 @@ impl Rtd3Level {
         }
     }
 
-    pub fn all() -> Vec<Rtd3Level> {
-        vec![
-            Rtd3Level::Disabled,
-            Rtd3Level::CoarseGrained,
-            Rtd3Level::FineGrained,
-            Rtd3Level::FineGrainedAmpere,
-        ]
+    pub fn from_value(value: u8) -> Option<Rtd3Level> {
+        Rtd3Level::ALL.into_iter().find(|l| l.value() == value)
+    }
+
+    pub const ALL: [Rtd3Level; 4] = [
+        Rtd3Level::Disabled,
+        Rtd3Level::CoarseGrained,
+        Rtd3Level::FineGrained,
+        Rtd3Level::FineGrainedAmpere,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppTab {
+    Switch,
+    System,
+    #[cfg(feature = "monitor")]
+    Monitor,
+    Logs,
+}
+
+impl AppTab {
+    pub fn title(&self) -> &'static str {
+        match self {
+            AppTab::Switch => "Switch",
+            AppTab::System => "System",
+            #[cfg(feature = "monitor")]
+            AppTab::Monitor => "Monitor",
+            AppTab::Logs => "Logs",
+        }
+    }
+
+    pub const ALL: &[AppTab] = &[
+        AppTab::Switch,
+        AppTab::System,
+        #[cfg(feature = "monitor")]
+        AppTab::Monitor,
+        AppTab::Logs,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionKind {
+    Rtd3,
+    Rtd3Level,
+    ForceComp,
+    Coolbits,
+    PowerMizer,
+    Info,
+}
+
+impl OptionKind {
+    // The longer explanation shown in the option detail popup.
+    pub fn explanation(self) -> &'static str {
+        match self {
+            OptionKind::Rtd3 => {
+                "Runtime D3 lets the driver power the dGPU off completely while nothing uses it, \
+                 instead of idling at a few watts. Needs a Turing (GTX 16xx / RTX 20xx) or newer \
+                 GPU and laptop firmware that supports it."
+            }
+            OptionKind::Rtd3Level => {
+                "0 disables runtime power management. 1 (coarse-grained) powers the dGPU off only \
+                 when no application holds it open. 2 (fine-grained) also powers it off between \
+                 uses while applications keep it open, the recommended level on Turing and newer. \
+                 3 is fine-grained for Ampere and newer GPUs with video memory self-refresh."
+            }
+            OptionKind::ForceComp => {
+                "Sets ForceCompositionPipeline in the generated xorg.conf. Every frame goes \
+                 through an extra composition pass, which removes tearing at the cost of some \
+                 input latency and a little GPU time."
+            }
+            OptionKind::Coolbits => {
+                "Sets the Coolbits option in the generated xorg.conf. The value is a bit mask: \
+                 4 unlocks manual fan control, 8 clock offsets (overclocking) and 16 overvoltage \
+                 in nvidia-settings. 28 enables all three; 1 and 2 only apply to old GPUs."
+            }
+            OptionKind::PowerMizer => {
+                "The driver's PowerMizer preference. Adaptive lowers clocks when the GPU is \
+                 lightly loaded; Prefer Maximum Performance keeps them high, which avoids stutter \
+                 from clock ramp-up at the cost of power and heat. Applied right away with \
+                 nvidia-settings, not by envycontrol, and lost when the X server restarts."
+            }
+            OptionKind::Info => "",
+        }
     }
+
+    pub fn flag(self) -> Option<&'static str> {
+        match self {
+            OptionKind::Rtd3 => Some("--rtd3 <level>, hybrid mode only"),
+            OptionKind::Rtd3Level => Some("--rtd3 0|1|2|3"),
+            OptionKind::ForceComp => Some("--force-comp, nvidia mode only"),
+            OptionKind::Coolbits => Some("--coolbits <value>, nvidia mode only"),
+            OptionKind::PowerMizer | OptionKind::Info => None,
+        }
+    }
+
+    // (session type, caveat) pairs; the session type matches XDG_SESSION_TYPE.
+    pub fn caveats(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            OptionKind::Rtd3 => &[
+                (
+                    "x11",
+                    "Applications started on the dGPU (prime-run) keep it awake until they exit.",
+                ),
+                (
+                    "wayland",
+                    "Some compositors open every GPU at startup and keep the dGPU awake; check \
+                     that nvidia-smi lists no processes if it never suspends.",
+                ),
+            ],
+            OptionKind::Rtd3Level => &[
+                (
+                    "x11",
+                    "Levels 2 and 3 add a short delay when the dGPU wakes up, e.g. for prime-run.",
+                ),
+                (
+                    "wayland",
+                    "Displays wired to the dGPU keep it awake at every level.",
+                ),
+            ],
+            OptionKind::ForceComp => &[
+                (
+                    "x11",
+                    "Only needed when the compositor doesn't already sync to vblank.",
+                ),
+                (
+                    "wayland",
+                    "No effect: Wayland sessions don't read xorg.conf.",
+                ),
+            ],
+            OptionKind::Coolbits => &[
+                (
+                    "x11",
+                    "Changes are made in nvidia-settings and need an X session on the NVIDIA GPU.",
+                ),
+                (
+                    "wayland",
+                    "nvidia-settings can't change clocks or fans under Wayland; use nvidia-smi \
+                     or GreenWithEnvy instead.",
+                ),
+            ],
+            OptionKind::PowerMizer => &[
+                (
+                    "x11",
+                    "Add the nvidia-settings -a command to your session startup to keep it.",
+                ),
+                (
+                    "wayland",
+                    "nvidia-settings can't change PowerMizer under Wayland.",
+                ),
+            ],
+            OptionKind::Info => &[],
+        }
+    }
+}
+
+pub struct OptionItem {
+    pub kind: OptionKind,
+    pub label: String,
+    pub description: &'static str,
+    pub is_on: bool,
+    pub is_toggle: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -98,13 +284,57 @@ pub enum AppPanel {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AppState {
     Normal,
+    ConfirmingProcesses,
     ConfirmingSwitch,
     ConfirmingReboot,
+    About,
+    OptionDetails,
+    Help,
+    ConfigProblems,
+    ThemeEditor,
+    #[cfg(feature = "overclock")]
+    Overclock,
+    Input,
     Loading,
     Success,
     Error,
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct AboutInfo {
+    pub envycontrol_version: Option<String>,
+    pub supergfx_version: Option<String>,
+    pub session_type: Option<String>,
+    pub desktop: Option<String>,
+    pub distro: Option<String>,
+    pub config_dir: Option<PathBuf>,
+    pub state_dir: Option<PathBuf>,
+}
+
+// A failed privileged command that can be re-run from the error popup. A
+// switch keeps the options it ran with, whatever the UI shows by then.
+#[derive(Debug, Clone)]
+pub enum Retry {
+    Switch(SwitchOptions),
+    Reset,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputPurpose {
+    CoolbitsValue,
+    Rtd3Level,
+    ProfileName,
+    Password,
+}
+
+#[derive(Debug, Clone)]
+pub struct InputPrompt {
+    pub purpose: InputPurpose,
+    pub title: String,
+    pub widget: InputWidget,
+    pub error: Option<String>,
+}
+
 pub struct Spinner {
     frames: Vec<&'static str>,
 }
@@ -125,6 +355,7 @@ pub struct App {
     pub current_mode: Option<GraphicsMode>,
     pub selected_mode_index: usize,
     pub selected_option_index: usize,
+    pub active_tab: AppTab,
     pub active_panel: AppPanel,
     pub state: AppState,
     pub message: String,
@@ -135,17 +366,112 @@ pub struct App {
     pub coolbits_value: u8,
     pub should_quit: bool,
     pub pending_mode: Option<GraphicsMode>,
+    // A power-source profile offered with the pending switch; it only replaces
+    // the selection once the switch goes ahead.
+    pub suggested_profile: Option<Profile>,
+    // Switched to but not rebooted into yet.
+    pub reboot_pending: Option<GraphicsMode>,
     pub spinner_frame: usize,
     pub spinner: Spinner,
     pub gpu_info: Option<GpuInfo>,
+    pub gpu_processes: Vec<GpuProcess>,
+    pub selected_process_index: usize,
+    pub services: Vec<ServiceStatus>,
+    pub persistenced: Option<ServiceStatus>,
+    pub persistence_mode: Option<bool>,
+    // Whether PowerMizer prefers maximum performance; None outside an X
+    // session on the NVIDIA GPU.
+    pub powermizer_max_performance: Option<bool>,
+    // The applied xorg.conf has the clock Coolbit; the offsets themselves are
+    // read when the panel opens.
+    #[cfg(feature = "overclock")]
+    pub overclock_unlocked: bool,
+    #[cfg(feature = "overclock")]
+    pub overclock: Option<OverclockPanel>,
+    pub kernel_params: Vec<KernelParam>,
+    pub modprobe_entries: Vec<ModprobeEntry>,
+    pub config: Config,
+    pub on_ac: Option<bool>,
+    pub skip_confirmations: bool,
+    // --monochrome or [ui] monochrome: no colors, ASCII icons.
+    pub monochrome: bool,
+    pub log_lines: Vec<String>,
+    pub log_scroll: usize,
+    pub popup_scroll: usize,
+    // How far the open popup can scroll, as of the last draw.
+    pub popup_max_scroll: Cell<usize>,
+    pub log_search: TextInput,
+    pub log_search_active: bool,
+    pub log_view_height: Cell<usize>,
+    pub input: Option<InputPrompt>,
+    pub password_task: Option<Held>,
+    pub compact: bool,
+    pub keymap: Keymap,
+    pub flash: Option<(String, Instant)>,
+    pub last_input: Instant,
+    pub envycontrol_installed: bool,
+    pub retry: Option<Retry>,
+    pub about: Option<AboutInfo>,
+    pub theme_editor: Option<ThemeEditor>,
+    pub help_page: usize,
+    pub config_problems: Vec<Problem>,
+    pub envycontrol_version: Option<String>,
+    // How envycontrol was installed, when that could be told.
+    pub envycontrol_install: Option<InstallMethod>,
+    pub progress_lines: Vec<String>,
+    // Every output line of the running task, for the summary after a switch.
+    pub task_output: Vec<String>,
+    pub phases: Vec<Phase>,
+    pub initramfs_tool: Option<InitramfsTool>,
+    pub sandbox: Sandbox,
+    pub immutable: Option<Immutable>,
+    pub conflicts: Vec<String>,
+    pub providers: Option<Vec<Provider>>,
+    pub outputs: Vec<Output>,
+    #[cfg(feature = "monitor")]
+    pub history: History,
+    // Time and mode of each past switch, for the before/after comparison.
+    #[cfg(feature = "monitor")]
+    pub switches: Vec<(u64, GraphicsMode)>,
+    // Switches before this boot have taken effect.
+    #[cfg(feature = "monitor")]
+    pub boot_time: Option<u64>,
+    // None when gamemoded isn't installed.
+    #[cfg(feature = "monitor")]
+    pub gamemode: Option<GameMode>,
+    // Set after the first failed write to [monitor] csv_log.
+    #[cfg(feature = "monitor")]
+    pub csv_log_failed: bool,
+    pub summary: Vec<String>,
+    pub log_path: Option<PathBuf>,
+    pub options: Vec<OptionItem>,
+    pub running: Option<&'static str>,
+    pub queued_switch: Option<GraphicsMode>,
+    pub session_type: Option<String>,
+    // Detected at startup; decides which display manager files a switch writes.
+    pub display_manager: Option<String>,
+    pub icons: Icons,
+    // The NVIDIA card lspci found while its driver isn't in use.
+    pub dgpu_hardware: Option<PciGpu>,
+    // Steam and games found when the switch was confirmed.
+    pub running_games: Vec<String>,
+    // What the login screen will offer in nvidia mode, read when the switch
+    // was confirmed.
+    pub login_note: Option<String>,
+    // The option flags the generated files for the confirmed mode carry.
+    pub applied_args: Vec<String>,
+    // Startup probes are still running; the header shows placeholders.
+    pub detecting: bool,
+    pub dgpu_power: Option<DgpuPower>,
 }
 
 impl App {
     pub fn new() -> Self {
-        Self {
+        let mut app = Self {
             current_mode: None,
             selected_mode_index: 0,
             selected_option_index: 0,
+            active_tab: AppTab::Switch,
             active_panel: AppPanel::ModeSelection,
             state: AppState::Normal,
             message: String::new(),
@@ -156,42 +482,352 @@ impl App {
             coolbits_value: 28,
             should_quit: false,
             pending_mode: None,
+            suggested_profile: None,
+            reboot_pending: None,
             spinner_frame: 0,
             spinner: Spinner::new(),
             gpu_info: None,
-        }
+            gpu_processes: Vec::new(),
+            selected_process_index: 0,
+            services: Vec::new(),
+            persistenced: None,
+            persistence_mode: None,
+            powermizer_max_performance: None,
+            #[cfg(feature = "overclock")]
+            overclock_unlocked: false,
+            #[cfg(feature = "overclock")]
+            overclock: None,
+            kernel_params: Vec::new(),
+            modprobe_entries: Vec::new(),
+            config: Config::default(),
+            on_ac: None,
+            skip_confirmations: false,
+            monochrome: false,
+            log_lines: Vec::new(),
+            log_scroll: 0,
+            popup_scroll: 0,
+            popup_max_scroll: Cell::new(0),
+            log_search: TextInput::new(),
+            log_search_active: false,
+            log_view_height: Cell::new(0),
+            input: None,
+            password_task: None,
+            compact: false,
+            keymap: Keymap::default(),
+            flash: None,
+            last_input: Instant::now(),
+            envycontrol_installed: true,
+            retry: None,
+            about: None,
+            theme_editor: None,
+            help_page: 0,
+            config_problems: Vec::new(),
+            envycontrol_version: None,
+            envycontrol_install: None,
+            progress_lines: Vec::new(),
+            task_output: Vec::new(),
+            phases: Vec::new(),
+            initramfs_tool: None,
+            sandbox: host::sandbox(),
+            immutable: distro::current().immutable,
+            session_type: system::session_type(),
+            display_manager: None,
+            icons: Icons::default(),
+            dgpu_hardware: None,
+            running_games: Vec::new(),
+            login_note: None,
+            applied_args: Vec::new(),
+            detecting: false,
+            dgpu_power: None,
+            conflicts: Vec::new(),
+            providers: None,
+            outputs: Vec::new(),
+            #[cfg(feature = "monitor")]
+            history: History::default(),
+            #[cfg(feature = "monitor")]
+            switches: Vec::new(),
+            #[cfg(feature = "monitor")]
+            boot_time: None,
+            #[cfg(feature = "monitor")]
+            gamemode: None,
+            #[cfg(feature = "monitor")]
+            csv_log_failed: false,
+            summary: Vec::new(),
+            log_path: logging::log_path(),
+            options: Vec::new(),
+            running: None,
+            queued_switch: None,
+        };
+        app.refresh_options();
+        app
     }
 
     pub fn selected_mode(&self) -> GraphicsMode {
-        GraphicsMode::all()[self.selected_mode_index]
+        GraphicsMode::ALL[self.selected_mode_index]
+    }
+
+    pub fn switch_options(&self, mode: GraphicsMode) -> SwitchOptions {
+        SwitchOptions {
+            mode,
+            rtd3_enabled: self.rtd3_enabled,
+            rtd3_level: self.rtd3_level,
+            force_comp: self.force_comp,
+            coolbits_enabled: self.coolbits_enabled,
+            coolbits_value: self.coolbits_value,
+            extra_args: self.config.extra_args.for_mode(mode).to_vec(),
+        }
+    }
+
+    // What confirming the switch would apply.
+    pub fn confirmed_options(&self, mode: GraphicsMode) -> SwitchOptions {
+        match &self.suggested_profile {
+            Some(profile) if profile.mode == mode => SwitchOptions {
+                extra_args: self.config.extra_args.for_mode(mode).to_vec(),
+                ..profile.switch_options()
+            },
+            _ => self.switch_options(mode),
+        }
+    }
+
+    pub fn current_profile(&self) -> Profile {
+        Profile {
+            mode: self.selected_mode(),
+            rtd3: self.rtd3_enabled.then(|| self.rtd3_level.value()),
+            force_comp: self.force_comp,
+            coolbits: self.coolbits_enabled.then_some(self.coolbits_value),
+        }
+    }
+
+    pub fn load_profile(&mut self, profile: &Profile) {
+        let options = profile.switch_options();
+        self.selected_mode_index = GraphicsMode::ALL
+            .iter()
+            .position(|&m| m == options.mode)
+            .unwrap_or(0);
+        self.rtd3_enabled = options.rtd3_enabled;
+        self.rtd3_level = options.rtd3_level;
+        self.force_comp = options.force_comp;
+        self.coolbits_enabled = options.coolbits_enabled;
+        self.coolbits_value = options.coolbits_value;
+        self.refresh_options();
+    }
+
+    // The Options panel rows for the selected mode. Kept in App and rebuilt
+    // only when the mode or an option value changes, not on every frame.
+    pub fn refresh_options(&mut self) {
+        let mode = self.selected_mode();
+        let mut options = match mode {
+            GraphicsMode::Hybrid => vec![
+                OptionItem {
+                    kind: OptionKind::Rtd3,
+                    label: "RTD3 Power Management".to_string(),
+                    description: "Enables Runtime D3 (RTD3) power management for the dGPU. Allows GPU to enter low-power state when idle.",
+                    is_on: self.rtd3_enabled,
+                    is_toggle: true,
+                },
+                OptionItem {
+                    kind: OptionKind::Rtd3Level,
+                    label: format!("RTD3 Level: {}", self.rtd3_level),
+                    description: "Controls RTD3 aggressiveness. Higher levels save more power but may cause latency on GPU wake.",
+                    is_on: false,
+                    is_toggle: self.rtd3_enabled,
+                },
+            ],
+            GraphicsMode::Nvidia => {
+                let mut options = vec![
+                    OptionItem {
+                        kind: OptionKind::ForceComp,
+                        label: "Force Composition Pipeline".to_string(),
+                        description: "Forces full composition pipeline. Fixes screen tearing but may reduce performance slightly.",
+                        is_on: self.force_comp,
+                        is_toggle: true,
+                    },
+                    OptionItem {
+                        kind: OptionKind::Coolbits,
+                        label: format!("Coolbits (value: {})", self.coolbits_value),
+                        description: "Enables advanced GPU features like overclocking, fan control, and voltage adjustment.",
+                        is_on: self.coolbits_enabled,
+                        is_toggle: true,
+                    },
+                ];
+                // A live driver setting, so only offered while running in
+                // nvidia mode.
+                if let (Some(GraphicsMode::Nvidia), Some(max_performance)) =
+                    (self.current_mode, self.powermizer_max_performance)
+                {
+                    options.push(OptionItem {
+                        kind: OptionKind::PowerMizer,
+                        label: format!(
+                            "PowerMizer: {}",
+                            if max_performance {
+                                "Prefer Maximum Performance"
+                            } else {
+                                "Adaptive"
+                            }
+                        ),
+                        description: "Applied immediately with nvidia-settings. Maximum performance keeps clocks high.",
+                        is_on: max_performance,
+                        is_toggle: true,
+                    });
+                }
+                options
+            }
+            GraphicsMode::Integrated => vec![OptionItem {
+                kind: OptionKind::Info,
+                label: "No additional options available".to_string(),
+                description: "Integrated mode uses only the iGPU. The dGPU is powered off to save battery.",
+                is_on: false,
+                is_toggle: false,
+            }],
+        };
+
+        let extra_args = self.config.extra_args.for_mode(mode);
+        if !extra_args.is_empty() {
+            options.push(OptionItem {
+                kind: OptionKind::Info,
+                label: format!("Extra Arguments: {}", extra_args.join(" ")),
+                description:
+                    "Advanced: appended to the envycontrol command from extra_args in config.toml.",
+                is_on: false,
+                is_toggle: false,
+            });
+        }
+        self.options = options;
+        self.selected_option_index = self
+            .selected_option_index
+            .min(self.options.len().saturating_sub(1));
+        if !self.has_options() {
+            self.active_panel = AppPanel::ModeSelection;
+        }
+    }
+
+    // Integrated mode only has informational rows, so there is nothing to
+    // focus in the Options panel.
+    pub fn has_options(&self) -> bool {
+        self.options
+            .iter()
+            .any(|option| option.kind != OptionKind::Info)
     }
 
     pub fn next_mode(&mut self) {
-        let modes = GraphicsMode::all();
-        self.selected_mode_index = (self.selected_mode_index + 1) % modes.len();
+        self.selected_mode_index = (self.selected_mode_index + 1) % GraphicsMode::ALL.len();
+        self.refresh_options();
     }
 
     pub fn previous_mode(&mut self) {
-        let modes = GraphicsMode::all();
         self.selected_mode_index = if self.selected_mode_index == 0 {
-            modes.len() - 1
+            GraphicsMode::ALL.len() - 1
         } else {
             self.selected_mode_index - 1
         };
+        self.refresh_options();
     }
 
     pub fn next_option(&mut self) {
-        self.selected_option_index = (self.selected_option_index + 1) % 4;
+        self.selected_option_index = (self.selected_option_index + 1) % self.options.len().max(1);
     }
 
     pub fn previous_option(&mut self) {
         self.selected_option_index = if self.selected_option_index == 0 {
-            3
+            self.options.len().saturating_sub(1)
         } else {
             self.selected_option_index - 1
         };
     }
 
+    pub fn selected_option(&self) -> Option<OptionKind> {
+        self.options
+            .get(self.selected_option_index)
+            .map(|option| option.kind)
+    }
+
+    pub fn next_tab(&mut self) {
+        let tabs = AppTab::ALL;
+        let idx = tabs.iter().position(|&t| t == self.active_tab).unwrap_or(0);
+        self.active_tab = tabs[(idx + 1) % tabs.len()];
+    }
+
+    pub fn previous_tab(&mut self) {
+        let tabs = AppTab::ALL;
+        let idx = tabs.iter().position(|&t| t == self.active_tab).unwrap_or(0);
+        self.active_tab = tabs[(idx + tabs.len() - 1) % tabs.len()];
+    }
+
+    pub fn suspend_services_recommended(&self) -> bool {
+        self.current_mode == Some(GraphicsMode::Hybrid) || self.rtd3_enabled
+    }
+
+    pub fn disabled_suspend_services(&self) -> Vec<&'static str> {
+        self.services
+            .iter()
+            .filter(|s| s.state == ServiceState::Disabled)
+            .map(|s| s.name)
+            .collect()
+    }
+
+    pub fn persistence_conflicts_with_rtd3(&self) -> bool {
+        let persistence_on = self.persistence_mode == Some(true)
+            || self.persistenced.as_ref().is_some_and(|s| s.active);
+        persistence_on && self.suspend_services_recommended()
+    }
+
+    pub fn set_log_lines(&mut self, lines: Vec<String>) {
+        // Keep the viewport anchored to the same lines when following older entries.
+        if self.log_scroll > 0 {
+            self.log_scroll += lines.len().saturating_sub(self.log_lines.len());
+        }
+        self.log_lines = lines;
+        self.log_scroll = self.log_scroll.min(self.max_log_scroll());
+    }
+
+    fn max_log_scroll(&self) -> usize {
+        self.log_lines
+            .len()
+            .saturating_sub(self.log_view_height.get().max(1))
+    }
+
+    pub fn scroll_log_up(&mut self, amount: usize) {
+        self.log_scroll = (self.log_scroll + amount).min(self.max_log_scroll());
+    }
+
+    pub fn scroll_log_down(&mut self, amount: usize) {
+        self.log_scroll = self.log_scroll.saturating_sub(amount);
+    }
+
+    pub fn scroll_log_to_top(&mut self) {
+        self.log_scroll = self.max_log_scroll();
+    }
+
+    pub fn visible_log_range(&self) -> std::ops::Range<usize> {
+        let end = self.log_lines.len().saturating_sub(self.log_scroll);
+        let start = end.saturating_sub(self.log_view_height.get());
+        start..end
+    }
+
+    pub fn log_line_matches(&self, line: &str) -> bool {
+        !self.log_search.is_empty()
+            && line
+                .to_lowercase()
+                .contains(&self.log_search.value().to_lowercase())
+    }
+
+    pub fn jump_to_log_match(&mut self, older: bool) {
+        if self.log_search.is_empty() || self.log_lines.is_empty() {
+            return;
+        }
+        let bottom = self.log_lines.len() - 1 - self.log_scroll.min(self.log_lines.len() - 1);
+        let found = if older {
+            (0..bottom)
+                .rev()
+                .find(|&i| self.log_line_matches(&self.log_lines[i]))
+        } else {
+            (bottom + 1..self.log_lines.len()).find(|&i| self.log_line_matches(&self.log_lines[i]))
+        };
+        if let Some(index) = found {
+            self.log_scroll = (self.log_lines.len() - 1 - index).min(self.max_log_scroll());
+        }
+    }
+
     pub fn toggle_panel(&mut self) {
         self.active_panel = match self.active_panel {
             AppPanel::ModeSelection => AppPanel::Options,
@@ -200,36 +836,387 @@ impl App {
     }
 
     pub fn toggle_current_option(&mut self) {
-        match self.selected_option_index {
-            0 => self.rtd3_enabled = !self.rtd3_enabled,
-            1 => {
-                let levels = Rtd3Level::all();
+        let toggleable = self
+            .options
+            .get(self.selected_option_index)
+            .is_some_and(|option| option.is_toggle);
+        if !toggleable {
+            return;
+        }
+        match self.selected_option() {
+            Some(OptionKind::Rtd3) => self.rtd3_enabled = !self.rtd3_enabled,
+            Some(OptionKind::Rtd3Level) => {
+                let levels = Rtd3Level::ALL;
                 let current_idx = levels
                     .iter()
                     .position(|&l| l == self.rtd3_level)
                     .unwrap_or(0);
                 self.rtd3_level = levels[(current_idx + 1) % levels.len()];
             }
-            2 => self.force_comp = !self.force_comp,
-            3 => self.coolbits_enabled = !self.coolbits_enabled,
-            _ => {}
+            Some(OptionKind::ForceComp) => self.force_comp = !self.force_comp,
+            Some(OptionKind::Coolbits) => self.coolbits_enabled = !self.coolbits_enabled,
+            // Applied through nvidia-settings by the caller.
+            Some(OptionKind::PowerMizer | OptionKind::Info) | None => {}
+        }
+        self.refresh_options();
+    }
+
+    pub fn next_process(&mut self) {
+        if !self.gpu_processes.is_empty() {
+            self.selected_process_index =
+                (self.selected_process_index + 1) % self.gpu_processes.len();
+        }
+    }
+
+    pub fn previous_process(&mut self) {
+        if !self.gpu_processes.is_empty() {
+            self.selected_process_index = if self.selected_process_index == 0 {
+                self.gpu_processes.len() - 1
+            } else {
+                self.selected_process_index - 1
+            };
+        }
+    }
+
+    fn set_gpu_processes(&mut self, processes: Vec<GpuProcess>) {
+        self.gpu_processes = processes;
+        if self.selected_process_index >= self.gpu_processes.len() {
+            self.selected_process_index = self.gpu_processes.len().saturating_sub(1);
+        }
+    }
+
+    pub fn confirm_switch(&mut self, mode: GraphicsMode) {
+        self.pending_mode = Some(mode);
+        self.gpu_processes.clear();
+        self.state = AppState::ConfirmingSwitch;
+        let options = self.confirmed_options(mode);
+        // Re-running the switch with the same options changes nothing but
+        // still asks for a reboot.
+        self.message = if self.reboot_pending.or(self.current_mode) == Some(mode)
+            && self.applied_args == options.effective_option_args()
+        {
+            format!("Already in {} mode — re-apply anyway? (y/n)", mode)
+        } else {
+            format!("Switch to {} mode? (y/n)", mode)
+        };
+        self.popup_scroll = 0;
+        for detail in options.details() {
+            self.message.push_str("\n• ");
+            self.message.push_str(&detail);
+        }
+        if !self.running_games.is_empty() {
+            self.message.push_str(&format!(
+                "\n{} Still running: {}. The switch and the reboot close {}, so answer n to save first.",
+                self.icons.warning,
+                self.running_games.join(", "),
+                if self.running_games.len() == 1 { "it" } else { "them" }
+            ));
+        }
+        if mode == GraphicsMode::Nvidia {
+            if let Some(note) = &self.login_note {
+                self.message.push('\n');
+                self.message.push_str(note);
+            }
         }
     }
 
+    pub fn warn_gpu_processes(&mut self, mode: GraphicsMode, processes: Vec<GpuProcess>) {
+        self.pending_mode = Some(mode);
+        self.set_gpu_processes(processes);
+        self.state = AppState::ConfirmingProcesses;
+        self.message = format!(
+            "{} process(es) are using the dGPU and may block the switch.",
+            self.gpu_processes.len()
+        );
+    }
+
+    pub fn editable_option(&self) -> Option<InputPurpose> {
+        match self.selected_option()? {
+            OptionKind::Rtd3Level => Some(InputPurpose::Rtd3Level),
+            OptionKind::Coolbits => Some(InputPurpose::CoolbitsValue),
+            _ => None,
+        }
+    }
+
+    // Whether an action applies in the current context. Unavailable actions are
+    // ignored and left out of the footer.
+    pub fn action_available(&self, action: Action) -> bool {
+        match action {
+            Action::Toggle => {
+                self.active_panel == AppPanel::Options
+                    && self
+                        .options
+                        .get(self.selected_option_index)
+                        .is_some_and(|option| option.is_toggle)
+            }
+            Action::SwitchPanel => self.active_panel == AppPanel::Options || self.has_options(),
+            // Enter explains the selected option instead of applying while
+            // the Options panel is focused.
+            Action::OptionDetails => {
+                self.active_panel == AppPanel::Options
+                    && self
+                        .selected_option()
+                        .is_some_and(|kind| kind != OptionKind::Info)
+            }
+            Action::Apply => {
+                self.envycontrol_installed && !self.action_available(Action::OptionDetails)
+            }
+            Action::Edit => {
+                self.active_panel == AppPanel::Options && self.editable_option().is_some()
+            }
+            // There are no colors to edit in monochrome.
+            Action::EditTheme => !self.monochrome,
+            #[cfg(feature = "overclock")]
+            Action::Overclock => {
+                self.current_mode == Some(GraphicsMode::Nvidia) && self.overclock_unlocked
+            }
+            #[cfg(not(feature = "overclock"))]
+            Action::Overclock => false,
+            // Package manager installs are updated the package manager's way.
+            Action::UpdateEnvycontrol => {
+                self.envycontrol_outdated()
+                    && self
+                        .envycontrol_install
+                        .as_ref()
+                        .is_none_or(|method| method.update_command().is_some())
+            }
+            Action::Reset | Action::ExportCommand => {
+                self.active_panel == AppPanel::ModeSelection && self.envycontrol_installed
+            }
+            Action::SaveProfile | Action::Compact => self.active_panel == AppPanel::ModeSelection,
+            Action::EnableServices => !self.disabled_suspend_services().is_empty(),
+            Action::TogglePersistenceMode => self.persistence_mode.is_some(),
+            Action::NextMatch | Action::PreviousMatch => !self.log_search.is_empty(),
+            _ => true,
+        }
+    }
+
+    // After a successful switch: what envycontrol changed and what the mode
+    // will be like, above the reboot question.
+    pub fn show_switch_summary(
+        &mut self,
+        mode: GraphicsMode,
+        changes: &SwitchChanges,
+        problems: &[String],
+    ) {
+        let options = self.switch_options(mode);
+        self.state = AppState::ConfirmingReboot;
+        self.popup_scroll = 0;
+        self.message = if problems.is_empty() {
+            format!("Switched to {} mode. Reboot now?", mode)
+        } else {
+            format!(
+                "Switched to {} mode, but the files don't look right. Reboot anyway?",
+                mode
+            )
+        };
+        let mut section = |title: &str, lines: Vec<String>| {
+            if lines.is_empty() {
+                return;
+            }
+            self.message.push_str(&format!("\n{}", title));
+            for line in lines {
+                self.message.push_str("\n• ");
+                self.message.push_str(&line);
+            }
+        };
+        if !problems.is_empty() {
+            let mut lines = problems.to_vec();
+            lines.push("Switch again or run envy-tui doctor before rebooting.".to_string());
+            section(&format!("{} Not as expected:", self.icons.warning), lines);
+        }
+        if changes.is_empty() {
+            // Older envycontrol releases don't list the files they write.
+            section(
+                "Files (expected, envycontrol didn't list them):",
+                options
+                    .written_files(self.display_manager.as_deref())
+                    .iter()
+                    .map(|path| path.to_string())
+                    .collect(),
+            );
+        } else {
+            let files = [
+                ("created", &changes.created),
+                ("updated", &changes.updated),
+                ("removed", &changes.removed),
+            ]
+            .into_iter()
+            .flat_map(|(action, paths)| {
+                paths
+                    .iter()
+                    .map(move |path| format!("{} ({})", path, action))
+            })
+            .collect();
+            section("Files:", files);
+        }
+        section("Services:", changes.services.clone());
+        section("After the reboot:", options.expectations());
+    }
+
+    pub fn open_input(&mut self, purpose: InputPurpose, title: &str, widget: InputWidget) {
+        self.input = Some(InputPrompt {
+            purpose,
+            title: title.to_string(),
+            widget,
+            error: None,
+        });
+        self.state = AppState::Input;
+    }
+
+    pub fn close_input(&mut self) {
+        self.input = None;
+        self.clear_message();
+    }
+
+    // Printed to stdout after the TUI closes, since the alternate screen
+    // hides everything that happened.
+    pub fn record(&mut self, entry: String) {
+        self.summary.push(entry);
+    }
+
+    // Brief note shown in the status line without interrupting with a popup.
+    pub fn set_flash(&mut self, msg: &str) {
+        self.flash = Some((msg.to_string(), Instant::now()));
+    }
+
+    // A tip for the footer once nothing has been pressed for a while, changing
+    // every few seconds; keys in it follow the user's remaps.
+    pub fn tip(&self) -> Option<String> {
+        const IDLE_BEFORE_TIPS: Duration = Duration::from_secs(15);
+        const TIP_DURATION: u64 = 10;
+
+        let idle = self.last_input.elapsed();
+        if !self.config.ui.tips || self.state != AppState::Normal || idle < IDLE_BEFORE_TIPS {
+            return None;
+        }
+        let key = |action: Action| {
+            self.keymap
+                .keys(action)
+                .first()
+                .map(|&code| keymap::key_display(code))
+        };
+        let tips: Vec<String> = [
+            Some("Hybrid + RTD3 gives the best battery life".to_string()),
+            key(Action::Compact).map(|k| format!("Press {} for the compact layout", k)),
+            key(Action::SaveProfile)
+                .map(|k| format!("Press {} to save the selection as a profile", k)),
+            Some(
+                "[power] in config.toml applies a profile when the charger is plugged in"
+                    .to_string(),
+            ),
+            #[cfg(feature = "monitor")]
+            Some("The Monitor tab charts dGPU temperature, load and clocks".to_string()),
+            Some("envy-tui doctor checks the driver, initramfs and display manager".to_string()),
+            key(Action::About)
+                .map(|k| format!("Press {} for details to include in bug reports", k)),
+            key(Action::Help)
+                .map(|k| format!("Press {} to read about RTD3, Coolbits and PRIME offline", k)),
+            Some("envy-tui switch nvidia --yes works from scripts".to_string()),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        let index = (idle - IDLE_BEFORE_TIPS).as_secs() / TIP_DURATION;
+        tips.get(index as usize % tips.len()).cloned()
+    }
+
+    pub fn active_flash(&self) -> Option<&str> {
+        self.flash
+            .as_ref()
+            .filter(|(_, at)| at.elapsed() < FLASH_DURATION)
+            .map(|(msg, _)| msg.as_str())
+    }
+
     pub fn set_success(&mut self, msg: &str) {
         self.state = AppState::Success;
         self.message = msg.to_string();
+        self.popup_scroll = 0;
     }
 
     pub fn set_error(&mut self, msg: &str) {
         self.state = AppState::Error;
         self.message = msg.to_string();
+        self.popup_scroll = 0;
+    }
+
+    pub fn set_retryable_error(&mut self, msg: &str, retry: Retry) {
+        self.set_error(msg);
+        self.retry = Some(retry);
     }
 
     pub fn set_loading(&mut self, msg: &str) {
         self.state = AppState::Loading;
         self.message = msg.to_string();
         self.spinner_frame = 0;
+        self.progress_lines.clear();
+        self.task_output.clear();
+        self.phases.clear();
+    }
+
+    pub fn push_progress_line(&mut self, line: String) {
+        const MAX_PROGRESS_LINES: usize = 8;
+        if let Some(phase) = Phase::from_line(&line) {
+            if !self.phases.contains(&phase) {
+                self.phases.push(phase);
+            }
+        }
+        self.task_output.push(line.clone());
+        self.progress_lines.push(line);
+        if self.progress_lines.len() > MAX_PROGRESS_LINES {
+            self.progress_lines.remove(0);
+        }
+    }
+
+    // Which displays are plugged in changes what the current mode means for them.
+    pub fn display_warning(&self) -> Option<String> {
+        let external: Vec<&Output> = self.outputs.iter().filter(|o| o.external).collect();
+        match self.current_mode? {
+            GraphicsMode::Hybrid => {
+                let on_nvidia: Vec<&str> = external
+                    .iter()
+                    .filter(|o| o.nvidia)
+                    .map(|o| o.connector.as_str())
+                    .collect();
+                (!on_nvidia.is_empty()).then(|| {
+                    format!(
+                        "{} is driven by the NVIDIA GPU and keeps it awake in hybrid mode",
+                        on_nvidia.join(", ")
+                    )
+                })
+            }
+            GraphicsMode::Integrated if external.is_empty() => None,
+            GraphicsMode::Integrated => Some(
+                "External display connected: ports wired to the NVIDIA GPU stay dark in integrated mode"
+                    .to_string(),
+            ),
+            GraphicsMode::Nvidia => None,
+        }
+    }
+
+    pub fn envycontrol_outdated(&self) -> bool {
+        self.envycontrol_version
+            .as_deref()
+            .is_some_and(envycontrol::is_outdated)
+    }
+
+    pub fn frame_interval(&self) -> Duration {
+        self.config.ui.frame_interval(self.on_ac.unwrap_or(true))
+    }
+
+    // A static indicator replaces the spinner with reduced_motion set, for
+    // accessibility and terminal recordings.
+    pub fn spinner_icon(&self) -> &str {
+        if self.config.ui.reduced_motion {
+            "…"
+        } else {
+            self.spinner.frame(self.spinner_frame)
+        }
+    }
+
+    pub fn animating(&self) -> bool {
+        self.state == AppState::Loading && !self.config.ui.reduced_motion
     }
 
     pub fn tick_spinner(&mut self) {
@@ -239,5 +1226,7 @@ impl App {
     pub fn clear_message(&mut self) {
         self.state = AppState::Normal;
         self.message.clear();
+        self.popup_scroll = 0;
+        self.retry = None;
     }
 }
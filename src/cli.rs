@@ -0,0 +1,665 @@
+use crate::app::{GraphicsMode, Rtd3Level};
+use crate::config::{self, Profile};
+use crate::distro;
+use crate::envycontrol::{self, Phase, SwitchOptions};
+use crate::host;
+use crate::keymap::Keymap;
+use crate::logging;
+use crate::rules;
+use crate::state;
+use crate::system;
+use crate::theme;
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_json::json;
+use std::cell::Cell;
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+pub fn apply_login(args: &[String]) -> Result<()> {
+    let mut yes = false;
+    let mut output = Output::Text;
+    for arg in args {
+        match arg.as_str() {
+            "--install-unit" => return install_login_unit(),
+            "--install-autostart" => return install_login_autostart(),
+            "--yes" | "-y" => yes = true,
+            "--quiet" | "-q" => output = Output::Quiet,
+            "--json" => output = Output::Json,
+            other => return Err(anyhow!("Unknown option for apply-login: {}", other)),
+        }
+    }
+    let outcome = finish(output, apply_login_profile(yes))?;
+    report(output, &outcome);
+    Ok(())
+}
+
+fn apply_login_profile(yes: bool) -> Result<Outcome> {
+    let config = config::load()?;
+    let environment = rules::detect_environment();
+
+    let Some((name, profile)) = rules::matching_login_profile(&config, &environment) else {
+        return Ok(Outcome::new(
+            "no-match",
+            format!(
+                "No login rule matches (AC: {}, docked: {}).",
+                environment.on_ac, environment.docked
+            ),
+        ));
+    };
+
+    if envycontrol::query_mode()? == Some(profile.mode) {
+        return Ok(Outcome {
+            mode: Some(profile.mode),
+            profile: Some(name.to_string()),
+            ..Outcome::new(
+                &format!("unchanged {}", profile.mode),
+                format!(
+                    "Already in {} mode, profile '{}' needs no switch.",
+                    profile.mode, name
+                ),
+            )
+        });
+    }
+
+    let prompt = format!("Apply profile '{}' ({} mode)?", name, profile.mode);
+    let skip_confirmation = yes || config.skip_confirmations || config.login.auto_apply;
+    if !skip_confirmation && !confirm_with_notification(&prompt)? {
+        return Ok(Outcome {
+            mode: Some(profile.mode),
+            profile: Some(name.to_string()),
+            ..Outcome::new("skipped", format!("Skipped profile '{}'.", name))
+        });
+    }
+
+    // Only now, so a login that switches nothing never asks for a password.
+    ensure_switchable()?;
+    let options = SwitchOptions {
+        extra_args: config.extra_args.for_mode(profile.mode).to_vec(),
+        ..profile.switch_options()
+    };
+    Ok(Outcome {
+        profile: Some(name.to_string()),
+        ..run_switch(options)?
+    })
+}
+
+// Read-only: prints what `doctor` would look at, without judging it, so it is
+// safe to run from scripts or a MOTD.
+pub fn status(args: &[String]) -> Result<()> {
+    let json = match args {
+        [] => false,
+        [flag] if flag == "--json" => true,
+        _ => return Err(anyhow!("Usage: envy-tui status [--json]")),
+    };
+
+    let installed = envycontrol::is_envycontrol_installed();
+    let version = installed.then(envycontrol::version).flatten();
+    let install = installed.then(envycontrol::install_method).flatten();
+    let mode = if installed {
+        envycontrol::query_mode()
+    } else {
+        Ok(None)
+    };
+    let driver = system::nvidia_driver_version();
+    let driver_loaded = Path::new("/sys/module/nvidia").exists();
+    let session_type = system::session_type();
+    let desktop = system::desktop();
+    let display_manager = system::display_manager();
+    let dgpu = system::dgpu_power();
+    let pending = state::load_pending_switch()
+        .filter(|pending| !pending.rebooted_since())
+        .map(|pending| pending.mode);
+
+    if json {
+        println!(
+            "{}",
+            json!({
+                "mode": mode.as_ref().ok().copied().flatten(),
+                "mode_error": mode.as_ref().err().map(|e| e.to_string()),
+                "backend": {
+                    "name": "envycontrol",
+                    "installed": installed,
+                    "version": version,
+                    "installed_from": install.as_ref().map(|m| m.label()),
+                },
+                "driver": {
+                    "version": driver,
+                    "loaded": driver_loaded,
+                },
+                "session": {
+                    "type": session_type,
+                    "desktop": desktop,
+                },
+                "display_manager": display_manager,
+                "dgpu": dgpu.as_ref().map(|power| json!({
+                    "status": power.status.as_str(),
+                    "suspended_percent": power.suspended_percent(),
+                })),
+                "pending_reboot": pending,
+            })
+        );
+        return Ok(());
+    }
+
+    let mode = match mode {
+        Ok(Some(mode)) => mode.to_string(),
+        Ok(None) if !installed => "unknown (envycontrol not installed)".to_string(),
+        Ok(None) => "unknown".to_string(),
+        Err(e) => format!("unknown ({})", e),
+    };
+    let mut backend = match (installed, version) {
+        (false, _) => "envycontrol (not installed)".to_string(),
+        (true, Some(version)) => format!("envycontrol {}", version),
+        (true, None) => "envycontrol".to_string(),
+    };
+    if let Some(method) = install {
+        backend.push_str(&format!(" (from {})", method.label()));
+    }
+    let driver = match driver {
+        Some(version) if driver_loaded => format!("{} (loaded)", version),
+        Some(version) => format!("{} (not loaded)", version),
+        None => "not installed".to_string(),
+    };
+    let session = match (session_type, desktop) {
+        (Some(session), Some(desktop)) => format!("{} ({})", session, desktop),
+        (Some(session), None) => session,
+        (None, Some(desktop)) => desktop,
+        (None, None) => "unknown".to_string(),
+    };
+    let dgpu = match dgpu {
+        Some(power) => power.badge().trim_start_matches("dGPU: ").to_string(),
+        None => "not found".to_string(),
+    };
+    let pending = match pending {
+        Some(mode) => format!("yes, switching to {}", mode),
+        None => "no".to_string(),
+    };
+
+    println!("envy-tui status");
+    println!();
+    for (label, value) in [
+        ("Mode", mode),
+        ("Backend", backend),
+        ("NVIDIA driver", driver),
+        ("Session", session),
+        (
+            "Display manager",
+            display_manager.unwrap_or_else(|| "unknown".to_string()),
+        ),
+        ("dGPU power", dgpu),
+        ("Pending reboot", pending),
+    ] {
+        println!("{:<16} {}", label, value);
+    }
+    Ok(())
+}
+
+pub fn cheat_sheet(args: &[String]) -> Result<()> {
+    let path = match args {
+        [] => PathBuf::from("envy-tui-keys.md"),
+        [path] => PathBuf::from(path),
+        _ => return Err(anyhow!("Usage: envy-tui cheatsheet [FILE]")),
+    };
+    let config = config::load()?;
+    let keymap = Keymap::from_config(&config.keys)?;
+    let markdown = path.extension().is_some_and(|ext| ext == "md");
+    fs::write(&path, keymap.cheat_sheet(markdown))?;
+    println!("Wrote the key bindings to {}", path.display());
+    Ok(())
+}
+
+pub fn config(args: &[String]) -> Result<()> {
+    let force = match args {
+        [command] if command == "path" => return config_paths(),
+        [command] if command == "init" => false,
+        [command, flag] if command == "init" && flag == "--force" => true,
+        _ => {
+            return Err(anyhow!(
+                "Usage: envy-tui config init [--force] | config path"
+            ))
+        }
+    };
+    let files = [
+        (config::config_path(), config::DEFAULT_CONFIG),
+        (theme::theme_path(), theme::DEFAULT_THEME),
+    ];
+    for (path, _) in &files {
+        match path {
+            None => return Err(anyhow!("Could not determine config directory")),
+            Some(path) if path.exists() && !force => {
+                return Err(anyhow!(
+                    "{} already exists; use --force to overwrite it",
+                    path.display()
+                ))
+            }
+            Some(_) => {}
+        }
+    }
+    for (path, contents) in files
+        .iter()
+        .filter_map(|(path, c)| Some((path.as_ref()?, c)))
+    {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, contents)?;
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+// Where envy-tui reads and writes its files, whether they exist yet or not.
+fn config_paths() -> Result<()> {
+    let unknown = || "unknown".to_string();
+    let show = |path: Option<PathBuf>| path.map_or_else(unknown, |p| p.display().to_string());
+    for (label, path) in [
+        ("config", config::config_path()),
+        ("theme", theme::theme_path()),
+        ("state", state::state_dir()),
+        ("log", logging::log_path()),
+    ] {
+        println!("{:<7} {}", label, show(path));
+    }
+    Ok(())
+}
+
+fn ensure_switchable() -> Result<()> {
+    if let Some(immutable) = distro::current().immutable {
+        return Err(anyhow!(immutable.explanation()));
+    }
+    // Without pkexec the switch goes through sudo, which can ask here.
+    if system::needs_password() && !host::command("sudo").arg("-v").status()?.success() {
+        return Err(anyhow!("sudo authentication failed"));
+    }
+    Ok(())
+}
+
+pub fn switch(args: &[String]) -> Result<()> {
+    let mut mode: Option<GraphicsMode> = None;
+    let mut rtd3 = None;
+    let mut force_comp = false;
+    let mut coolbits = None;
+    let mut yes = false;
+    let mut reboot = false;
+    let mut no_reboot = false;
+    let mut output = Output::Text;
+    let mut dry_run = false;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--rtd3" => {
+                let level: u8 = parse_value(iter.next(), "--rtd3")?;
+                Rtd3Level::from_value(level)
+                    .ok_or_else(|| anyhow!("--rtd3 expects a level between 0 and 3"))?;
+                rtd3 = Some(level);
+            }
+            "--force-comp" => force_comp = true,
+            "--coolbits" => coolbits = Some(parse_value(iter.next(), "--coolbits")?),
+            "--yes" | "-y" => yes = true,
+            "--reboot" => reboot = true,
+            "--no-reboot" => no_reboot = true,
+            "--quiet" | "-q" => output = Output::Quiet,
+            "--json" => output = Output::Json,
+            "--dry-run" => dry_run = true,
+            other if mode.is_none() && !other.starts_with('-') => {
+                mode = Some(other.parse().map_err(|e: String| anyhow!(e))?);
+            }
+            other => return Err(anyhow!("Unexpected argument for switch: {}", other)),
+        }
+    }
+
+    let mode = mode.ok_or_else(|| anyhow!("Usage: envy-tui switch <integrated|hybrid|nvidia>"))?;
+    if reboot && no_reboot {
+        return Err(anyhow!("--reboot and --no-reboot can't be combined"));
+    }
+    let profile = Profile {
+        mode,
+        rtd3,
+        force_comp,
+        coolbits,
+    };
+    let config = config::load()?;
+    let yes = yes || config.skip_confirmations;
+    let options = SwitchOptions {
+        extra_args: config.extra_args.for_mode(mode).to_vec(),
+        ..profile.switch_options()
+    };
+
+    let outcome = if dry_run {
+        Ok(dry_run_outcome(options))
+    } else {
+        ensure_switchable().and_then(|()| {
+            let games = if yes {
+                Vec::new()
+            } else {
+                system::running_games()
+            };
+            if !games.is_empty() {
+                eprintln!(
+                    "Still running: {}. The switch and the reboot close them.",
+                    games.join(", ")
+                );
+            }
+            if !yes && !confirm(&format!("Switch to {} mode?", mode))? {
+                return Ok(Outcome {
+                    mode: Some(mode),
+                    ..Outcome::new("aborted", "Aborted.".to_string())
+                });
+            }
+            if output == Output::Text {
+                println!("Switching to {} mode...", mode);
+            }
+            run_switch(options)
+        })
+    };
+    let outcome = finish(output, outcome)?;
+    report(output, &outcome);
+
+    // --yes only answers the switch question; rebooting unasked takes --reboot.
+    if outcome.reboot_required && (reboot || (!no_reboot && !yes && confirm("Reboot now?")?)) {
+        envycontrol::reboot()?;
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Output {
+    Text,
+    // Only a short result such as "switched nvidia", for scripts and systemd
+    // units.
+    Quiet,
+    // One JSON object, for configuration management tools.
+    Json,
+}
+
+// What switch or apply-login did.
+struct Outcome {
+    // The --quiet line; its first word is the JSON result.
+    result: String,
+    message: String,
+    mode: Option<GraphicsMode>,
+    profile: Option<String>,
+    options: Option<SwitchOptions>,
+    reboot_required: bool,
+    warnings: Vec<String>,
+    // Generated files a dry run would write or remove, as (action, path).
+    files: Vec<(&'static str, &'static str)>,
+}
+
+impl Outcome {
+    fn new(result: &str, message: String) -> Self {
+        Outcome {
+            result: result.to_string(),
+            message,
+            mode: None,
+            profile: None,
+            options: None,
+            reboot_required: false,
+            warnings: Vec::new(),
+            files: Vec::new(),
+        }
+    }
+
+    fn command(&self) -> Option<String> {
+        self.options
+            .as_ref()
+            .map(|options| format!("sudo {}", options.command_line()))
+    }
+
+    fn to_json(&self) -> String {
+        let options = self.options.as_ref().map(|options| {
+            let hybrid = options.mode == GraphicsMode::Hybrid;
+            let nvidia = options.mode == GraphicsMode::Nvidia;
+            JsonOptions {
+                rtd3: (hybrid && options.rtd3_enabled).then(|| options.rtd3_level.value()),
+                force_comp: nvidia && options.force_comp,
+                coolbits: (nvidia && options.coolbits_enabled).then_some(options.coolbits_value),
+                extra_args: &options.extra_args,
+            }
+        });
+        let outcome = JsonOutcome {
+            result: self.result.split(' ').next().unwrap_or_default(),
+            mode: self.mode,
+            profile: self.profile.as_deref(),
+            options,
+            command: self.command(),
+            backend: JsonBackend {
+                name: "envycontrol",
+                version: envycontrol::version(),
+            },
+            reboot_required: self.reboot_required,
+            warnings: &self.warnings,
+            files: self
+                .files
+                .iter()
+                .map(|&(action, path)| JsonFile { action, path })
+                .collect(),
+            message: &self.message,
+        };
+        serde_json::to_string(&outcome).unwrap_or_default()
+    }
+}
+
+// The --json form of an Outcome.
+#[derive(Serialize)]
+struct JsonOutcome<'a> {
+    result: &'a str,
+    mode: Option<GraphicsMode>,
+    profile: Option<&'a str>,
+    options: Option<JsonOptions<'a>>,
+    command: Option<String>,
+    backend: JsonBackend,
+    reboot_required: bool,
+    warnings: &'a [String],
+    files: Vec<JsonFile>,
+    message: &'a str,
+}
+
+// Only the options that apply to the mode are set.
+#[derive(Serialize)]
+struct JsonOptions<'a> {
+    rtd3: Option<u8>,
+    force_comp: bool,
+    coolbits: Option<u8>,
+    extra_args: &'a [String],
+}
+
+#[derive(Serialize)]
+struct JsonBackend {
+    name: &'static str,
+    version: Option<String>,
+}
+
+#[derive(Serialize)]
+struct JsonFile {
+    action: &'static str,
+    path: &'static str,
+}
+
+// Failures are results too in JSON mode, so stdout always holds one object.
+fn finish(output: Output, outcome: Result<Outcome>) -> Result<Outcome> {
+    if let (Output::Json, Err(e)) = (output, &outcome) {
+        println!("{}", Outcome::new("error", e.to_string()).to_json());
+    }
+    outcome
+}
+
+fn report(output: Output, outcome: &Outcome) {
+    match output {
+        Output::Json => println!("{}", outcome.to_json()),
+        Output::Quiet if outcome.result == "dry-run" => {
+            println!("{}", outcome.command().unwrap_or_default());
+        }
+        Output::Quiet => println!("{}", outcome.result),
+        Output::Text => {
+            println!("{}", outcome.message);
+            if outcome.result == "dry-run" {
+                println!("Command: {}", outcome.command().unwrap_or_default());
+                if outcome.files.is_empty() {
+                    println!("Files: none");
+                } else {
+                    println!("Files:");
+                    for (action, path) in &outcome.files {
+                        println!("  {:<7} {}", action, path);
+                    }
+                }
+            }
+            for warning in &outcome.warnings {
+                println!("Warning: {}", warning);
+            }
+        }
+    }
+}
+
+fn run_switch(options: SwitchOptions) -> Result<Outcome> {
+    let mode = options.mode;
+    let flags = options.summary();
+    let rebuilt_initramfs = Cell::new(false);
+    let message = envycontrol::switch_mode(options.clone(), &|line| {
+        if Phase::from_line(&line) == Some(Phase::Initramfs) {
+            rebuilt_initramfs.set(true);
+        }
+    })?;
+    let _ = state::record_switch(mode, &flags);
+
+    let mut warnings = Vec::new();
+    if mode != GraphicsMode::Integrated && system::nvidia_driver_version().is_none() {
+        warnings.push(format!(
+            "The NVIDIA driver is not installed. {}",
+            distro::current().install_driver()
+        ));
+    }
+    if !rebuilt_initramfs.get() {
+        if let Some(tool) = system::initramfs_tool() {
+            warnings.push(format!(
+                "The initramfs was not rebuilt; run `{}` before rebooting.",
+                tool.command_line()
+            ));
+        }
+    }
+    warnings.extend(distro::current().reboot_hint(mode).map(str::to_string));
+
+    Ok(Outcome {
+        mode: Some(mode),
+        options: Some(options),
+        reboot_required: true,
+        warnings,
+        ..Outcome::new(&format!("switched {}", mode), message)
+    })
+}
+
+// What a switch would do, without running anything as root.
+fn dry_run_outcome(options: SwitchOptions) -> Outcome {
+    let written = options.written_files(system::display_manager().as_deref());
+    let files = envycontrol::GENERATED_FILES
+        .iter()
+        .filter_map(|&path| {
+            if written.contains(&path) {
+                Some(("write", path))
+            } else if Path::new(path).exists() {
+                Some(("remove", path))
+            } else {
+                None
+            }
+        })
+        .collect();
+    Outcome {
+        mode: Some(options.mode),
+        options: Some(options),
+        files,
+        ..Outcome::new("dry-run", "Dry run, nothing was changed.".to_string())
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(value: Option<&String>, flag: &str) -> Result<T> {
+    value
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| anyhow!("{} expects a numeric value", flag))
+}
+
+fn confirm(prompt: &str) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        return Ok(false);
+    }
+
+    // On stderr, so stdout only carries the result.
+    eprint!("{} [y/N] ", prompt);
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+fn confirm_with_notification(prompt: &str) -> Result<bool> {
+    if io::stdin().is_terminal() {
+        return confirm(prompt);
+    }
+
+    // Without a terminal (autostart, systemd), ask through a desktop notification.
+    let output = host::command("notify-send")
+        .args([
+            "--app-name=envy-tui",
+            "--action=apply=Apply",
+            "--wait",
+            "envy-tui",
+            prompt,
+        ])
+        .output();
+
+    Ok(output.is_ok_and(|o| String::from_utf8_lossy(&o.stdout).trim() == "apply"))
+}
+
+fn install_login_unit() -> Result<()> {
+    let dir = config::xdg_config_home()
+        .ok_or_else(|| anyhow!("Could not determine config directory"))?
+        .join("systemd/user");
+    fs::create_dir_all(&dir)?;
+
+    let path = dir.join("envy-tui-login.service");
+    let unit = format!(
+        "[Unit]\n\
+         Description=Apply envy-tui login profile\n\
+         After=graphical-session.target\n\
+         PartOf=graphical-session.target\n\
+         \n\
+         [Service]\n\
+         Type=oneshot\n\
+         ExecStart={} apply-login\n\
+         \n\
+         [Install]\n\
+         WantedBy=graphical-session.target\n",
+        env::current_exe()?.display()
+    );
+    fs::write(&path, unit)?;
+
+    println!("Wrote {}", path.display());
+    println!("Enable it with: systemctl --user enable envy-tui-login.service");
+    Ok(())
+}
+
+fn install_login_autostart() -> Result<()> {
+    let dir = config::xdg_config_home()
+        .ok_or_else(|| anyhow!("Could not determine config directory"))?
+        .join("autostart");
+    fs::create_dir_all(&dir)?;
+
+    let path = dir.join("envy-tui-login.desktop");
+    let entry = format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=envy-tui login profile\n\
+         Exec={} apply-login\n\
+         NoDisplay=true\n\
+         X-GNOME-Autostart-enabled=true\n",
+        env::current_exe()?.display()
+    );
+    fs::write(&path, entry)?;
+
+    println!("Wrote {}", path.display());
+    Ok(())
+}
@@ -0,0 +1,71 @@
+#[cfg(feature = "clipboard")]
+use crate::host;
+use anyhow::{anyhow, Result};
+#[cfg(feature = "clipboard")]
+use std::io::{self, Write};
+#[cfg(feature = "clipboard")]
+use std::process::Stdio;
+
+#[cfg(feature = "clipboard")]
+const CLIPBOARD_TOOLS: [(&str, &[&str]); 3] = [
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+];
+
+// Callers fall back to showing the text, so a packager can drop this.
+#[cfg(not(feature = "clipboard"))]
+pub fn copy(_text: &str) -> Result<()> {
+    Err(anyhow!(
+        "This build of envy-tui can't copy to the clipboard"
+    ))
+}
+
+#[cfg(feature = "clipboard")]
+pub fn copy(text: &str) -> Result<()> {
+    for (program, args) in CLIPBOARD_TOOLS {
+        let Ok(mut child) = host::command(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            continue;
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        if child.wait()?.success() {
+            return Ok(());
+        }
+    }
+
+    // No clipboard tool available (e.g. over SSH): ask the terminal via OSC 52.
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))
+        .and_then(|_| stdout.flush())
+        .map_err(|e| anyhow!("Failed to copy to clipboard: {}", e))
+}
+
+#[cfg(feature = "clipboard")]
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[((n >> (18 - 6 * i)) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
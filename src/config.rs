@@ -0,0 +1,442 @@
+use crate::app::{GraphicsMode, Rtd3Level};
+use crate::envycontrol::SwitchOptions;
+use crate::keymap::Action;
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+const CONFIG_FILE: &str = "config.toml";
+
+// Written by `envy-tui config init`. Everything is commented out, so the file
+// starts out behaving exactly like no config at all.
+pub const DEFAULT_CONFIG: &str = r#"# envy-tui configuration. Uncomment a setting to change it; the values shown
+# are the defaults. Changes are picked up while envy-tui is running.
+
+# Skip the switch and reboot confirmations, like --yes.
+# skip_confirmations = false
+
+# Run envycontrol from this executable or .py file instead of $PATH.
+# envycontrol_path = "/usr/bin/envycontrol"
+
+# Named option sets, applied by [power] and [login] rules or from the TUI.
+# rtd3 is 0-3 (hybrid mode), coolbits 0-31 (nvidia mode).
+# [profiles.performance]
+# mode = "nvidia"
+# force_comp = true
+# coolbits = 28
+#
+# [profiles.battery]
+# mode = "hybrid"
+# rtd3 = 2
+
+[login]
+# Switch without asking when `envy-tui apply-login` finds a matching rule.
+# auto_apply = false
+#
+# The first rule whose conditions all hold picks the profile. Conditions:
+# ac, battery, docked (an external display is connected), undocked.
+# [[login.rules]]
+# when = ["ac", "docked"]
+# profile = "performance"
+
+[power]
+# Profiles offered when the charger is plugged in or unplugged.
+# ac = "performance"
+# battery = "battery"
+# auto_apply = false
+
+[extra_args]
+# Flags appended to the envycontrol command when switching to each mode.
+# integrated = []
+# hybrid = []
+# nvidia = ["--dm", "sddm"]
+
+[ui]
+# border = "plain"            # plain, rounded, thick or none
+# mode_panel_percent = 50     # width of the Graphics Mode panel, 20-80
+# compact = false             # start in the compact layout (toggle with c)
+# max_fps = 10                # animation frame rate cap; default 10 on AC, 4 on battery
+# tips = true                 # rotating tips in the footer when idle
+# reduced_motion = false      # static "…" instead of the spinner
+# monochrome = false          # no colors, only bold/reverse/underline and ASCII icons
+
+[icons]
+# set = "nerd"                # nerd, emoji or ascii
+# Single symbols can be overridden on top of the set: integrated, hybrid,
+# nvidia, gpu, temperature, memory, utilization, info, warning, current,
+# pending, selector, check, separator.
+# nvidia = "NV"
+
+[monitor]
+# Append every Monitor tab sample to this CSV file.
+# csv_log = "/home/me/gpu-metrics.csv"
+# chart = "block"             # block or braille, for fonts that draw one of them poorly
+
+[logs]
+# level = "info"              # info, warn or error
+# Rotation for the application log and the csv_log file.
+# max_size_kb = 1024          # rotate at this size, 0 to disable
+# daily = false               # also rotate on the first write of each day (UTC)
+# keep = 3                    # rotated files to keep
+# max_age_days = 30           # delete rotated files older than this
+
+[keys]
+# Remap any action; `envy-tui cheatsheet` lists the current bindings.
+# reset = ["D"]
+# quit = ["q", "esc"]
+"#;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub skip_confirmations: bool,
+    pub envycontrol_path: Option<PathBuf>,
+    pub profiles: BTreeMap<String, Profile>,
+    pub login: LoginConfig,
+    pub power: PowerConfig,
+    pub extra_args: ExtraArgs,
+    pub ui: UiConfig,
+    pub monitor: MonitorConfig,
+    pub logs: LogConfig,
+    pub icons: IconConfig,
+    pub keys: BTreeMap<Action, Vec<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Profile {
+    pub mode: GraphicsMode,
+    #[serde(default)]
+    pub rtd3: Option<u8>,
+    #[serde(default)]
+    pub force_comp: bool,
+    #[serde(default)]
+    pub coolbits: Option<u8>,
+}
+
+impl Profile {
+    pub fn switch_options(&self) -> SwitchOptions {
+        let rtd3_level = self.rtd3.and_then(Rtd3Level::from_value);
+        SwitchOptions {
+            mode: self.mode,
+            rtd3_enabled: rtd3_level.is_some(),
+            rtd3_level: rtd3_level.unwrap_or(Rtd3Level::FineGrained),
+            force_comp: self.force_comp,
+            coolbits_enabled: self.coolbits.is_some(),
+            coolbits_value: self.coolbits.unwrap_or(28),
+            extra_args: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LoginConfig {
+    pub auto_apply: bool,
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PowerConfig {
+    pub ac: Option<String>,
+    pub battery: Option<String>,
+    pub auto_apply: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct MonitorConfig {
+    pub csv_log: Option<PathBuf>,
+    pub chart: ChartStyle,
+}
+
+// Block characters give one sample per column, braille dots two at half the
+// height resolution.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChartStyle {
+    #[default]
+    Block,
+    Braille,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+}
+
+impl LogLevel {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "error" => Some(LogLevel::Error),
+            "warn" | "warning" => Some(LogLevel::Warn),
+            "info" => Some(LogLevel::Info),
+            _ => None,
+        }
+    }
+}
+
+// The application log's level, and rotation for it and the [monitor] csv_log
+// file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    pub level: LogLevel,
+    // 0 turns size-based rotation off.
+    pub max_size_kb: u64,
+    pub daily: bool,
+    pub keep: usize,
+    pub max_age_days: Option<u64>,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: LogLevel::Info,
+            max_size_kb: 1024,
+            daily: false,
+            keep: 3,
+            max_age_days: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct UiConfig {
+    pub border: BorderStyle,
+    pub mode_panel_percent: u16,
+    pub compact: bool,
+    pub max_fps: Option<u32>,
+    pub tips: bool,
+    pub reduced_motion: bool,
+    pub monochrome: bool,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            border: BorderStyle::Plain,
+            mode_panel_percent: 50,
+            compact: false,
+            max_fps: None,
+            tips: true,
+            reduced_motion: false,
+            monochrome: false,
+        }
+    }
+}
+
+impl UiConfig {
+    pub fn mode_panel_percent(&self) -> u16 {
+        self.mode_panel_percent.clamp(20, 80)
+    }
+
+    // Minimum time between animation frames; lower by default on battery so
+    // the spinner doesn't keep the CPU awake.
+    pub fn frame_interval(&self, on_ac: bool) -> Duration {
+        let fps = self
+            .max_fps
+            .unwrap_or(if on_ac { 10 } else { 4 })
+            .clamp(1, 60);
+        Duration::from_millis(1000 / u64::from(fps))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IconSet {
+    #[default]
+    Nerd,
+    Emoji,
+    Ascii,
+}
+
+// A preset plus optional per-symbol glyphs, e.g. for fonts missing a few
+// Nerd Font icons.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IconConfig {
+    pub set: IconSet,
+    pub integrated: Option<String>,
+    pub hybrid: Option<String>,
+    pub nvidia: Option<String>,
+    pub gpu: Option<String>,
+    pub temperature: Option<String>,
+    pub memory: Option<String>,
+    pub utilization: Option<String>,
+    pub info: Option<String>,
+    pub warning: Option<String>,
+    pub current: Option<String>,
+    pub pending: Option<String>,
+    pub selector: Option<String>,
+    pub check: Option<String>,
+    pub separator: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BorderStyle {
+    Plain,
+    Rounded,
+    Thick,
+    None,
+}
+
+// Flags appended verbatim to the envycontrol invocation, for options the TUI
+// doesn't model yet.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ExtraArgs {
+    pub integrated: Vec<String>,
+    pub hybrid: Vec<String>,
+    pub nvidia: Vec<String>,
+}
+
+impl ExtraArgs {
+    pub fn for_mode(&self, mode: GraphicsMode) -> &[String] {
+        match mode {
+            GraphicsMode::Integrated => &self.integrated,
+            GraphicsMode::Hybrid => &self.hybrid,
+            GraphicsMode::Nvidia => &self.nvidia,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Rule {
+    #[serde(default)]
+    pub when: Vec<Condition>,
+    pub profile: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Condition {
+    Ac,
+    Battery,
+    Docked,
+    Undocked,
+}
+
+pub fn xdg_config_home() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    xdg_config_home().map(|base| base.join("envy-tui"))
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(CONFIG_FILE))
+}
+
+// Detects edits to a file by its modification time, so changes can be picked
+// up while the app is running.
+pub struct FileWatcher {
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+}
+
+impl FileWatcher {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        let modified = modified_time(&path);
+        Self { path, modified }
+    }
+
+    pub fn changed(&mut self) -> bool {
+        let modified = modified_time(&self.path);
+        if modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        true
+    }
+}
+
+fn modified_time(path: &Option<PathBuf>) -> Option<SystemTime> {
+    path.as_ref()
+        .and_then(|path| fs::metadata(path).ok())
+        .and_then(|meta| meta.modified().ok())
+}
+
+pub fn load() -> Result<Config> {
+    let mut config = match config_path().filter(|path| path.exists()) {
+        None => Config::default(),
+        Some(path) => {
+            let contents = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            toml::from_str(&contents)
+                .with_context(|| format!("Invalid config in {}", path.display()))?
+        }
+    };
+    config.apply_env();
+    Ok(config)
+}
+
+pub const ENV_ENVYCONTROL_PATH: &str = "ENVY_TUI_ENVYCONTROL_PATH";
+pub const ENV_NO_CONFIRM: &str = "ENVY_TUI_NO_CONFIRM";
+pub const ENV_LOG_LEVEL: &str = "ENVY_TUI_LOG_LEVEL";
+
+// "1"/"0", "true"/"false", "yes"/"no" or "on"/"off".
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+impl Config {
+    // ENVY_TUI_* variables override config.toml for a single run, e.g. in a
+    // container; command line flags still take precedence over them. Invalid
+    // values are ignored here and reported on the Config Problems screen.
+    pub fn apply_env(&mut self) {
+        if let Some(path) = env::var_os(ENV_ENVYCONTROL_PATH).filter(|path| !path.is_empty()) {
+            self.envycontrol_path = Some(PathBuf::from(path));
+        }
+        if let Some(skip) = env::var(ENV_NO_CONFIRM).ok().and_then(|v| parse_bool(&v)) {
+            self.skip_confirmations = skip;
+        }
+        if let Some(level) = env::var(ENV_LOG_LEVEL)
+            .ok()
+            .and_then(|v| LogLevel::from_name(&v))
+        {
+            self.logs.level = level;
+        }
+    }
+}
+
+pub fn save_profile(name: &str, profile: &Profile) -> Result<PathBuf> {
+    let path = config_path().ok_or_else(|| anyhow!("Could not determine config directory"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut section = format!(
+        "\n[profiles.{}]\nmode = \"{}\"\nforce_comp = {}\n",
+        name, profile.mode, profile.force_comp
+    );
+    if let Some(rtd3) = profile.rtd3 {
+        section.push_str(&format!("rtd3 = {}\n", rtd3));
+    }
+    if let Some(coolbits) = profile.coolbits {
+        section.push_str(&format!("coolbits = {}\n", coolbits));
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    file.write_all(section.as_bytes())?;
+    Ok(path)
+}
@@ -0,0 +1,196 @@
+use crate::app::GraphicsMode;
+use crate::system::InitramfsTool;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Family {
+    Arch,
+    Debian,
+    Fedora,
+    Suse,
+    Other,
+}
+
+// Systems where /etc, the initramfs or kernel arguments are managed by the
+// OS image or a declarative config, so envycontrol's edits don't stick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Immutable {
+    NixOs,
+    Ostree,
+    SteamOs,
+}
+
+impl Immutable {
+    pub fn badge(self) -> &'static str {
+        match self {
+            Immutable::NixOs => "NixOS: switching disabled",
+            Immutable::Ostree => "image-based system: switching disabled",
+            Immutable::SteamOs => "SteamOS: switching disabled",
+        }
+    }
+
+    pub fn explanation(self) -> &'static str {
+        match self {
+            Immutable::NixOs => {
+                "Switching is disabled on NixOS.\n\
+                 /etc is generated from your configuration, so envycontrol's files would be \
+                 replaced on the next rebuild. Set the mode with hardware.nvidia.prime \
+                 (offload or sync) and specialisations instead.\n\
+                 https://nixos.wiki/wiki/Nvidia"
+            }
+            Immutable::Ostree => {
+                "Switching is disabled on image-based (ostree) systems.\n\
+                 The initramfs and kernel arguments envycontrol relies on are managed by \
+                 rpm-ostree, so a switch would only half apply. Use rpm-ostree kargs and \
+                 rpm-ostree initramfs, or your image's GPU tooling.\n\
+                 https://docs.fedoraproject.org/en-US/fedora-silverblue/"
+            }
+            Immutable::SteamOs => {
+                "Switching is disabled on SteamOS.\n\
+                 The root filesystem is read-only and replaced by updates, so envycontrol's \
+                 changes would not persist.\n\
+                 https://help.steampowered.com/en/faqs/view/1B71-EDF2-EB6D-2BB3"
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Distro {
+    pub id: String,
+    pub pretty_name: Option<String>,
+    pub family: Family,
+    pub immutable: Option<Immutable>,
+}
+
+// Read once from /etc/os-release; derivatives (Manjaro, Pop!_OS, Nobara, ...)
+// are matched through ID_LIKE.
+pub fn current() -> &'static Distro {
+    static DISTRO: OnceLock<Distro> = OnceLock::new();
+    DISTRO.get_or_init(|| {
+        let contents = fs::read_to_string("/etc/os-release")
+            .or_else(|_| fs::read_to_string("/usr/lib/os-release"))
+            .unwrap_or_default();
+        let mut distro = parse_os_release(&contents);
+        if distro.immutable.is_none() && Path::new("/run/ostree-booted").exists() {
+            distro.immutable = Some(Immutable::Ostree);
+        }
+        distro
+    })
+}
+
+pub fn parse_os_release(contents: &str) -> Distro {
+    let value = |key: &str| {
+        contents.lines().find_map(|line| {
+            line.strip_prefix(key)
+                .and_then(|rest| rest.strip_prefix('='))
+                .map(|value| value.trim().trim_matches('"').to_string())
+        })
+    };
+    let id = value("ID").unwrap_or_else(|| "linux".to_string());
+    let id_like = value("ID_LIKE").unwrap_or_default();
+
+    let ids: Vec<&str> = std::iter::once(id.as_str())
+        .chain(id_like.split_whitespace())
+        .collect();
+    let family = ids
+        .iter()
+        .find_map(|id| match *id {
+            "arch" => Some(Family::Arch),
+            "debian" | "ubuntu" => Some(Family::Debian),
+            "fedora" | "rhel" | "centos" => Some(Family::Fedora),
+            "suse" | "opensuse" | "opensuse-tumbleweed" | "opensuse-leap" => Some(Family::Suse),
+            _ => None,
+        })
+        .unwrap_or(Family::Other);
+
+    let immutable = match id.as_str() {
+        "nixos" => Some(Immutable::NixOs),
+        "steamos" => Some(Immutable::SteamOs),
+        _ => None,
+    };
+
+    Distro {
+        pretty_name: value("PRETTY_NAME"),
+        id,
+        family,
+        immutable,
+    }
+}
+
+impl Distro {
+    pub fn name(&self) -> &str {
+        self.pretty_name.as_deref().unwrap_or(&self.id)
+    }
+
+    pub fn install_envycontrol(&self) -> &'static str {
+        match self.family {
+            Family::Arch => "Install it from the AUR: yay -S envycontrol",
+            Family::Debian => {
+                "Install the .deb from https://github.com/bayasdev/envycontrol/releases"
+            }
+            Family::Fedora => {
+                "Install it from COPR: sudo dnf copr enable sunwire/envycontrol && sudo dnf install python3-envycontrol"
+            }
+            Family::Suse | Family::Other => "Install it with: pipx install envycontrol",
+        }
+    }
+
+    pub fn install_driver(&self) -> &'static str {
+        match self.family {
+            Family::Arch => "Install the driver with: sudo pacman -S nvidia nvidia-utils",
+            Family::Debian if self.id == "debian" => {
+                "Install the driver with: sudo apt install nvidia-driver (non-free)"
+            }
+            Family::Debian => "Install the driver with: sudo ubuntu-drivers install",
+            Family::Fedora => "Install the driver from RPM Fusion: sudo dnf install akmod-nvidia",
+            Family::Suse => "Install the driver with: sudo zypper install nvidia-video-G06",
+            Family::Other => "Install the NVIDIA proprietary driver from your distribution",
+        }
+    }
+
+    pub fn initramfs_tool(&self) -> Option<InitramfsTool> {
+        match self.family {
+            Family::Arch => Some(InitramfsTool::Mkinitcpio),
+            Family::Debian => Some(InitramfsTool::UpdateInitramfs),
+            Family::Fedora | Family::Suse => Some(InitramfsTool::Dracut),
+            Family::Other => None,
+        }
+    }
+
+    // The display manager a default install ships, for the doctor output.
+    pub fn default_display_manager(&self) -> Option<&'static str> {
+        match self.family {
+            Family::Debian if self.id == "ubuntu" || self.id == "pop" => Some("gdm3"),
+            Family::Fedora => Some("gdm"),
+            Family::Suse => Some("sddm"),
+            _ => None,
+        }
+    }
+
+    // How an installed RPM package is updated.
+    pub fn rpm_update(&self) -> &'static str {
+        match self.family {
+            Family::Suse => "sudo zypper update",
+            _ => "sudo dnf upgrade",
+        }
+    }
+
+    // Shown under the reboot prompt after a successful switch.
+    pub fn reboot_hint(&self, mode: GraphicsMode) -> Option<&'static str> {
+        if mode == GraphicsMode::Integrated {
+            return None;
+        }
+        match self.family {
+            Family::Fedora => Some(
+                "Fedora: akmods builds the nvidia module in the background; wait for it to finish before rebooting.",
+            ),
+            Family::Arch => Some(
+                "Arch: with a custom kernel the nvidia module comes from nvidia-dkms, make sure it is installed.",
+            ),
+            _ => None,
+        }
+    }
+}
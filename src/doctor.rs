@@ -0,0 +1,288 @@
+use crate::distro::{self, Distro, Family};
+use crate::envycontrol;
+use crate::host;
+use crate::system;
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::Path;
+
+struct Check {
+    name: &'static str,
+    ok: bool,
+    detail: String,
+    hint: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Check {
+            name,
+            ok: true,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Check {
+            name,
+            ok: false,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn with_hint(mut self, hint: Option<String>) -> Self {
+        self.hint = hint;
+        self
+    }
+}
+
+pub fn run() -> Result<()> {
+    let distro = distro::current();
+    let mut checks = vec![
+        Check::pass("Distribution", distro.name()),
+        check_envycontrol(distro),
+        check_driver(distro),
+        check_initramfs(distro),
+        check_display_manager(distro),
+        check_conflicts(),
+        check_legacy_power_off(),
+    ];
+    if let Some(immutable) = distro.immutable {
+        let mut lines = immutable.explanation().lines();
+        checks.push(Check::fail(
+            "Immutable system",
+            lines.next().unwrap_or_default(),
+            lines.collect::<Vec<_>>().join(" "),
+        ));
+    }
+    if distro.family == Family::Fedora {
+        checks.extend([check_akmods(), check_selinux(), check_module_kernel()]);
+    }
+
+    println!("envy-tui doctor");
+    println!();
+    for check in &checks {
+        println!(
+            "{} {:<16} {}",
+            if check.ok { "✓" } else { "✗" },
+            check.name,
+            check.detail
+        );
+        for line in check.hint.iter().flat_map(|hint| hint.lines()) {
+            println!("  {:<16} {}", "", line);
+        }
+    }
+
+    let problems = checks.iter().filter(|check| !check.ok).count();
+    println!();
+    if problems == 0 {
+        println!("No problems found.");
+        Ok(())
+    } else {
+        Err(anyhow!("{} problem(s) found", problems))
+    }
+}
+
+fn check_envycontrol(distro: &Distro) -> Check {
+    if !envycontrol::is_envycontrol_installed() {
+        return Check::fail("envycontrol", "not installed", distro.install_envycontrol());
+    }
+    let version = envycontrol::version();
+    let method = envycontrol::install_method();
+    let detail = match &method {
+        Some(method) => format!(
+            "{} (from {})",
+            version.as_deref().unwrap_or("unknown version"),
+            method.label()
+        ),
+        None => version
+            .clone()
+            .unwrap_or_else(|| "unknown version".to_string()),
+    };
+    let outdated = version.as_deref().is_some_and(envycontrol::is_outdated);
+    let hint = outdated.then(|| {
+        let (major, minor, patch) = envycontrol::RECOMMENDED_VERSION;
+        let update = method.map_or_else(
+            || "update it the way it was installed".to_string(),
+            |method| method.update_hint(),
+        );
+        format!("Older than {}.{}.{}: {}", major, minor, patch, update)
+    });
+    Check::pass("envycontrol", detail).with_hint(hint)
+}
+
+fn check_driver(distro: &Distro) -> Check {
+    match system::nvidia_driver_version() {
+        Some(version) if Path::new("/sys/module/nvidia").exists() => {
+            Check::pass("NVIDIA driver", format!("{} (loaded)", version))
+        }
+        Some(version) => Check::pass("NVIDIA driver", format!("{} (not loaded)", version)),
+        None => Check::fail(
+            "NVIDIA driver",
+            "no nvidia module for the running kernel",
+            distro.install_driver(),
+        ),
+    }
+}
+
+fn check_initramfs(distro: &Distro) -> Check {
+    match system::initramfs_tool() {
+        Some(tool) => Check::pass("initramfs", tool.command_line()),
+        None => Check::pass("initramfs", "no generator found").with_hint(
+            distro
+                .initramfs_tool()
+                .map(|tool| format!("Expected `{}` on {}", tool.command_line(), distro.name())),
+        ),
+    }
+}
+
+fn check_display_manager(distro: &Distro) -> Check {
+    let Some(dm) = system::display_manager() else {
+        return Check::pass("Display manager", "unknown");
+    };
+    let hint = distro
+        .default_display_manager()
+        .filter(|default| *default != dm)
+        .map(|default| {
+            format!(
+                "Not the {} default ({}); add [\"--dm\", \"{}\"] to extra_args.nvidia if nvidia mode shows a black screen",
+                distro.name(),
+                default,
+                dm
+            )
+        });
+    Check::pass("Display manager", dm).with_hint(hint)
+}
+
+fn check_conflicts() -> Check {
+    let conflicts = system::running_switchers();
+    if conflicts.is_empty() {
+        return Check::pass("Other switchers", "none");
+    }
+    Check::fail(
+        "Other switchers",
+        conflicts.join(", "),
+        "Disable or uninstall them; they fight envycontrol over the same config files",
+    )
+}
+
+fn check_legacy_power_off() -> Check {
+    let setups = system::legacy_power_off();
+    if setups.is_empty() {
+        return Check::pass("Legacy power-off", "none");
+    }
+    let detail = setups
+        .iter()
+        .map(|setup| format!("{} ({})", setup.name, setup.source))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut hint = vec![
+        "Bumblebee leftovers power the dGPU off behind the driver's back and break RTD3 and envycontrol's udev rules. To remove them:".to_string(),
+    ];
+    for setup in &setups {
+        let step = format!("- {}: {}", setup.name, setup.removal());
+        if !hint.contains(&step) {
+            hint.push(step);
+        }
+    }
+    hint.push("Then reboot.".to_string());
+    Check::fail("Legacy power-off", detail, hint.join("\n"))
+}
+
+// akmods rebuilds the nvidia module after kernel updates, in the background at
+// boot; a failed or unfinished build leaves the new kernel without a driver.
+fn check_akmods() -> Check {
+    let kernel = system::kernel_release().unwrap_or_default();
+    let failed: Vec<String> = fs::read_dir("/var/cache/akmods/nvidia")
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    name.ends_with(".failed.log") && name.contains(&kernel)
+                })
+                .map(|path| path.display().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    if let Some(log) = failed.first() {
+        return Check::fail(
+            "akmods",
+            format!("building nvidia for {} failed", kernel),
+            format!(
+                "See {}, then run: sudo akmods --force --kernels {}",
+                log, kernel
+            ),
+        );
+    }
+
+    let building = host::command("pgrep")
+        .args(["-x", "akmods"])
+        .output()
+        .is_ok_and(|output| output.status.success());
+    if building {
+        return Check::fail(
+            "akmods",
+            "still building the nvidia module",
+            "Wait for it to finish before switching or rebooting",
+        );
+    }
+    Check::pass("akmods", "no failed builds")
+}
+
+// Files written by a process without the right context (e.g. a venv install)
+// keep a wrong SELinux label, and the denials break the next boot.
+fn check_selinux() -> Check {
+    let enforcing = host::command("getenforce")
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).trim() == "Enforcing");
+    if !enforcing {
+        return Check::pass("SELinux", "not enforcing");
+    }
+
+    let mislabeled: Vec<&str> = envycontrol::GENERATED_FILES
+        .iter()
+        .copied()
+        .filter(|file| Path::new(file).exists())
+        .filter(|file| {
+            host::command("matchpathcon")
+                .args(["-V", file])
+                .output()
+                .is_ok_and(|output| !String::from_utf8_lossy(&output.stdout).contains("verified"))
+        })
+        .collect();
+    if mislabeled.is_empty() {
+        Check::pass("SELinux", "generated files are labeled correctly")
+    } else {
+        Check::fail(
+            "SELinux",
+            format!(
+                "{} generated file(s) have the wrong label",
+                mislabeled.len()
+            ),
+            format!("Run: sudo restorecon -v {}", mislabeled.join(" ")),
+        )
+    }
+}
+
+fn check_module_kernel() -> Check {
+    let kernel = system::kernel_release().unwrap_or_else(|| "the running kernel".to_string());
+    let Some(installed) = system::installed_nvidia_version() else {
+        return Check::fail(
+            "Module/kernel",
+            format!("no nvidia module built for {}", kernel),
+            format!("Run: sudo akmods --force --kernels {}", kernel),
+        );
+    };
+    match system::loaded_nvidia_version() {
+        Some(loaded) if loaded != installed => Check::fail(
+            "Module/kernel",
+            format!("loaded {} but {} is installed", loaded, installed),
+            "Reboot to load the updated driver",
+        ),
+        _ => Check::pass("Module/kernel", format!("{} for {}", installed, kernel)),
+    }
+}
@@ -1,9 +1,180 @@
 use crate::app::{GraphicsMode, Rtd3Level};
+use crate::distro;
+use crate::host;
+use crate::logging;
+#[cfg(feature = "monitor")]
+use crate::monitor::Sample;
+use crate::system;
+use crate::xml;
 use anyhow::{anyhow, Result};
-use std::process::Command;
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+// Files envycontrol writes, depending on the mode and display manager.
+pub const GENERATED_FILES: [&str; 9] = [
+    "/etc/modprobe.d/blacklist-nvidia.conf",
+    "/etc/modprobe.d/nvidia.conf",
+    "/lib/udev/rules.d/50-remove-nvidia.rules",
+    "/lib/udev/rules.d/80-nvidia-pm.rules",
+    "/etc/X11/xorg.conf",
+    "/etc/X11/xorg.conf.d/10-nvidia.conf",
+    "/etc/lightdm/nvidia.sh",
+    "/etc/lightdm/lightdm.conf.d/20-nvidia.conf",
+    "/usr/share/sddm/scripts/Xsetup",
+];
+
+static CUSTOM_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+// The invocation found last. Only a successful lookup is kept, so an
+// envycontrol installed while envy-tui runs is still picked up.
+static INVOCATION: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+// Set from the config file or --envycontrol-path, e.g. for a git checkout or venv.
+pub fn set_custom_path(path: Option<PathBuf>) {
+    if let Ok(mut custom) = CUSTOM_PATH.lock() {
+        *custom = path;
+    }
+    forget_invocation();
+}
+
+// An update may move envycontrol, e.g. from a pip script to a pipx venv.
+fn forget_invocation() {
+    if let Ok(mut cached) = INVOCATION.lock() {
+        *cached = None;
+    }
+}
+
+fn custom_path() -> Option<PathBuf> {
+    CUSTOM_PATH.lock().ok().and_then(|custom| custom.clone())
+}
+
+fn invocation() -> Option<Vec<String>> {
+    if let Some(cached) = INVOCATION.lock().ok().and_then(|cached| cached.clone()) {
+        return Some(cached);
+    }
+    let found = find_invocation()?;
+    if let Ok(mut cached) = INVOCATION.lock() {
+        *cached = Some(found.clone());
+    }
+    Some(found)
+}
+
+// How to run envycontrol: a configured path, the executable on PATH, or the
+// Python module for pip installs that don't expose a script.
+fn find_invocation() -> Option<Vec<String>> {
+    let sandboxed = host::sandbox() != host::Sandbox::None;
+    if let Some(path) = custom_path() {
+        // Host paths aren't visible from inside a sandbox.
+        if !sandboxed && !path.is_file() {
+            return None;
+        }
+        let path = path.to_string_lossy().to_string();
+        return Some(if path.ends_with(".py") {
+            vec!["python3".to_string(), path]
+        } else {
+            vec![path]
+        });
+    }
+
+    if sandboxed {
+        if let Some(path) = host_lookup() {
+            return Some(vec![path]);
+        }
+    } else if let Some(path) = search_locations()
+        .into_iter()
+        .map(|dir| dir.join("envycontrol"))
+        .find(|path| is_executable(path))
+    {
+        return Some(vec![path.to_string_lossy().to_string()]);
+    }
+
+    let module_found = host::command("python3")
+        .args(["-c", "import envycontrol"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success());
+    module_found.then(|| ["python3", "-m", "envycontrol"].map(String::from).to_vec())
+}
+
+fn host_lookup() -> Option<String> {
+    let output = host::command("sh")
+        .args(["-c", "command -v envycontrol"])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !path.is_empty()).then_some(path)
+}
+
+// PATH plus the usual pip/pipx install locations, which are often missing
+// from PATH in desktop launchers.
+pub fn search_locations() -> Vec<PathBuf> {
+    let mut candidates: Vec<PathBuf> = env::var_os("PATH")
+        .map(|path| env::split_paths(&path).collect())
+        .unwrap_or_default();
+    candidates.push(PathBuf::from("/usr/local/bin"));
+    candidates.push(PathBuf::from("/usr/bin"));
+    if let Some(home) = env::var_os("HOME").map(PathBuf::from) {
+        candidates.push(home.join(".local/bin"));
+        candidates.push(home.join(".local/pipx/venvs/envycontrol/bin"));
+        candidates.push(home.join(".local/share/pipx/venvs/envycontrol/bin"));
+    }
+
+    let mut dirs = Vec::new();
+    for dir in candidates {
+        if !dir.as_os_str().is_empty() && !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+    dirs
+}
+
+fn is_executable(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+pub fn not_found_message() -> String {
+    if host::sandbox() != host::Sandbox::None {
+        return format!(
+            "envycontrol is not installed on the host. Please install it first.\nLooked in:\n• the host PATH\n• python3 -m envycontrol\n{}",
+            distro::current().install_envycontrol()
+        );
+    }
+    let tried: Vec<String> = search_locations()
+        .iter()
+        .map(|dir| format!("• {}", dir.display()))
+        .collect();
+    format!(
+        "envycontrol is not installed. Please install it first.\nLooked in:\n{}\n• python3 -m envycontrol\n{}",
+        tried.join("\n"),
+        distro::current().install_envycontrol()
+    )
+}
+
+fn command() -> Command {
+    let invocation = invocation().unwrap_or_else(|| vec!["envycontrol".to_string()]);
+    let mut command = host::command(&invocation[0]);
+    command.args(&invocation[1..]);
+    command
+}
+
+// The invocation as a string for `pkexec sh -c`.
+fn shell_command() -> String {
+    invocation()
+        .unwrap_or_else(|| vec!["envycontrol".to_string()])
+        .iter()
+        .map(|part| shell_quote(part))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
 
 pub fn query_mode() -> Result<Option<GraphicsMode>> {
-    let output = Command::new("envycontrol").arg("--query").output()?;
+    let output = command().arg("--query").output()?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -26,6 +197,7 @@ pub fn query_mode() -> Result<Option<GraphicsMode>> {
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct SwitchOptions {
     pub mode: GraphicsMode,
     pub rtd3_enabled: bool,
@@ -33,114 +205,1171 @@ pub struct SwitchOptions {
     pub force_comp: bool,
     pub coolbits_enabled: bool,
     pub coolbits_value: u8,
+    pub extra_args: Vec<String>,
+}
+
+impl SwitchOptions {
+    // Short labels for the options that apply to the mode, e.g. "+coolbits 28".
+    pub fn summary(&self) -> Vec<String> {
+        let mut flags = Vec::new();
+        match self.mode {
+            GraphicsMode::Hybrid if self.rtd3_enabled => {
+                flags.push(format!("+rtd3 {}", self.rtd3_level.value()));
+            }
+            GraphicsMode::Nvidia => {
+                if self.force_comp {
+                    flags.push("+force-comp".to_string());
+                }
+                if self.coolbits_enabled {
+                    flags.push(format!("+coolbits {}", self.coolbits_value));
+                }
+            }
+            _ => {}
+        }
+        if !self.extra_args.is_empty() {
+            flags.push(format!("+{}", self.extra_args.join(" ")));
+        }
+        flags
+    }
+
+    // One line per option the switch will apply, for the confirmation popup.
+    pub fn details(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        match self.mode {
+            GraphicsMode::Hybrid if self.rtd3_enabled => {
+                lines.push(format!("RTD3 power management: level {}", self.rtd3_level));
+            }
+            GraphicsMode::Hybrid => lines.push("RTD3 power management: off".to_string()),
+            GraphicsMode::Nvidia => {
+                lines.push(format!(
+                    "Force composition pipeline: {}",
+                    if self.force_comp { "on" } else { "off" }
+                ));
+                if self.coolbits_enabled {
+                    lines.push(format!("Coolbits: {}", self.coolbits_value));
+                } else {
+                    lines.push("Coolbits: off".to_string());
+                }
+            }
+            GraphicsMode::Integrated => {}
+        }
+
+        let mut extra = self.extra_args.iter();
+        let mut others = Vec::new();
+        while let Some(arg) = extra.next() {
+            if arg == "--dm" {
+                match extra.next() {
+                    Some(dm) => lines.push(format!("Display manager: {}", dm)),
+                    None => others.push(arg.clone()),
+                }
+            } else if let Some(dm) = arg.strip_prefix("--dm=") {
+                lines.push(format!("Display manager: {}", dm));
+            } else {
+                others.push(arg.clone());
+            }
+        }
+        if !others.is_empty() {
+            lines.push(format!("Extra arguments: {}", others.join(" ")));
+        }
+        if lines.is_empty() {
+            lines.push("No extra options".to_string());
+        }
+        lines
+    }
+
+    // envycontrol flags for the options that apply to the mode, without the
+    // extra arguments.
+    pub fn option_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        match self.mode {
+            GraphicsMode::Hybrid if self.rtd3_enabled => {
+                args.push("--rtd3".to_string());
+                args.push(self.rtd3_level.value().to_string());
+            }
+            GraphicsMode::Nvidia => {
+                if self.force_comp {
+                    args.push("--force-comp".to_string());
+                }
+                if self.coolbits_enabled {
+                    args.push("--coolbits".to_string());
+                    args.push(self.coolbits_value.to_string());
+                }
+            }
+            _ => {}
+        }
+        args
+    }
+
+    // Everything after `-s <mode>`, as envycontrol receives it.
+    pub fn flags(&self) -> Vec<String> {
+        let mut flags = self.option_args();
+        flags.extend(self.extra_args.iter().cloned());
+        flags
+    }
+
+    // The options envycontrol applies once the extra arguments are added, in
+    // the same form as option_args: the last occurrence of a flag wins, and a
+    // bare --rtd3 or --coolbits takes envycontrol's default.
+    pub fn effective_option_args(&self) -> Vec<String> {
+        let flags = self.flags();
+        let mut rtd3 = None;
+        let mut force_comp = false;
+        let mut coolbits = None;
+        let mut rest = flags.iter().peekable();
+        while let Some(flag) = rest.next() {
+            let (name, inline) = match flag.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (flag.as_str(), None),
+            };
+            let mut value = |default| {
+                inline
+                    .or_else(|| {
+                        rest.next_if(|next| !next.starts_with('-'))
+                            .map(String::as_str)
+                    })
+                    .unwrap_or(default)
+            };
+            match name {
+                "--rtd3" => rtd3 = Some(value("2")),
+                "--coolbits" => coolbits = Some(value("28")),
+                "--force-comp" => force_comp = true,
+                _ => {}
+            }
+        }
+
+        let mut args = Vec::new();
+        match self.mode {
+            GraphicsMode::Hybrid => {
+                if let Some(level) = rtd3 {
+                    args.extend(["--rtd3".to_string(), level.to_string()]);
+                }
+            }
+            GraphicsMode::Nvidia => {
+                if force_comp {
+                    args.push("--force-comp".to_string());
+                }
+                if let Some(value) = coolbits {
+                    args.extend(["--coolbits".to_string(), value.to_string()]);
+                }
+            }
+            GraphicsMode::Integrated => {}
+        }
+        args
+    }
+
+    // The envycontrol invocation for this switch, shell-quoted. switch_mode runs
+    // exactly this, so an exported command does what Apply would.
+    pub fn command_line(&self) -> String {
+        let mut args = vec![shell_command(), "-s".to_string(), self.mode.to_string()];
+        args.extend(self.option_args());
+        args.extend(self.extra_args.iter().map(|arg| shell_quote(arg)));
+        args.join(" ")
+    }
+
+    // What the mode will be like once the machine has rebooted into it.
+    pub fn expectations(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        match self.mode {
+            GraphicsMode::Integrated => {
+                lines.push("Only the iGPU is used; the dGPU is powered off and the nvidia modules stay unloaded.".to_string());
+                lines.push("Displays wired to the dGPU stay dark.".to_string());
+            }
+            GraphicsMode::Hybrid => {
+                lines.push(
+                    "The desktop runs on the iGPU; run applications on the dGPU with prime-run."
+                        .to_string(),
+                );
+                if self.rtd3_enabled {
+                    lines.push(format!(
+                        "The dGPU powers off while idle (RTD3 level {}); the power badge shows it.",
+                        self.rtd3_level.value()
+                    ));
+                } else {
+                    lines.push(
+                        "Without RTD3 the dGPU stays powered, drawing a few watts while idle."
+                            .to_string(),
+                    );
+                }
+            }
+            GraphicsMode::Nvidia => {
+                lines.push("Everything renders on the dGPU: best performance and external displays, highest power draw.".to_string());
+                if self.force_comp {
+                    lines.push("ForceCompositionPipeline removes tearing on X11.".to_string());
+                }
+                if self.coolbits_enabled {
+                    lines.push(format!(
+                        "Coolbits {} unlocks extra nvidia-settings controls on X11.",
+                        self.coolbits_value
+                    ));
+                }
+            }
+        }
+        lines
+    }
+
+    // The GENERATED_FILES this switch writes. envycontrol deletes the others
+    // before writing, so any of them that exist are removed.
+    pub fn written_files(&self, display_manager: Option<&str>) -> Vec<&'static str> {
+        match self.mode {
+            GraphicsMode::Integrated => vec![
+                "/etc/modprobe.d/blacklist-nvidia.conf",
+                "/lib/udev/rules.d/50-remove-nvidia.rules",
+            ],
+            // --rtd3 is the only option hybrid mode takes.
+            GraphicsMode::Hybrid if !self.effective_option_args().is_empty() => vec![
+                "/etc/modprobe.d/nvidia.conf",
+                "/lib/udev/rules.d/80-nvidia-pm.rules",
+            ],
+            GraphicsMode::Hybrid => Vec::new(),
+            GraphicsMode::Nvidia => {
+                let mut files = vec![
+                    "/etc/modprobe.d/nvidia.conf",
+                    "/etc/X11/xorg.conf",
+                    "/etc/X11/xorg.conf.d/10-nvidia.conf",
+                ];
+                // --dm in extra_args wins over the detected display manager.
+                let dm = self
+                    .extra_args
+                    .iter()
+                    .skip_while(|arg| *arg != "--dm")
+                    .nth(1)
+                    .map(String::as_str)
+                    .or(display_manager);
+                match dm {
+                    Some("lightdm") => files.extend([
+                        "/etc/lightdm/nvidia.sh",
+                        "/etc/lightdm/lightdm.conf.d/20-nvidia.conf",
+                    ]),
+                    Some("sddm") => files.push("/usr/share/sddm/scripts/Xsetup"),
+                    _ => {}
+                }
+                files
+            }
+        }
+    }
 }
 
-pub fn switch_mode(options: SwitchOptions) -> Result<String> {
-    let mut args = vec!["-s".to_string(), options.mode.to_string()];
+// What a switch did, from envycontrol's verbose output, e.g.
+// "INFO: Removed file /etc/X11/xorg.conf" or "INFO: Created file ...".
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SwitchChanges {
+    pub created: Vec<String>,
+    // Removed during the cleanup and written again.
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+    pub services: Vec<String>,
+}
 
-    match options.mode {
-        GraphicsMode::Hybrid if options.rtd3_enabled => {
-            args.push("--rtd3".to_string());
-            args.push(options.rtd3_level.value().to_string());
+impl SwitchChanges {
+    pub fn parse(lines: &[String]) -> Self {
+        let mut created: Vec<String> = Vec::new();
+        let mut removed: Vec<String> = Vec::new();
+        let mut services: Vec<String> = Vec::new();
+        for line in lines {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let service = words
+                .iter()
+                .map(|word| word.trim_matches(|c: char| c == '"' || c == '\'' || c == '.'))
+                .find(|word| word.ends_with(".service"));
+            if let Some(service) = service {
+                let name = service.rsplit('/').next().unwrap_or(service).to_string();
+                if !services.contains(&name) {
+                    services.push(name);
+                }
+                continue;
+            }
+            let Some(path) = words.iter().find(|word| word.starts_with('/')) else {
+                continue;
+            };
+            let path = path.trim_end_matches(['.', ':', ',']).to_string();
+            let lower = line.to_lowercase();
+            let has = |verbs: &[&str]| verbs.iter().any(|verb| lower.contains(verb));
+            let list = if has(&["removed", "removing", "deleted"]) {
+                &mut removed
+            } else if has(&["created", "creating", "wrote", "writing"]) {
+                &mut created
+            } else {
+                continue;
+            };
+            if !list.contains(&path) {
+                list.push(path);
+            }
+        }
+        let updated: Vec<String> = created
+            .iter()
+            .filter(|path| removed.contains(path))
+            .cloned()
+            .collect();
+        created.retain(|path| !updated.contains(path));
+        removed.retain(|path| !updated.contains(path));
+        SwitchChanges {
+            created,
+            updated,
+            removed,
+            services,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
+// `path` under `root`, which is / outside the tests.
+fn under(root: &Path, path: &str) -> PathBuf {
+    root.join(path.trim_start_matches('/'))
+}
+
+// The option flags the last switch to `mode` left in the generated files
+// under `root`, in the same form as SwitchOptions::option_args.
+pub fn applied_option_args(root: &Path, mode: GraphicsMode) -> Vec<String> {
+    let mut args = Vec::new();
+    match mode {
+        GraphicsMode::Hybrid => {
+            let modprobe =
+                fs::read_to_string(under(root, "/etc/modprobe.d/nvidia.conf")).unwrap_or_default();
+            let level = modprobe
+                .split("NVreg_DynamicPowerManagement=0x")
+                .nth(1)
+                .and_then(|rest| rest.get(..2))
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            if let Some(level) = level {
+                args.push("--rtd3".to_string());
+                args.push(level.to_string());
+            }
         }
         GraphicsMode::Nvidia => {
-            if options.force_comp {
+            let xorg = fs::read_to_string(under(root, "/etc/X11/xorg.conf")).unwrap_or_default();
+            if xorg.contains("ForceCompositionPipeline") {
                 args.push("--force-comp".to_string());
             }
-            if options.coolbits_enabled {
+            if let Some(value) = coolbits_option(&xorg) {
                 args.push("--coolbits".to_string());
-                args.push(options.coolbits_value.to_string());
+                args.push(value.to_string());
             }
         }
-        _ => {}
+        GraphicsMode::Integrated => {}
     }
+    args
+}
+
+fn coolbits_option(xorg: &str) -> Option<&str> {
+    xorg.lines().find_map(|line| {
+        line.trim()
+            .strip_prefix("Option \"Coolbits\"")
+            .map(|value| value.trim().trim_matches('"'))
+    })
+}
 
-    args.push("--verbose".to_string());
+// The Coolbits value in the generated xorg.conf, whatever the selection says.
+#[cfg(feature = "overclock")]
+pub fn applied_coolbits() -> Option<u8> {
+    let xorg = fs::read_to_string("/etc/X11/xorg.conf").ok()?;
+    coolbits_option(&xorg)?.parse().ok()
+}
 
-    let args_str = args.join(" ");
-    let output = Command::new("pkexec")
-        .arg("sh")
-        .arg("-c")
-        .arg(format!("yes | envycontrol {}", args_str))
-        .output()?;
+// Re-reads what a successful switch should have left behind, so a missing
+// file or directive shows up before the reboot rather than as a failed boot.
+// `options` are the ones the switch ran with; the files are read under `root`.
+pub fn verify_switch(
+    root: &Path,
+    options: &SwitchOptions,
+    display_manager: Option<&str>,
+) -> Vec<String> {
+    const BLACKLIST: &str = "/etc/modprobe.d/blacklist-nvidia.conf";
+    const MODPROBE: &str = "/etc/modprobe.d/nvidia.conf";
+    const XORG: &str = "/etc/X11/xorg.conf";
+    let has_line = |contents: &str, matches: &dyn Fn(&str) -> bool| {
+        contents
+            .lines()
+            .map(str::trim)
+            .any(|line| !line.starts_with('#') && matches(line))
+    };
 
-    if output.status.success() {
-        Ok(format!(
-            "Switched to {} mode. Please reboot for changes to take effect.",
-            options.mode
-        ))
-    } else {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        Err(anyhow!("Failed to switch mode: {}", stderr))
+    let mut problems: Vec<String> = options
+        .written_files(display_manager)
+        .into_iter()
+        .filter(|path| !under(root, path).exists())
+        .map(|path| format!("{} was not written", path))
+        .collect();
+
+    let nvidia = options.mode == GraphicsMode::Nvidia;
+    match (options.mode, fs::read_to_string(under(root, BLACKLIST))) {
+        (GraphicsMode::Integrated, Ok(contents))
+            if !has_line(&contents, &|line| line == "blacklist nvidia") =>
+        {
+            problems.push(format!("{} doesn't blacklist nvidia", BLACKLIST));
+        }
+        (GraphicsMode::Hybrid | GraphicsMode::Nvidia, Ok(_)) => problems.push(format!(
+            "{} is still there and keeps the driver from loading",
+            BLACKLIST
+        )),
+        _ => {}
+    }
+    if let Ok(contents) = fs::read_to_string(under(root, XORG)) {
+        let loads_nvidia = has_line(&contents, &|line| {
+            line.starts_with("Driver") && line.contains("\"nvidia\"")
+        });
+        if nvidia && !loads_nvidia {
+            problems.push(format!("{} doesn't load the nvidia driver", XORG));
+        } else if !nvidia && loads_nvidia {
+            problems.push(format!(
+                "{} from nvidia mode is still there and will start X on the dGPU",
+                XORG
+            ));
+        }
+    }
+    if let (true, Ok(contents)) = (nvidia, fs::read_to_string(under(root, MODPROBE))) {
+        if !has_line(&contents, &|line| line.contains("nvidia-drm modeset=1")) {
+            problems.push(format!("{} doesn't enable nvidia-drm modeset", MODPROBE));
+        }
+    }
+
+    let expected = options.effective_option_args();
+    let applied = applied_option_args(root, options.mode);
+    if applied != expected {
+        let describe = |args: &[String]| {
+            if args.is_empty() {
+                "no options".to_string()
+            } else {
+                args.join(" ")
+            }
+        };
+        problems.push(format!(
+            "The written files have {} instead of {}",
+            describe(&applied),
+            describe(&expected)
+        ));
     }
+    problems
 }
 
-pub fn reset() -> Result<String> {
-    let output = Command::new("pkexec")
-        .arg("sh")
-        .arg("-c")
-        .arg("yes | envycontrol --reset --verbose")
-        .output()?;
+pub fn switch_mode(options: SwitchOptions, output: &dyn Fn(String)) -> Result<String> {
+    let command_line = format!("{} --verbose", options.command_line());
+    match system::run_privileged(&command_line, "Switching the graphics mode", output) {
+        Ok(()) => {
+            logging::info(&format!("Switched to {} mode", options.mode));
+            Ok(format!(
+                "Switched to {} mode. Please reboot for changes to take effect.",
+                options.mode
+            ))
+        }
+        Err(e) => {
+            logging::error(&format!("Failed to switch mode: {}", e));
+            Err(anyhow!("Failed to switch mode: {}", e))
+        }
+    }
+}
 
-    if output.status.success() {
-        Ok("Reset successful. Please reboot for changes to take effect.".to_string())
+// The steps of a switch as envycontrol --verbose reports them, in the order
+// it runs them. Not every mode goes through all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Cleanup,
+    Modprobe,
+    Xorg,
+    Udev,
+    DisplayManager,
+    Initramfs,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 6] = [
+        Phase::Cleanup,
+        Phase::Modprobe,
+        Phase::Xorg,
+        Phase::Udev,
+        Phase::DisplayManager,
+        Phase::Initramfs,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Phase::Cleanup => "Removing the previous configuration",
+            Phase::Modprobe => "Writing modprobe options",
+            Phase::Xorg => "Writing the Xorg config",
+            Phase::Udev => "Creating udev rules",
+            Phase::DisplayManager => "Configuring the display manager",
+            Phase::Initramfs => "Rebuilding the initramfs",
+        }
+    }
+
+    pub fn from_line(line: &str) -> Option<Phase> {
+        let line = line.to_lowercase();
+        let has = |words: &[&str]| words.iter().any(|word| line.contains(word));
+        if has(&["initramfs", "dracut", "mkinitcpio", "initrd"]) {
+            Some(Phase::Initramfs)
+        } else if has(&["removed", "removing", "deleted", "cleanup"]) {
+            Some(Phase::Cleanup)
+        } else if has(&["xorg", "x11"]) {
+            Some(Phase::Xorg)
+        } else if has(&["udev"]) {
+            Some(Phase::Udev)
+        } else if has(&["modprobe", "blacklist"]) {
+            Some(Phase::Modprobe)
+        } else if has(&["lightdm", "sddm", "gdm", "display manager"]) {
+            Some(Phase::DisplayManager)
+        } else {
+            None
+        }
+    }
+}
+
+// The arguments end up in a `sh -c` string, so anything from the config file
+// has to be quoted.
+fn shell_quote(arg: &str) -> String {
+    let safe = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_=.,:/+".contains(c));
+    if safe {
+        arg.to_string()
     } else {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        Err(anyhow!("Failed to reset: {}", stderr))
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+pub fn reset(output: &dyn Fn(String)) -> Result<String> {
+    let command_line = format!("{} --reset --verbose", shell_command());
+    match system::run_privileged(&command_line, "Resetting envycontrol", output) {
+        Ok(()) => {
+            logging::info("Reset successful");
+            Ok("Reset successful. Please reboot for changes to take effect.".to_string())
+        }
+        Err(e) => {
+            logging::error(&format!("Failed to reset: {}", e));
+            Err(anyhow!("Failed to reset: {}", e))
+        }
     }
 }
 
 pub fn is_envycontrol_installed() -> bool {
-    Command::new("which")
-        .arg("envycontrol")
+    invocation().is_some()
+}
+
+pub fn version() -> Option<String> {
+    let output = command().arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!version.is_empty()).then_some(version)
+}
+
+// Oldest release without the known switching bugs; older installs are offered an update.
+pub const RECOMMENDED_VERSION: (u32, u32, u32) = (3, 5, 1);
+
+pub fn parse_version(text: &str) -> Option<(u32, u32, u32)> {
+    let version = text
+        .split_whitespace()
+        .find(|word| word.starts_with(|c: char| c.is_ascii_digit()))?;
+    let mut parts = version.split('.').map(|p| p.parse::<u32>().ok());
+    Some((
+        parts.next()??,
+        parts.next()??,
+        parts.next().flatten().unwrap_or(0),
+    ))
+}
+
+pub fn is_outdated(version: &str) -> bool {
+    parse_version(version).is_some_and(|v| v < RECOMMENDED_VERSION)
+}
+
+const PIP_UPGRADE: &str = "python3 -m pip install --user --upgrade envycontrol";
+
+// Where envycontrol came from decides how to update it: pip advice breaks a
+// package manager's files, and an AUR helper can't update a pipx install.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstallMethod {
+    Pipx,
+    Pip,
+    // Foreign packages (pacman -Qm) came from the AUR.
+    Pacman { package: String, aur: bool },
+    Dpkg(String),
+    Rpm(String),
+    // envycontrol_path in config.toml or --envycontrol-path.
+    Custom(PathBuf),
+}
+
+impl InstallMethod {
+    pub fn label(&self) -> String {
+        match self {
+            InstallMethod::Pipx => "pipx".to_string(),
+            InstallMethod::Pip => "pip".to_string(),
+            InstallMethod::Pacman { package, aur: true } => format!("AUR package {}", package),
+            InstallMethod::Pacman { package, .. } => format!("pacman package {}", package),
+            InstallMethod::Dpkg(package) => format!("deb package {}", package),
+            InstallMethod::Rpm(package) => format!("rpm package {}", package),
+            InstallMethod::Custom(path) => path.display().to_string(),
+        }
+    }
+
+    // Only pip and pipx installs belong to the user, so only those are
+    // updated from the TUI.
+    pub fn update_command(&self) -> Option<&'static str> {
+        match self {
+            InstallMethod::Pipx => Some("pipx upgrade envycontrol"),
+            InstallMethod::Pip => Some(PIP_UPGRADE),
+            _ => None,
+        }
+    }
+
+    pub fn update_hint(&self) -> String {
+        match self {
+            InstallMethod::Pipx | InstallMethod::Pip => {
+                format!("run {}", self.update_command().unwrap_or(PIP_UPGRADE))
+            }
+            InstallMethod::Pacman { package, aur: true } => {
+                format!("update it with your AUR helper, e.g. yay -S {}", package)
+            }
+            InstallMethod::Pacman { .. } => "update it with sudo pacman -Syu".to_string(),
+            InstallMethod::Dpkg(_) => {
+                "install the latest .deb from https://github.com/bayasdev/envycontrol/releases"
+                    .to_string()
+            }
+            InstallMethod::Rpm(package) => {
+                format!(
+                    "update it with {} {}",
+                    distro::current().rpm_update(),
+                    package
+                )
+            }
+            InstallMethod::Custom(path) => format!("update the copy at {}", path.display()),
+        }
+    }
+}
+
+// None when the origin can't be told, e.g. a script copied by hand.
+pub fn install_method() -> Option<InstallMethod> {
+    if let Some(path) = custom_path() {
+        return Some(InstallMethod::Custom(path));
+    }
+    let invocation = invocation()?;
+    let path = if invocation.len() == 1 {
+        invocation[0].clone()
+    } else {
+        // python3 -m envycontrol: where the module lives.
+        let output = host::command("python3")
+            .args(["-c", "import envycontrol; print(envycontrol.__file__)"])
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    };
+    // ~/.local/bin/envycontrol is a symlink into the venv for pipx.
+    let resolved = host::command("readlink")
+        .args(["-f", &path])
+        .stderr(Stdio::null())
         .output()
-        .map(|o| o.status.success())
-        .unwrap_or(false)
+        .ok()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|resolved| !resolved.is_empty())
+        .unwrap_or(path);
+    classify(&resolved)
+}
+
+fn classify(path: &str) -> Option<InstallMethod> {
+    if path.contains("/pipx/") {
+        return Some(InstallMethod::Pipx);
+    }
+    let owner = |program: &str, args: &[&str]| {
+        let output = host::command(program)
+            .args(args)
+            .arg(path)
+            .stderr(Stdio::null())
+            .output()
+            .ok()
+            .filter(|output| output.status.success())?;
+        let owner = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (!owner.is_empty()).then_some(owner)
+    };
+    if let Some(package) = owner("pacman", &["-Qqo"]) {
+        let aur = host::command("pacman")
+            .args(["-Qqm", &package])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+        return Some(InstallMethod::Pacman { package, aur });
+    }
+    // "envycontrol: /usr/bin/envycontrol"
+    if let Some(owner) = owner("dpkg", &["-S"]) {
+        let package = owner.split(':').next().unwrap_or_default().to_string();
+        return Some(InstallMethod::Dpkg(package));
+    }
+    if let Some(package) = owner("rpm", &["-qf", "--queryformat", "%{NAME}"]) {
+        return Some(InstallMethod::Rpm(package));
+    }
+    // Not a package's file: pip put it there, for the user or with sudo.
+    let home = env::var("HOME").unwrap_or_default();
+    (path.starts_with("/usr/local/") || (!home.is_empty() && path.starts_with(&home)))
+        .then_some(InstallMethod::Pip)
+}
+
+// Streams the installer output line by line to `output`.
+pub fn update(output: &dyn Fn(String)) -> Result<String> {
+    let command = match install_method() {
+        Some(method) => method.update_command().ok_or_else(|| {
+            anyhow!(
+                "envycontrol comes from {}: {}",
+                method.label(),
+                method.update_hint()
+            )
+        })?,
+        None => PIP_UPGRADE,
+    };
+    logging::info(&format!("Running: {}", command));
+
+    let mut child = host::command("sh")
+        .arg("-c")
+        .arg(format!("{} 2>&1", command))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let mut last_line = String::new();
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines().map_while(|l| l.ok()) {
+            logging::info(&line);
+            last_line = line.clone();
+            output(line);
+        }
+    }
+
+    if child.wait()?.success() {
+        forget_invocation();
+        let version = version().unwrap_or_else(|| "unknown version".to_string());
+        logging::info(&format!("Updated envycontrol to {}", version));
+        Ok(format!("envycontrol updated ({}).", version))
+    } else {
+        logging::error(&format!("Failed to update envycontrol: {}", last_line));
+        Err(anyhow!("Failed to update envycontrol: {}", last_line))
+    }
 }
 
 pub fn reboot() -> Result<()> {
-    Command::new("systemctl").arg("reboot").spawn()?;
+    logging::info("Running: systemctl reboot");
+    host::command("systemctl").arg("reboot").spawn()?;
     Ok(())
 }
 
+// Utilization, encoder, clocks and link are only shown on the Monitor tab.
 #[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "monitor"), allow(dead_code))]
 pub struct GpuInfo {
     pub name: String,
     pub temperature: String,
     pub memory_used: String,
     pub memory_total: String,
+    pub utilization: Option<u32>,
+    // Watts, rounded.
+    pub power_draw: Option<u32>,
+    pub encoder: Option<u32>,
+    pub decoder: Option<u32>,
+    pub clocks: Option<Clocks>,
+    pub link: Option<PcieLink>,
+}
+
+// Current and maximum clocks in MHz.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clocks {
+    pub graphics: u32,
+    pub memory: u32,
+    pub max_graphics: u32,
+    pub max_memory: u32,
+    pub pstate: String,
+}
+
+#[cfg_attr(not(feature = "monitor"), allow(dead_code))]
+impl Clocks {
+    // P0-P2 are the high performance states; an idle GPU sitting in one is
+    // usually kept awake by some process.
+    pub fn stuck_high(&self, utilization: Option<u32>) -> bool {
+        matches!(self.pstate.as_str(), "P0" | "P1" | "P2") && utilization.is_some_and(|u| u < 5)
+    }
+}
+
+// Current and maximum PCIe generation and lane count. A link that stays at full
+// speed while idle means the dGPU never gets to power down.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PcieLink {
+    pub generation: u32,
+    pub max_generation: u32,
+    pub width: u32,
+    pub max_width: u32,
+}
+
+#[cfg_attr(not(feature = "monitor"), allow(dead_code))]
+impl PcieLink {
+    pub fn display(&self) -> String {
+        format!(
+            "Gen{} x{} (max Gen{} x{})",
+            self.generation, self.width, self.max_generation, self.max_width
+        )
+    }
 }
 
 impl GpuInfo {
     pub fn memory_display(&self) -> String {
+        if self.memory_total == "?" {
+            return "N/A".to_string();
+        }
         format!("{} / {} MiB", self.memory_used, self.memory_total)
     }
+
+    #[cfg(feature = "monitor")]
+    pub fn sample(&self) -> Option<Sample> {
+        let temperature = self.temperature.trim_end_matches("°C").parse().ok()?;
+        Some(Sample::now(temperature, self.utilization?, self.power_draw))
+    }
 }
 
-pub fn query_gpu_info() -> Option<GpuInfo> {
-    let output = Command::new("nvidia-smi")
-        .args([
-            "--query-gpu=name,temperature.gpu,memory.used,memory.total",
-            "--format=csv,noheader,nounits",
-        ])
+// `nvidia-smi -q -x` reports every field in one call, in a format that stays
+// the same across locales and driver versions.
+fn query_smi() -> Option<xml::Element> {
+    let output = host::command("nvidia-smi")
+        .args(["-q", "-x"])
         .output()
         .ok()?;
-
     if !output.status.success() {
         return None;
     }
+    xml::parse(&String::from_utf8_lossy(&output.stdout))
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let parts: Vec<&str> = stdout.trim().split(',').map(|s| s.trim()).collect();
+// nvidia-smi wakes a runtime-suspended dGPU, so polling it would defeat RTD3;
+// sysfs and /proc still give the name without waking it.
+pub fn query_gpu_info() -> Option<GpuInfo> {
+    let suspended = system::dgpu_runtime_status().as_deref() == Some("suspended");
+    if !suspended {
+        if let Some(info) = query_smi_gpu_info() {
+            return Some(info);
+        }
+    }
+    query_sysfs_gpu_info(suspended)
+}
 
-    if parts.len() >= 4 {
-        Some(GpuInfo {
-            name: parts[0].to_string(),
-            temperature: format!("{}°C", parts[1]),
-            memory_used: parts[2].to_string(),
-            memory_total: parts[3].to_string(),
+fn query_sysfs_gpu_info(suspended: bool) -> Option<GpuInfo> {
+    let device = system::nvidia_pci_device()?;
+    let bus_id = device.file_name()?.to_string_lossy().to_string();
+    let name = fs::read_to_string(
+        Path::new("/proc/driver/nvidia/gpus")
+            .join(&bus_id)
+            .join("information"),
+    )
+    .ok()
+    .and_then(|info| {
+        info.lines().find_map(|line| {
+            line.strip_prefix("Model:")
+                .map(|model| model.trim().to_string())
         })
+    })
+    .unwrap_or_else(|| format!("NVIDIA GPU ({})", bus_id));
+    // Only nouveau registers a hwmon sensor; the proprietary driver doesn't.
+    let temperature = fs::read_dir(device.join("hwmon"))
+        .ok()
+        .and_then(|mut entries| entries.next())
+        .and_then(|entry| entry.ok())
+        .and_then(|entry| fs::read_to_string(entry.path().join("temp1_input")).ok())
+        .and_then(|value| value.trim().parse::<u32>().ok())
+        .map(|millidegrees| format!("{}°C", millidegrees / 1000));
+    Some(GpuInfo {
+        name,
+        temperature: temperature
+            .unwrap_or_else(|| if suspended { "suspended" } else { "N/A" }.to_string()),
+        memory_used: "?".to_string(),
+        memory_total: "?".to_string(),
+        utilization: None,
+        power_draw: None,
+        encoder: None,
+        decoder: None,
+        clocks: None,
+        // Reading the link state of a suspended device would wake it up.
+        link: if suspended {
+            None
+        } else {
+            sysfs_pcie_link(&device)
+        },
+    })
+}
+
+fn sysfs_pcie_link(device: &Path) -> Option<PcieLink> {
+    let read = |file: &str| fs::read_to_string(device.join(file)).ok();
+    // e.g. "8.0 GT/s PCIe"
+    let generation = |file: &str| {
+        let speed: f32 = read(file)?.split_whitespace().next()?.parse().ok()?;
+        [2.5, 5.0, 8.0, 16.0, 32.0, 64.0]
+            .iter()
+            .position(|&rate| (speed - rate).abs() < 0.1)
+            .map(|index| index as u32 + 1)
+    };
+    let width = |file: &str| read(file)?.trim().parse().ok();
+    Some(PcieLink {
+        generation: generation("current_link_speed")?,
+        max_generation: generation("max_link_speed")?,
+        width: width("current_link_width")?,
+        max_width: width("max_link_width")?,
+    })
+}
+
+fn query_smi_gpu_info() -> Option<GpuInfo> {
+    let log = query_smi()?;
+    let gpu = log.child("gpu")?;
+    let number = |path: &str| gpu.number(path);
+    let clocks = (|| {
+        Some(Clocks {
+            graphics: number("clocks/sm_clock")?,
+            memory: number("clocks/mem_clock")?,
+            max_graphics: number("max_clocks/sm_clock")?,
+            max_memory: number("max_clocks/mem_clock")?,
+            pstate: gpu.text("performance_state")?.to_string(),
+        })
+    })();
+    // Widths are reported as e.g. "16x".
+    let width = |path: &str| gpu.text(path)?.trim_end_matches('x').parse().ok();
+    let link = (|| {
+        Some(PcieLink {
+            generation: number("pci/pci_gpu_link_info/pcie_gen/current_link_gen")?,
+            max_generation: number("pci/pci_gpu_link_info/pcie_gen/max_link_gen")?,
+            width: width("pci/pci_gpu_link_info/link_widths/current_link_width")?,
+            max_width: width("pci/pci_gpu_link_info/link_widths/max_link_width")?,
+        })
+    })();
+    let megabytes = |path: &str| {
+        number(path)
+            .map(|value| value.to_string())
+            .unwrap_or_else(|| "?".to_string())
+    };
+    Some(GpuInfo {
+        name: gpu.text("product_name")?.to_string(),
+        temperature: number("temperature/gpu_temp")
+            .map(|value| format!("{}°C", value))
+            .unwrap_or_else(|| "N/A".to_string()),
+        memory_used: megabytes("fb_memory_usage/used"),
+        memory_total: megabytes("fb_memory_usage/total"),
+        utilization: number("utilization/gpu_util"),
+        // e.g. "12.34 W"; drivers before 530 report it under power_readings.
+        power_draw: ["gpu_power_readings/power_draw", "power_readings/power_draw"]
+            .iter()
+            .find_map(|path| {
+                gpu.text(path)?
+                    .split_whitespace()
+                    .next()?
+                    .parse::<f32>()
+                    .ok()
+            })
+            .map(|watts| watts.round() as u32),
+        encoder: number("utilization/encoder_util"),
+        decoder: number("utilization/decoder_util"),
+        clocks,
+        link,
+    })
+}
+
+pub fn query_persistence_mode() -> Option<bool> {
+    match query_smi()?.child("gpu")?.text("persistence_mode")? {
+        "Enabled" => Some(true),
+        "Disabled" => Some(false),
+        _ => None,
+    }
+}
+
+pub fn set_persistence_mode(enabled: bool) -> Result<String> {
+    logging::info(&format!("Running: nvidia-smi -pm {}", u8::from(enabled)));
+    let output = system::privileged()
+        .args(["nvidia-smi", "-pm", if enabled { "1" } else { "0" }])
+        .output()?;
+
+    if output.status.success() {
+        Ok(format!(
+            "Persistence mode {}.",
+            if enabled { "enabled" } else { "disabled" }
+        ))
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        logging::error(&format!("Failed to change persistence mode: {}", stderr));
+        Err(anyhow!("Failed to change persistence mode: {}", stderr))
+    }
+}
+
+// nvidia-settings needs an X session on the NVIDIA GPU, so this is None
+// everywhere else.
+pub fn query_powermizer_mode() -> Option<bool> {
+    let output = host::command("nvidia-settings")
+        .args(["-t", "-q", "[gpu:0]/GpuPowerMizerMode"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    // 0 adaptive, 1 prefer maximum performance, 2 auto
+    match String::from_utf8_lossy(&output.stdout).trim() {
+        "1" => Some(true),
+        "0" | "2" => Some(false),
+        _ => None,
+    }
+}
+
+pub fn set_powermizer_mode(max_performance: bool) -> Result<String> {
+    let assignment = format!("[gpu:0]/GpuPowerMizerMode={}", u8::from(max_performance));
+    logging::info(&format!("Running: nvidia-settings -a {}", assignment));
+    let output = host::command("nvidia-settings")
+        .args(["-a", &assignment])
+        .output()?;
+
+    if output.status.success() {
+        Ok(format!(
+            "PowerMizer set to {}.",
+            if max_performance {
+                "prefer maximum performance"
+            } else {
+                "adaptive"
+            }
+        ))
     } else {
-        None
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        logging::error(&format!("Failed to change PowerMizer mode: {}", stderr));
+        Err(anyhow!("Failed to change PowerMizer mode: {}", stderr))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GpuProcess {
+    pub pid: u32,
+    pub name: String,
+    pub used_memory: String,
+}
+
+// Compute processes only: graphics clients such as the X server on the dGPU
+// are expected in nvidia mode.
+pub fn query_gpu_processes() -> Vec<GpuProcess> {
+    let Some(log) = query_smi() else {
+        return Vec::new();
+    };
+    log.children("gpu")
+        .filter_map(|gpu| gpu.child("processes"))
+        .flat_map(|processes| processes.children("process_info"))
+        .filter(|process| process.text("type").is_some_and(|kind| kind.contains('C')))
+        .filter_map(|process| {
+            Some(GpuProcess {
+                pid: process.number("pid")?,
+                name: process.text("process_name")?.to_string(),
+                used_memory: process.number("used_memory")?.to_string(),
+            })
+        })
+        .collect()
+}
+
+// False when kill was refused, e.g. for a process of another user.
+pub fn terminate_process(pid: u32) -> Result<bool> {
+    logging::info(&format!("Terminating process {}", pid));
+    let status = host::command("kill")
+        .args(["-TERM", &pid.to_string()])
+        .stderr(Stdio::null())
+        .status()?;
+    Ok(status.success())
+}
+
+// Processes owned by other users (e.g. a system CUDA service) need elevation.
+pub fn terminate_privileged(pids: &[u32], output: &dyn Fn(String)) -> Result<String> {
+    let pids: Vec<String> = pids.iter().map(u32::to_string).collect();
+    let command_line = format!("kill -TERM {}", pids.join(" "));
+    match system::run_privileged(&command_line, "Terminating processes on the dGPU", output) {
+        Ok(()) => Ok(format!("Terminated process {}", pids.join(", "))),
+        Err(e) => {
+            logging::error(&format!(
+                "Failed to terminate process {}: {}",
+                pids.join(", "),
+                e
+            ));
+            Err(anyhow!(
+                "Could not terminate process {}: {}",
+                pids.join(", "),
+                e
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nvidia(extra_args: &[&str]) -> SwitchOptions {
+        SwitchOptions {
+            mode: GraphicsMode::Nvidia,
+            rtd3_enabled: false,
+            rtd3_level: Rtd3Level::FineGrained,
+            force_comp: false,
+            coolbits_enabled: true,
+            coolbits_value: 28,
+            extra_args: extra_args.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+
+    fn write(root: &Path, path: &str, contents: &str) {
+        let path = under(root, path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn extra_args_override_the_selected_options() {
+        let options = nvidia(&["--coolbits=24", "--force-comp", "--dm", "sddm"]);
+        assert_eq!(
+            options.effective_option_args(),
+            ["--force-comp", "--coolbits", "24"]
+        );
+
+        let hybrid = SwitchOptions {
+            mode: GraphicsMode::Hybrid,
+            ..nvidia(&["--rtd3", "--verbose"])
+        };
+        assert_eq!(hybrid.effective_option_args(), ["--rtd3", "2"]);
+        assert_eq!(
+            hybrid.written_files(None),
+            [
+                "/etc/modprobe.d/nvidia.conf",
+                "/lib/udev/rules.d/80-nvidia-pm.rules"
+            ]
+        );
+    }
+
+    #[test]
+    fn verify_switch_reads_the_files_under_root() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        let options = nvidia(&["--coolbits", "24"]);
+        write(
+            root,
+            "/etc/modprobe.d/nvidia.conf",
+            "options nvidia-drm modeset=1\n",
+        );
+        write(
+            root,
+            "/etc/X11/xorg.conf",
+            "Section \"Device\"\n    Driver \"nvidia\"\n    Option \"Coolbits\" \"24\"\nEndSection\n",
+        );
+        write(root, "/etc/X11/xorg.conf.d/10-nvidia.conf", "");
+        assert_eq!(verify_switch(root, &options, None), Vec::<String>::new());
+
+        write(
+            root,
+            "/etc/modprobe.d/blacklist-nvidia.conf",
+            "blacklist nvidia\n",
+        );
+        write(
+            root,
+            "/etc/modprobe.d/nvidia.conf",
+            "# options nvidia-drm modeset=1\n",
+        );
+        assert_eq!(
+            verify_switch(root, &nvidia(&[]), Some("sddm")),
+            [
+                "/usr/share/sddm/scripts/Xsetup was not written",
+                "/etc/modprobe.d/blacklist-nvidia.conf is still there and keeps the driver from loading",
+                "/etc/modprobe.d/nvidia.conf doesn't enable nvidia-drm modeset",
+                "The written files have --coolbits 24 instead of --coolbits 28",
+            ]
+        );
     }
 }
@@ -0,0 +1,80 @@
+// Short explanations bundled with the binary, for when a broken graphics setup
+// leaves no browser or network to look things up.
+pub struct Page {
+    pub title: &'static str,
+    pub body: &'static str,
+}
+
+pub const PAGES: [Page; 6] = [
+    Page {
+        title: "Graphics modes",
+        body: "integrated: only the iGPU is used. The nvidia modules are blacklisted and the \
+               dGPU is removed from the PCI bus, which gives the best battery life. Displays \
+               wired to the dGPU stay dark.\n\n\
+               hybrid: the iGPU drives the desktop and applications can be offloaded to the dGPU \
+               (see PRIME render offload). With RTD3 the dGPU powers off while unused.\n\n\
+               nvidia: the dGPU renders everything and the iGPU only passes frames to the \
+               internal panel. Best performance and external display support, highest power \
+               draw.\n\n\
+               Every switch needs a reboot to take effect.",
+    },
+    Page {
+        title: "RTD3 levels",
+        body: "Runtime D3 lets the driver cut power to the dGPU while it is idle in hybrid mode. \
+               It needs a Turing (GTX 16xx / RTX 20xx) or newer GPU and firmware support.\n\n\
+               0  Disabled: the dGPU stays powered, idling at a few watts.\n\
+               1  Coarse-grained: powered off only when no application has it open.\n\
+               2  Fine-grained: also powered off between uses while applications keep it \
+               open. Recommended for Turing and newer.\n\
+               3  Fine-grained for Ampere and newer, which can keep video memory in \
+               self-refresh.\n\n\
+               Check /sys/bus/pci/devices/<dGPU>/power/runtime_status: \"suspended\" means it \
+               works. Anything holding the dGPU open (nvidia-smi, a display wired to it) keeps \
+               it awake.",
+    },
+    Page {
+        title: "Coolbits",
+        body: "Coolbits is a bit mask in xorg.conf that unlocks extra controls in \
+               nvidia-settings, nvidia mode only. Add the values together:\n\n\
+               1   Overclocking for old GeForce FX cards\n\
+               2   SLI on mismatched cards\n\
+               4   Manual fan control\n\
+               8   Clock offsets (overclocking) in the PowerMizer page\n\
+               16  Overvoltage\n\n\
+               28 (4 + 8 + 16) is the usual value. The controls only work in an X11 session \
+               running on the NVIDIA GPU; Wayland ignores xorg.conf.",
+    },
+    Page {
+        title: "Force composition pipeline",
+        body: "ForceCompositionPipeline makes the driver composite every frame before scanning \
+               it out, which removes screen tearing on X11 in nvidia mode.\n\n\
+               It costs a little input latency and GPU time, and is unnecessary when the \
+               desktop's compositor already syncs to vblank. ForceFullCompositionPipeline is a \
+               stronger variant that envycontrol doesn't set.\n\n\
+               Wayland sessions don't read xorg.conf, so the option has no effect there.",
+    },
+    Page {
+        title: "PRIME render offload",
+        body: "In hybrid mode the desktop runs on the iGPU and single applications can be \
+               rendered on the dGPU:\n\n\
+               prime-run <command>\n\n\
+               or, without the helper script:\n\n\
+               __NV_PRIME_RENDER_OFFLOAD=1 __GLX_VENDOR_LIBRARY_NAME=nvidia <command>\n\
+               __NV_PRIME_RENDER_OFFLOAD=1 <command>   (Vulkan)\n\n\
+               In Steam, set the launch options to: prime-run %command%\n\n\
+               Check which GPU renders with: glxinfo | grep \"OpenGL renderer\". The dGPU \
+               stays awake while an offloaded application runs.",
+    },
+    Page {
+        title: "Files",
+        body: "Settings live in ~/.config/envy-tui/ (or $XDG_CONFIG_HOME/envy-tui/):\n\n\
+               config.toml  profiles, login and power rules, key bindings\n\
+               theme.toml   colors\n\n\
+               Everything envy-tui writes itself is in ~/.local/state/envy-tui/ (or \
+               $XDG_STATE_HOME/envy-tui/): the log, the switch history, the Monitor tab's \
+               samples and the switch waiting for a reboot.\n\n\
+               The About popup (a) shows both directories on this system: o opens the \
+               config directory, s the state directory and y copies both paths. From a \
+               shell, envy-tui config path prints them.",
+    },
+];
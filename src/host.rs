@@ -0,0 +1,78 @@
+use std::env;
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+static MOCK_BACKEND: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sandbox {
+    None,
+    Flatpak,
+    Toolbox,
+    Distrobox,
+}
+
+impl Sandbox {
+    // Header badge for when commands are forwarded to the host.
+    pub fn badge(self) -> Option<&'static str> {
+        match self {
+            Sandbox::None => None,
+            Sandbox::Flatpak => Some("running in Flatpak, acting on host"),
+            Sandbox::Toolbox | Sandbox::Distrobox => Some("running in container, acting on host"),
+        }
+    }
+}
+
+pub fn sandbox() -> Sandbox {
+    static SANDBOX: OnceLock<Sandbox> = OnceLock::new();
+    *SANDBOX.get_or_init(detect)
+}
+
+fn detect() -> Sandbox {
+    if Path::new("/.flatpak-info").exists() {
+        Sandbox::Flatpak
+    } else if Path::new("/run/.toolboxenv").exists() {
+        Sandbox::Toolbox
+    } else if env::var_os("DISTROBOX_ENTER_PATH").is_some()
+        || (env::var_os("CONTAINER_ID").is_some() && Path::new("/run/.containerenv").exists())
+    {
+        Sandbox::Distrobox
+    } else {
+        Sandbox::None
+    }
+}
+
+// For --replay: every external command becomes `true`, which ignores its
+// arguments, prints nothing and succeeds, so nothing is queried or changed.
+pub fn use_mock_backend() {
+    MOCK_BACKEND.store(true, Ordering::Relaxed);
+}
+
+// Whether files under /etc are the host's: not inside a sandbox, and not in
+// a replay, where nothing was actually written.
+pub fn sees_host_files() -> bool {
+    !MOCK_BACKEND.load(Ordering::Relaxed) && sandbox() == Sandbox::None
+}
+
+// All external tools act on the host system, so inside a Flatpak or a
+// toolbox/distrobox container they are forwarded to it.
+pub fn command(program: &str) -> Command {
+    if MOCK_BACKEND.load(Ordering::Relaxed) {
+        return Command::new("true");
+    }
+    match sandbox() {
+        Sandbox::Flatpak | Sandbox::Toolbox => {
+            let mut command = Command::new("flatpak-spawn");
+            command.arg("--host").arg(program);
+            command
+        }
+        Sandbox::Distrobox => {
+            let mut command = Command::new("distrobox-host-exec");
+            command.arg(program);
+            command
+        }
+        Sandbox::None => Command::new(program),
+    }
+}
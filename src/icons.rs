@@ -0,0 +1,97 @@
+use crate::app::GraphicsMode;
+use crate::config::{IconConfig, IconSet};
+
+pub struct Icons {
+    pub set: IconSet,
+    pub integrated: String,
+    pub hybrid: String,
+    pub nvidia: String,
+    pub gpu: String,
+    pub temperature: String,
+    pub memory: String,
+    pub utilization: String,
+    pub info: String,
+    pub warning: String,
+    pub current: String,
+    pub pending: String,
+    pub selector: String,
+    pub check: String,
+    pub separator: String,
+}
+
+impl Default for Icons {
+    fn default() -> Self {
+        Icons::preset(IconSet::Nerd)
+    }
+}
+
+impl Icons {
+    pub fn preset(set: IconSet) -> Self {
+        let [integrated, hybrid, nvidia, gpu, temperature, memory, utilization, info, warning, current, pending, selector, check, separator] =
+            match set {
+                IconSet::Nerd => [
+                    "󰍹", "󰢮", "󰾲", "󰍹", "🌡", "󰍛", "󰓅", "󰋼", "⚠", "●", "◌", "▶", "✓", "│",
+                ],
+                IconSet::Emoji => [
+                    "💻", "🔀", "🎮", "🖥", "🌡", "💾", "📊", "ℹ", "⚠", "●", "◌", "▶", "✓", "│",
+                ],
+                IconSet::Ascii => [
+                    "[I]", "[H]", "[N]", "GPU", "T", "MEM", "UTIL", "i", "!", "*", "~", ">", "x",
+                    "|",
+                ],
+            };
+        Icons {
+            set,
+            integrated: integrated.to_string(),
+            hybrid: hybrid.to_string(),
+            nvidia: nvidia.to_string(),
+            gpu: gpu.to_string(),
+            temperature: temperature.to_string(),
+            memory: memory.to_string(),
+            utilization: utilization.to_string(),
+            info: info.to_string(),
+            warning: warning.to_string(),
+            current: current.to_string(),
+            pending: pending.to_string(),
+            selector: selector.to_string(),
+            check: check.to_string(),
+            separator: separator.to_string(),
+        }
+    }
+
+    // The [icons] set, with each glyph set in config.toml replacing the
+    // preset's.
+    pub fn from_config(config: &IconConfig) -> Self {
+        let mut icons = Icons::preset(config.set);
+        let overrides = [
+            (&mut icons.integrated, &config.integrated),
+            (&mut icons.hybrid, &config.hybrid),
+            (&mut icons.nvidia, &config.nvidia),
+            (&mut icons.gpu, &config.gpu),
+            (&mut icons.temperature, &config.temperature),
+            (&mut icons.memory, &config.memory),
+            (&mut icons.utilization, &config.utilization),
+            (&mut icons.info, &config.info),
+            (&mut icons.warning, &config.warning),
+            (&mut icons.current, &config.current),
+            (&mut icons.pending, &config.pending),
+            (&mut icons.selector, &config.selector),
+            (&mut icons.check, &config.check),
+            (&mut icons.separator, &config.separator),
+        ];
+        for (icon, value) in overrides {
+            if let Some(value) = value {
+                *icon = value.clone();
+            }
+        }
+        icons
+    }
+
+    pub fn mode(&self, mode: GraphicsMode) -> &str {
+        match mode {
+            GraphicsMode::Integrated => &self.integrated,
+            GraphicsMode::Hybrid => &self.hybrid,
+            GraphicsMode::Nvidia => &self.nvidia,
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use crossterm::event::KeyCode;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputEvent {
+    Changed,
+    Submitted,
+    Cancelled,
+    Ignored,
+}
+
+#[derive(Clone, Default)]
+pub struct TextInput {
+    value: String,
+    cursor: usize,
+    max_len: Option<usize>,
+    // For passwords: drawn as dots, and kept out of Debug output.
+    masked: bool,
+}
+
+impl fmt::Debug for TextInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value: &dyn fmt::Debug = if self.masked {
+            &"<hidden>"
+        } else {
+            &self.value
+        };
+        f.debug_struct("TextInput")
+            .field("value", value)
+            .field("cursor", &self.cursor)
+            .field("max_len", &self.max_len)
+            .field("masked", &self.masked)
+            .finish()
+    }
+}
+
+impl TextInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_value(value: &str) -> Self {
+        Self {
+            value: value.to_string(),
+            cursor: value.chars().count(),
+            ..Self::default()
+        }
+    }
+
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = Some(max_len);
+        self
+    }
+
+    pub fn masked(mut self) -> Self {
+        self.masked = true;
+        self
+    }
+
+    pub fn is_masked(&self) -> bool {
+        self.masked
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    // What the prompt shows.
+    pub fn display(&self) -> String {
+        if self.masked {
+            "•".repeat(self.value.chars().count())
+        } else {
+            self.value.clone()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.value.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.value.clear();
+        self.cursor = 0;
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    fn byte_index(&self, char_index: usize) -> usize {
+        self.value
+            .char_indices()
+            .nth(char_index)
+            .map(|(i, _)| i)
+            .unwrap_or(self.value.len())
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) -> InputEvent {
+        match code {
+            KeyCode::Char(c) => {
+                if self
+                    .max_len
+                    .is_some_and(|max| self.value.chars().count() >= max)
+                {
+                    return InputEvent::Ignored;
+                }
+                let index = self.byte_index(self.cursor);
+                self.value.insert(index, c);
+                self.cursor += 1;
+                InputEvent::Changed
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                let index = self.byte_index(self.cursor);
+                self.value.remove(index);
+                InputEvent::Changed
+            }
+            KeyCode::Delete if self.cursor < self.value.chars().count() => {
+                let index = self.byte_index(self.cursor);
+                self.value.remove(index);
+                InputEvent::Changed
+            }
+            KeyCode::Left => {
+                self.cursor = self.cursor.saturating_sub(1);
+                InputEvent::Ignored
+            }
+            KeyCode::Right => {
+                self.cursor = (self.cursor + 1).min(self.value.chars().count());
+                InputEvent::Ignored
+            }
+            KeyCode::Home => {
+                self.cursor = 0;
+                InputEvent::Ignored
+            }
+            KeyCode::End => {
+                self.cursor = self.value.chars().count();
+                InputEvent::Ignored
+            }
+            KeyCode::Enter => InputEvent::Submitted,
+            KeyCode::Esc => InputEvent::Cancelled,
+            _ => InputEvent::Ignored,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NumberInput {
+    text: TextInput,
+    min: i64,
+    max: i64,
+}
+
+impl NumberInput {
+    pub fn new(value: i64, min: i64, max: i64) -> Self {
+        Self {
+            text: TextInput::with_value(&value.to_string()),
+            min,
+            max,
+        }
+    }
+
+    pub fn value(&self) -> Option<i64> {
+        self.text
+            .value()
+            .parse()
+            .ok()
+            .filter(|v| (self.min..=self.max).contains(v))
+    }
+
+    pub fn text(&self) -> &str {
+        self.text.value()
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.text.cursor()
+    }
+
+    pub fn range_hint(&self) -> String {
+        format!("{}–{}", self.min, self.max)
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) -> InputEvent {
+        match code {
+            KeyCode::Char(c) if c.is_ascii_digit() || (c == '-' && self.min < 0) => {
+                self.text.handle_key(code)
+            }
+            KeyCode::Char(_) => InputEvent::Ignored,
+            KeyCode::Up | KeyCode::Down => {
+                let current = self.text.value().parse().unwrap_or(self.min);
+                let next = if code == KeyCode::Up {
+                    current.saturating_add(1)
+                } else {
+                    current.saturating_sub(1)
+                };
+                self.text = TextInput::with_value(&next.clamp(self.min, self.max).to_string());
+                InputEvent::Changed
+            }
+            // Only allow submitting values that are in range.
+            KeyCode::Enter if self.value().is_none() => InputEvent::Ignored,
+            _ => self.text.handle_key(code),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SelectList {
+    items: Vec<String>,
+    selected: usize,
+}
+
+impl SelectList {
+    pub fn new(items: Vec<String>, selected: usize) -> Self {
+        let selected = selected.min(items.len().saturating_sub(1));
+        Self { items, selected }
+    }
+
+    pub fn items(&self) -> &[String] {
+        &self.items
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) -> InputEvent {
+        if self.items.is_empty() {
+            return match code {
+                KeyCode::Esc => InputEvent::Cancelled,
+                _ => InputEvent::Ignored,
+            };
+        }
+        match code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.selected = (self.selected + self.items.len() - 1) % self.items.len();
+                InputEvent::Changed
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1) % self.items.len();
+                InputEvent::Changed
+            }
+            KeyCode::Enter => InputEvent::Submitted,
+            KeyCode::Esc => InputEvent::Cancelled,
+            _ => InputEvent::Ignored,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum InputWidget {
+    Text(TextInput),
+    Number(NumberInput),
+    Select(SelectList),
+}
+
+impl InputWidget {
+    pub fn is_masked(&self) -> bool {
+        matches!(self, InputWidget::Text(input) if input.is_masked())
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) -> InputEvent {
+        match self {
+            InputWidget::Text(input) => input.handle_key(code),
+            InputWidget::Number(input) => input.handle_key(code),
+            InputWidget::Select(list) => list.handle_key(code),
+        }
+    }
+}
@@ -0,0 +1,542 @@
+use crate::app::AppTab;
+use anyhow::{anyhow, Result};
+use crossterm::event::KeyCode;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    PreviousTab,
+    NextTab,
+    Refresh,
+    About,
+    Help,
+    EditTheme,
+    UpdateEnvycontrol,
+    Quit,
+    Up,
+    Down,
+    SwitchPanel,
+    Apply,
+    Toggle,
+    Edit,
+    OptionDetails,
+    SaveProfile,
+    ExportCommand,
+    Compact,
+    Reset,
+    Overclock,
+    EnableServices,
+    TogglePersistenced,
+    TogglePersistenceMode,
+    ScrollUp,
+    ScrollDown,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+    Search,
+    NextMatch,
+    PreviousMatch,
+    CopyVisible,
+}
+
+impl Action {
+    // Footer order: tab-specific actions first, then the global ones.
+    const ALL: [Action; 33] = [
+        Action::Up,
+        Action::Down,
+        Action::SwitchPanel,
+        Action::Apply,
+        Action::Toggle,
+        Action::Edit,
+        Action::OptionDetails,
+        Action::SaveProfile,
+        Action::Compact,
+        Action::Reset,
+        Action::Overclock,
+        Action::ExportCommand,
+        Action::EnableServices,
+        Action::TogglePersistenced,
+        Action::TogglePersistenceMode,
+        Action::ScrollUp,
+        Action::ScrollDown,
+        Action::PageUp,
+        Action::PageDown,
+        Action::Top,
+        Action::Bottom,
+        Action::Search,
+        Action::NextMatch,
+        Action::PreviousMatch,
+        Action::CopyVisible,
+        Action::PreviousTab,
+        Action::NextTab,
+        Action::Refresh,
+        Action::About,
+        Action::Help,
+        Action::EditTheme,
+        Action::UpdateEnvycontrol,
+        Action::Quit,
+    ];
+
+    // None means the action is available on every tab.
+    pub fn tab(self) -> Option<AppTab> {
+        match self {
+            Action::PreviousTab
+            | Action::NextTab
+            | Action::Refresh
+            | Action::About
+            | Action::Help
+            | Action::EditTheme
+            | Action::UpdateEnvycontrol
+            | Action::Quit => None,
+            Action::Up
+            | Action::Down
+            | Action::SwitchPanel
+            | Action::Apply
+            | Action::Toggle
+            | Action::Edit
+            | Action::OptionDetails
+            | Action::SaveProfile
+            | Action::ExportCommand
+            | Action::Compact
+            | Action::Reset
+            | Action::Overclock => Some(AppTab::Switch),
+            Action::EnableServices | Action::TogglePersistenced | Action::TogglePersistenceMode => {
+                Some(AppTab::System)
+            }
+            Action::ScrollUp
+            | Action::ScrollDown
+            | Action::PageUp
+            | Action::PageDown
+            | Action::Top
+            | Action::Bottom
+            | Action::Search
+            | Action::NextMatch
+            | Action::PreviousMatch
+            | Action::CopyVisible => Some(AppTab::Logs),
+        }
+    }
+
+    fn default_keys(self) -> Vec<KeyCode> {
+        match self {
+            Action::PreviousTab => vec![KeyCode::Char('[')],
+            Action::NextTab => vec![KeyCode::Char(']')],
+            Action::Refresh => vec![KeyCode::Char('R')],
+            Action::About => vec![KeyCode::Char('a')],
+            Action::Help => vec![KeyCode::Char('?')],
+            Action::EditTheme => vec![KeyCode::Char('T')],
+            Action::UpdateEnvycontrol => vec![KeyCode::Char('U')],
+            Action::Quit => vec![KeyCode::Char('q'), KeyCode::Esc],
+            Action::Up | Action::ScrollUp => vec![KeyCode::Up, KeyCode::Char('k')],
+            Action::Down | Action::ScrollDown => vec![KeyCode::Down, KeyCode::Char('j')],
+            Action::SwitchPanel => vec![KeyCode::Tab],
+            Action::Apply => vec![KeyCode::Enter],
+            Action::Toggle => vec![KeyCode::Char(' ')],
+            Action::OptionDetails => vec![KeyCode::Enter, KeyCode::Char('i')],
+            Action::Edit | Action::EnableServices => vec![KeyCode::Char('e')],
+            Action::SaveProfile => vec![KeyCode::Char('S')],
+            Action::ExportCommand => vec![KeyCode::Char('x')],
+            Action::Compact => vec![KeyCode::Char('c')],
+            Action::Reset => vec![KeyCode::Char('r')],
+            Action::Overclock => vec![KeyCode::Char('O')],
+            Action::TogglePersistenced => vec![KeyCode::Char('p')],
+            Action::TogglePersistenceMode => vec![KeyCode::Char('P')],
+            Action::PageUp => vec![KeyCode::PageUp],
+            Action::PageDown => vec![KeyCode::PageDown],
+            Action::Top => vec![KeyCode::Char('g')],
+            Action::Bottom => vec![KeyCode::Char('G')],
+            Action::Search => vec![KeyCode::Char('/')],
+            Action::NextMatch => vec![KeyCode::Char('n')],
+            Action::PreviousMatch => vec![KeyCode::Char('N')],
+            Action::CopyVisible => vec![KeyCode::Char('y')],
+        }
+    }
+
+    // Footer label; actions without one are either hidden or shown together
+    // with the action they are paired with.
+    pub fn hint(self) -> Option<&'static str> {
+        match self {
+            Action::PreviousTab => Some("Tabs"),
+            Action::Refresh => Some("Refresh"),
+            Action::About => Some("About"),
+            Action::Help => Some("Help"),
+            Action::EditTheme => Some("Theme"),
+            Action::UpdateEnvycontrol => Some("Update envycontrol"),
+            Action::Quit => Some("Quit"),
+            Action::Up => Some("Navigate"),
+            Action::SwitchPanel => Some("Switch Panel"),
+            Action::Apply => Some("Apply"),
+            Action::Toggle => Some("Toggle"),
+            Action::Edit => Some("Edit"),
+            Action::OptionDetails => Some("Details"),
+            Action::SaveProfile => Some("Save"),
+            Action::ExportCommand => Some("Export"),
+            Action::Compact => Some("Compact"),
+            Action::Reset => Some("Reset"),
+            Action::Overclock => Some("Overclock"),
+            Action::EnableServices => Some("Enable Services"),
+            Action::TogglePersistenced => Some("Persistenced"),
+            Action::TogglePersistenceMode => Some("Persistence Mode"),
+            Action::ScrollUp => Some("Scroll"),
+            Action::Top => Some("Top/Bottom"),
+            Action::Search => Some("Search"),
+            Action::NextMatch => Some("Next/Prev"),
+            Action::CopyVisible => Some("Copy Visible"),
+            _ => None,
+        }
+    }
+
+    // Longer wording for the exported cheat sheet.
+    fn description(self) -> &'static str {
+        match self {
+            Action::PreviousTab => "Previous tab",
+            Action::NextTab => "Next tab",
+            Action::Refresh => "Re-read the current mode, GPU info and system state",
+            Action::About => "Versions, backend and session details",
+            Action::Help => "Offline help on modes, RTD3, Coolbits and PRIME offload",
+            Action::EditTheme => "Edit the colors in theme.toml with a live preview",
+            Action::UpdateEnvycontrol => "Update an outdated envycontrol",
+            Action::Quit => "Quit",
+            Action::Up => "Move up",
+            Action::Down => "Move down",
+            Action::SwitchPanel => "Switch between the mode and options panels",
+            Action::Apply => "Apply the selected mode",
+            Action::Toggle => "Toggle the selected option",
+            Action::Edit => "Edit the selected option value",
+            Action::OptionDetails => "Explain the selected option",
+            Action::SaveProfile => "Save the selection as a named profile",
+            Action::ExportCommand => "Copy the envycontrol command for the selection",
+            Action::Compact => "Toggle the compact layout",
+            Action::Reset => "Reset envycontrol",
+            Action::Overclock => "Set GPU and memory clock offsets (nvidia mode, Coolbits 8)",
+            Action::EnableServices => "Enable the NVIDIA suspend/resume services",
+            Action::TogglePersistenced => "Toggle nvidia-persistenced",
+            Action::TogglePersistenceMode => "Toggle GPU persistence mode",
+            Action::ScrollUp => "Scroll up",
+            Action::ScrollDown => "Scroll down",
+            Action::PageUp => "Page up",
+            Action::PageDown => "Page down",
+            Action::Top => "Jump to the oldest entry",
+            Action::Bottom => "Jump to the newest entry",
+            Action::Search => "Search the log",
+            Action::NextMatch => "Next match",
+            Action::PreviousMatch => "Previous match",
+            Action::CopyVisible => "Copy the visible log lines",
+        }
+    }
+
+    // The name used in the [keys] table of config.toml.
+    fn config_name(self) -> String {
+        let mut name = String::new();
+        for c in format!("{:?}", self).chars() {
+            if c.is_uppercase() && !name.is_empty() {
+                name.push('_');
+            }
+            name.push(c.to_ascii_lowercase());
+        }
+        name
+    }
+
+    fn pair(self) -> Option<Action> {
+        match self {
+            Action::PreviousTab => Some(Action::NextTab),
+            Action::Up => Some(Action::Down),
+            Action::ScrollUp => Some(Action::ScrollDown),
+            Action::Top => Some(Action::Bottom),
+            Action::NextMatch => Some(Action::PreviousMatch),
+            _ => None,
+        }
+    }
+}
+
+pub struct Binding {
+    pub action: Action,
+    pub keys: Vec<KeyCode>,
+}
+
+pub struct Keymap {
+    bindings: Vec<Binding>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            bindings: Action::ALL
+                .iter()
+                .map(|&action| Binding {
+                    action,
+                    keys: action.default_keys(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Keymap {
+    pub fn from_config(overrides: &BTreeMap<Action, Vec<String>>) -> Result<Self> {
+        let mut keymap = Keymap::default();
+        for (action, keys) in overrides {
+            let keys = keys
+                .iter()
+                .map(|key| parse_key(key))
+                .collect::<Result<Vec<_>>>()?;
+            if let Some(binding) = keymap.bindings.iter_mut().find(|b| b.action == *action) {
+                binding.keys = keys;
+            }
+        }
+        Ok(keymap)
+    }
+
+    // Pairs of actions a remap left on the same key where both can apply: the
+    // same tab, or a tab action hiding a global one. Shared default keys are
+    // deliberate (e.g. Enter applies or explains, whichever is available).
+    pub fn clashes(&self) -> Vec<(Action, Action, String)> {
+        let defaults = Keymap::default();
+        let mut clashes = Vec::new();
+        for (i, a) in self.bindings.iter().enumerate() {
+            for b in &self.bindings[i + 1..] {
+                let overlap = match (a.action.tab(), b.action.tab()) {
+                    (Some(x), Some(y)) => x == y,
+                    _ => true,
+                };
+                if !overlap {
+                    continue;
+                }
+                for &key in a.keys.iter().filter(|key| b.keys.contains(key)) {
+                    let default = defaults.keys(a.action).contains(&key)
+                        && defaults.keys(b.action).contains(&key);
+                    if !default {
+                        clashes.push((
+                            a.action,
+                            b.action,
+                            format!(
+                                "{} is bound to both {} and {}",
+                                key_display(key),
+                                a.action.config_name(),
+                                b.action.config_name()
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+        clashes
+    }
+
+    // Actions that share a key resolve to the first one available.
+    pub fn action(
+        &self,
+        tab: AppTab,
+        code: KeyCode,
+        available: impl Fn(Action) -> bool,
+    ) -> Option<Action> {
+        let bound = |b: &&Binding| b.keys.contains(&code) && available(b.action);
+        self.bindings
+            .iter()
+            .filter(|b| b.action.tab() == Some(tab))
+            .find(bound)
+            .or_else(|| {
+                self.bindings
+                    .iter()
+                    .filter(|b| b.action.tab().is_none())
+                    .find(bound)
+            })
+            .map(|b| b.action)
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.bindings
+            .iter()
+            .find(|b| b.action == action)
+            .map_or(&[], |b| b.keys.as_slice())
+    }
+
+    // The keymap including remaps from config.toml, grouped by tab, as a
+    // Markdown table or aligned plain text.
+    pub fn cheat_sheet(&self, markdown: bool) -> String {
+        let sections = [(None, "Everywhere")]
+            .into_iter()
+            .chain(AppTab::ALL.iter().map(|&tab| (Some(tab), tab.title())));
+
+        let mut out = String::new();
+        if markdown {
+            out.push_str("# envy-tui key bindings\n");
+        } else {
+            out.push_str("envy-tui key bindings\n");
+        }
+        for (tab, title) in sections {
+            let bindings: Vec<&Binding> = self
+                .bindings
+                .iter()
+                .filter(|b| b.action.tab() == tab && !b.keys.is_empty())
+                .collect();
+            if bindings.is_empty() {
+                continue;
+            }
+            if markdown {
+                out.push_str(&format!(
+                    "\n## {}\n\n| Keys | Action | Config name |\n|------|--------|-------------|\n",
+                    title
+                ));
+            } else {
+                out.push_str(&format!("\n{}\n", title));
+            }
+            for binding in bindings {
+                let keys = keys_display(&binding.keys);
+                let description = binding.action.description();
+                let name = binding.action.config_name();
+                if markdown {
+                    out.push_str(&format!(
+                        "| `{}` | {} | `{}` |\n",
+                        keys.replace('|', "\\|"),
+                        description,
+                        name
+                    ));
+                } else {
+                    out.push_str(&format!("  {:<14} {:<55} {}\n", keys, description, name));
+                }
+            }
+        }
+        out
+    }
+
+    // (keys, label) pairs for the footer of a tab, in binding order.
+    pub fn hints(
+        &self,
+        tab: AppTab,
+        available: impl Fn(Action) -> bool,
+    ) -> Vec<(String, &'static str)> {
+        self.bindings
+            .iter()
+            .filter(|b| b.action.tab().is_none_or(|t| t == tab) && available(b.action))
+            .filter_map(|b| {
+                let label = b.action.hint()?;
+                let keys = match b.action.pair() {
+                    Some(pair) => pair_display(&b.keys, self.keys(pair)),
+                    None => keys_display(&b.keys),
+                };
+                (!keys.is_empty()).then_some((keys, label))
+            })
+            .collect()
+    }
+}
+
+pub fn key_display(code: KeyCode) -> String {
+    match code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Up => "↑".to_string(),
+        KeyCode::Down => "↓".to_string(),
+        KeyCode::Left => "←".to_string(),
+        KeyCode::Right => "→".to_string(),
+        KeyCode::Enter => "Enter".to_string(),
+        KeyCode::Esc => "Esc".to_string(),
+        KeyCode::Tab => "Tab".to_string(),
+        KeyCode::Backspace => "Backspace".to_string(),
+        KeyCode::PageUp => "PgUp".to_string(),
+        KeyCode::PageDown => "PgDn".to_string(),
+        KeyCode::Home => "Home".to_string(),
+        KeyCode::End => "End".to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        other => format!("{:?}", other),
+    }
+}
+
+fn keys_display(keys: &[KeyCode]) -> String {
+    keys.iter()
+        .map(|&k| key_display(k))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// Shows paired actions together, e.g. "↑↓/kj" for up/down.
+fn pair_display(first: &[KeyCode], second: &[KeyCode]) -> String {
+    (0..first.len().max(second.len()))
+        .map(|i| {
+            let a = first.get(i).map(|&k| key_display(k)).unwrap_or_default();
+            let b = second.get(i).map(|&k| key_display(k)).unwrap_or_default();
+            if a.chars().count() <= 1 && b.chars().count() <= 1 {
+                format!("{}{}", a, b)
+            } else {
+                [a, b]
+                    .into_iter()
+                    .filter(|s| !s.is_empty())
+                    .collect::<Vec<_>>()
+                    .join("/")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// The name parse_key reads back, e.g. "space" or "pageup".
+pub fn key_name(code: KeyCode) -> Option<String> {
+    let name = match code {
+        KeyCode::Char(' ') => "space",
+        KeyCode::Char(c) => return Some(c.to_string()),
+        KeyCode::Up => "up",
+        KeyCode::Down => "down",
+        KeyCode::Left => "left",
+        KeyCode::Right => "right",
+        KeyCode::Enter => "enter",
+        KeyCode::Esc => "esc",
+        KeyCode::Tab => "tab",
+        KeyCode::Backspace => "backspace",
+        KeyCode::PageUp => "pageup",
+        KeyCode::PageDown => "pagedown",
+        KeyCode::Home => "home",
+        KeyCode::End => "end",
+        KeyCode::F(n) => return Some(format!("f{}", n)),
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
+pub fn parse_key(key: &str) -> Result<KeyCode> {
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(KeyCode::Char(c));
+    }
+
+    Ok(match key.to_lowercase().as_str() {
+        "space" => KeyCode::Char(' '),
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "enter" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        other => match other.strip_prefix('f').and_then(|n| n.parse().ok()) {
+            Some(n) if (1..=12).contains(&n) => KeyCode::F(n),
+            _ => return Err(anyhow!("Unknown key '{}' in [keys]", key)),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remap_onto_another_action_clashes() {
+        assert!(Keymap::default().clashes().is_empty());
+        let overrides = BTreeMap::from([(Action::Reset, vec!["R".to_string()])]);
+        let clashes = Keymap::from_config(&overrides).unwrap().clashes();
+        assert_eq!(
+            clashes,
+            [(
+                Action::Reset,
+                Action::Refresh,
+                "R is bound to both reset and refresh".to_string()
+            )]
+        );
+    }
+}
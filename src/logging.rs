@@ -0,0 +1,165 @@
+use crate::config::{LogConfig, LogLevel};
+use crate::state;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const LOG_FILE: &str = "envy-tui.log";
+const MAX_LOADED_LINES: usize = 2000;
+
+static SETTINGS: Mutex<Option<LogConfig>> = Mutex::new(None);
+
+pub fn log_path() -> Option<PathBuf> {
+    state::state_dir().map(|dir| dir.join(LOG_FILE))
+}
+
+pub fn info(message: &str) {
+    write(LogLevel::Info, "INFO", message);
+}
+
+pub fn warn(message: &str) {
+    write(LogLevel::Warn, "WARN", message);
+}
+
+pub fn error(message: &str) {
+    write(LogLevel::Error, "ERROR", message);
+}
+
+fn write(level: LogLevel, label: &str, message: &str) {
+    if level > settings().level {
+        return;
+    }
+    let Some(path) = log_path() else {
+        return;
+    };
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    rotate(&path);
+    let Ok(mut file) = OpenOptions::new().create(true).append(true).open(&path) else {
+        return;
+    };
+    for line in message.trim_end().lines() {
+        let _ = writeln!(file, "{} {:<5} {}", timestamp(), label, line);
+    }
+}
+
+// Applies the [logs] level and limits, and drops rotated files the limits no
+// longer allow.
+pub fn configure(config: &LogConfig, csv_log: Option<&Path>) {
+    if let Ok(mut settings) = SETTINGS.lock() {
+        *settings = Some(config.clone());
+    }
+    for path in log_path().as_deref().into_iter().chain(csv_log) {
+        prune(path, config);
+    }
+}
+
+fn settings() -> LogConfig {
+    SETTINGS
+        .lock()
+        .ok()
+        .and_then(|settings| settings.clone())
+        .unwrap_or_default()
+}
+
+// Moves `path` to `path.1`, shifting older files up, once it outgrows
+// max_size_kb or, with daily set, on the first write of a new (UTC) day.
+pub fn rotate(path: &Path) {
+    let config = settings();
+    let Ok(metadata) = fs::metadata(path) else {
+        return;
+    };
+    let too_big = config.max_size_kb > 0 && metadata.len() >= config.max_size_kb * 1024;
+    let new_day = config.daily
+        && metadata
+            .modified()
+            .is_ok_and(|modified| day(modified) < day(SystemTime::now()));
+    if !too_big && !new_day {
+        return;
+    }
+    if config.keep == 0 {
+        let _ = fs::remove_file(path);
+        return;
+    }
+    for index in (1..config.keep).rev() {
+        let _ = fs::rename(rotated(path, index), rotated(path, index + 1));
+    }
+    let _ = fs::rename(path, rotated(path, 1));
+    prune(path, &config);
+}
+
+fn prune(path: &Path, config: &LogConfig) {
+    let mut index = config.keep + 1;
+    while fs::remove_file(rotated(path, index)).is_ok() {
+        index += 1;
+    }
+    let Some(days) = config.max_age_days else {
+        return;
+    };
+    let max_age = Duration::from_secs(days * 86_400);
+    for index in 1..=config.keep {
+        let file = rotated(path, index);
+        let expired = fs::metadata(&file)
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|modified| modified.elapsed().is_ok_and(|age| age > max_age));
+        if expired {
+            let _ = fs::remove_file(file);
+        }
+    }
+}
+
+fn rotated(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+fn day(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86_400)
+        .unwrap_or(0)
+}
+
+pub fn read_recent() -> Vec<String> {
+    let Some(contents) = log_path().and_then(|path| fs::read_to_string(path).ok()) else {
+        return Vec::new();
+    };
+    let lines: Vec<&str> = contents.lines().collect();
+    let start = lines.len().saturating_sub(MAX_LOADED_LINES);
+    lines[start..].iter().map(|l| l.to_string()).collect()
+}
+
+fn timestamp() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let time = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        (time % 3600) / 60,
+        time % 60
+    )
+}
+
+// Howard Hinnant's days-to-civil algorithm; the log is written in UTC.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
@@ -1,242 +1,1819 @@
 mod app;
+mod cli;
+mod clipboard;
+mod config;
+mod distro;
+mod doctor;
 mod envycontrol;
+mod help;
+mod host;
+mod icons;
+mod input;
+mod keymap;
+mod logging;
+#[cfg(feature = "monitor")]
+mod monitor;
+#[cfg(feature = "overclock")]
+mod overclock;
+mod poll;
+mod probe;
+mod report;
+mod rules;
+mod session;
+mod state;
+mod system;
+mod task;
 mod theme;
 mod ui;
+mod validate;
+mod xml;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use ratatui::{backend::CrosstermBackend, Terminal};
+use futures_util::StreamExt;
+use ratatui::{backend::CrosstermBackend, layout::Position, Terminal, TerminalOptions, Viewport};
 use std::io;
-use std::sync::mpsc;
-use std::thread;
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio::time;
 
-use app::{App, AppPanel, AppState};
-use theme::Theme;
+use app::{
+    App, AppPanel, AppState, AppTab, GraphicsMode, InputPurpose, OptionKind, Retry, Rtd3Level,
+};
+use config::IconSet;
+use envycontrol::SwitchOptions;
+use icons::Icons;
+use input::{InputEvent, InputWidget, NumberInput, SelectList, TextInput};
+use keymap::{Action, Keymap};
+#[cfg(feature = "overclock")]
+use overclock::{Offsets, OverclockPanel};
+use poll::Poll;
+use probe::{Probe, SwitchCheck};
+use rules::Output;
+use task::{AppEvent, Events, Task};
+use theme::{Theme, ThemeEditor};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
+const TICK_RATE: Duration = Duration::from_millis(250);
+const FILE_WATCH_INTERVAL: Duration = Duration::from_secs(1);
+const THEME_ADJUST_STEP: i16 = 8;
+const INLINE_HEIGHT: u16 = 22;
+// Gives killed processes a moment to release the device before re-checking.
+const PROCESS_SETTLE: Duration = Duration::from_millis(300);
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    // Before anything reads the config or state directories, which a replay
+    // swaps for scratch ones.
+    let mut _scratch = None;
+    if let Some(i) = args.iter().position(|a| a == "--replay") {
+        let path = args
+            .get(i + 1)
+            .cloned()
+            .ok_or_else(|| anyhow!("--replay expects a session file"))?;
+        args.drain(i..i + 2);
+        _scratch = Some(session::start_replay(Path::new(&path))?);
+    } else if let Some(i) = args.iter().position(|a| a == "--script") {
+        let script = args
+            .get(i + 1)
+            .cloned()
+            .ok_or_else(|| anyhow!("--script expects keys or a file"))?;
+        args.drain(i..i + 2);
+        _scratch = Some(session::start_script(&script)?);
+    }
+    // A broken config.toml is reported once the TUI starts; until then only
+    // the ENVY_TUI_* overrides apply.
+    let loaded_config = config::load().unwrap_or_else(|_| {
+        let mut defaults = config::Config::default();
+        defaults.apply_env();
+        defaults
+    });
+
+    let envycontrol_path = match args.iter().position(|a| a == "--envycontrol-path") {
+        Some(i) => {
+            let path = args
+                .get(i + 1)
+                .cloned()
+                .ok_or_else(|| anyhow!("--envycontrol-path expects a path"))?;
+            args.drain(i..i + 2);
+            Some(PathBuf::from(path))
+        }
+        None => loaded_config.envycontrol_path.clone(),
+    };
+    envycontrol::set_custom_path(envycontrol_path);
+    logging::configure(
+        &loaded_config.logs,
+        loaded_config.monitor.csv_log.as_deref(),
+    );
+
+    match args.first().map(String::as_str) {
+        Some("--version" | "-V") => {
+            println!("envy-tui {}", VERSION);
+            return Ok(());
+        }
+        Some("--help" | "-h") => {
+            println!("envy-tui {} - TUI manager for EnvyControl", VERSION);
+            println!();
+            println!("Usage: envy-tui [OPTIONS] [COMMAND]");
+            println!();
+            println!("Commands:");
+            println!("  switch <mode>    Switch to integrated, hybrid or nvidia mode");
+            println!("                   (--rtd3 <0-3>, --force-comp, --coolbits <n>)");
+            println!("                   (-y/--yes skips the question, --reboot/--no-reboot");
+            println!("                   reboot afterwards or not without asking)");
+            println!("                   (--dry-run prints the command and files it would change)");
+            println!("  status           Print the mode, backend, driver, session and dGPU state");
+            println!("                   (--json prints them as a JSON object)");
+            println!("  doctor           Check envycontrol, the driver and the distro setup");
+            println!("  cheatsheet [FILE]");
+            println!("                   Write the key bindings, with remaps, to FILE");
+            println!("                   (Markdown for .md, plain text otherwise; default envy-tui-keys.md)");
+            println!("  config init      Write a commented config.toml and theme.toml");
+            println!("                   (--force overwrites existing files)");
+            println!("  config path      Print where the config, theme, state and log files are");
+            println!("  apply-login      Apply the profile matching the login rules");
+            println!("                   (--install-unit / --install-autostart to set it up)");
+            println!(
+                "                   (both: -q/--quiet prints only the result, e.g. \"switched nvidia\")"
+            );
+            println!("                   (both: --json prints the result as a JSON object)");
+            println!();
+            println!("Options:");
+            println!("  -y, --yes        Skip switch and reboot confirmations");
+            println!("  --inline         Draw below the prompt instead of on the alternate screen");
+            println!("  --monochrome     No colors: bold, reverse, underline and ASCII icons only");
+            println!(
+                "  --record <file>  Save key presses, states and command output to a session file"
+            );
+            println!("  --replay <file>  Play a recorded session against a mock backend");
+            println!("  --script <keys>  Press keys like \"3,Enter,y,n,q\" (or listed in a file)");
+            println!("                   on a mock backend, then quit");
+            println!("  --envycontrol-path <path>");
+            println!("                   Run envycontrol from this executable or .py file");
+            println!("  -V, --version    Print version information");
+            println!("  -h, --help       Print this help message");
+            println!();
+            println!("Environment (overrides config.toml, command line flags win):");
+            println!("  ENVY_TUI_ENVYCONTROL_PATH   envycontrol executable or .py file");
+            println!("  ENVY_TUI_NO_CONFIRM=1|0     Skip confirmations, like --yes");
+            println!("  ENVY_TUI_LOG_LEVEL          info, warn or error");
+            println!("  ENVY_TUI_THEME              default, ansi or the path of a theme file");
+            println!("  (No ENVY_TUI_BACKEND: envycontrol is the only backend.)");
+            return Ok(());
+        }
+        Some("switch") => {
+            // A dry run changes nothing, so it may run next to an open TUI.
+            let _lock = if args.iter().any(|arg| arg == "--dry-run") {
+                None
+            } else {
+                Some(state::acquire_instance_lock()?)
+            };
+            return cli::switch(&args[1..]);
+        }
+        Some("status") => return cli::status(&args[1..]),
+        Some("doctor") => return doctor::run(),
+        Some("cheatsheet") => return cli::cheat_sheet(&args[1..]),
+        Some("config") => return cli::config(&args[1..]),
+        Some("apply-login") => {
+            let _lock = state::acquire_instance_lock()?;
+            return cli::apply_login(&args[1..]);
+        }
+        _ => {}
+    }
 
-fn main() -> Result<()> {
-    let args: Vec<String> = std::env::args().collect();
-
-    if args.len() > 1 {
-        match args[1].as_str() {
-            "--version" | "-V" => {
-                println!("envy-tui {}", VERSION);
-                return Ok(());
-            }
-            "--help" | "-h" => {
-                println!("envy-tui {} - TUI manager for EnvyControl", VERSION);
-                println!();
-                println!("Usage: envy-tui [OPTIONS]");
-                println!();
-                println!("Options:");
-                println!("  -V, --version    Print version information");
-                println!("  -h, --help       Print this help message");
-                return Ok(());
+    let mut skip_confirmations = false;
+    let mut inline = false;
+    let mut monochrome = false;
+    let mut record = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--yes" | "-y" => skip_confirmations = true,
+            "--inline" => inline = true,
+            "--monochrome" => monochrome = true,
+            "--record" => {
+                let path = iter
+                    .next()
+                    .ok_or_else(|| anyhow!("--record expects a file name"))?;
+                record = Some(PathBuf::from(path));
             }
-            _ => {}
+            other => return Err(anyhow!("Unknown option: {}", other)),
         }
     }
 
+    let _lock = state::acquire_instance_lock()?;
+
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    // Inline mode draws in the normal screen buffer so the last frame stays
+    // in the scrollback, e.g. for a quick check over SSH.
+    let mut terminal = if inline {
+        Terminal::with_options(
+            CrosstermBackend::new(stdout),
+            TerminalOptions {
+                viewport: Viewport::Inline(INLINE_HEIGHT),
+            },
+        )
+        .inspect_err(|_| {
+            let _ = disable_raw_mode();
+        })?
+    } else {
+        execute!(stdout, EnterAlternateScreen)?;
+        Terminal::new(CrosstermBackend::new(stdout))?
+    };
 
-    let result = run_app(&mut terminal);
+    let result = run_app(
+        &mut terminal,
+        skip_confirmations,
+        inline,
+        monochrome,
+        record,
+    )
+    .await;
+    session::save_recording();
 
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    if inline {
+        let area = terminal.get_frame().area();
+        terminal.set_cursor_position(Position::new(0, area.bottom().saturating_sub(1)))?;
+        println!();
+    } else {
+        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    }
     terminal.show_cursor()?;
 
-    if let Err(err) = result {
-        eprintln!("Error: {}", err);
+    match result {
+        Ok(summary) => {
+            for entry in summary {
+                println!("{}", entry);
+            }
+        }
+        Err(err) => eprintln!("Error: {}", err),
     }
 
     Ok(())
 }
 
-fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<()> {
+async fn run_app(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    skip_confirmations: bool,
+    inline: bool,
+    monochrome: bool,
+    record: Option<PathBuf>,
+) -> Result<Vec<String>> {
     let mut app = App::new();
-    let theme = Theme::default();
-
-    if !envycontrol::is_envycontrol_installed() {
-        app.set_error("envycontrol is not installed. Please install it first.");
-    } else {
-        match envycontrol::query_mode() {
-            Ok(mode) => {
-                app.current_mode = mode;
-                if mode != Some(app::GraphicsMode::Integrated) {
-                    app.gpu_info = envycontrol::query_gpu_info();
-                }
-            }
-            Err(e) => app.set_error(&format!("Failed to query mode: {}", e)),
+    #[cfg(feature = "monitor")]
+    {
+        app.history = monitor::load_history();
+        app.switches = state::load_switch_history();
+        app.boot_time = state::boot_time();
+    }
+    match config::load() {
+        Ok(config) => app.config = config,
+        Err(e) => {
+            logging::warn(&format!("{:#}", e));
+            app.set_error(&format!("{:#}", e));
+            app.config.apply_env();
         }
     }
+    app.skip_confirmations = skip_confirmations || app.config.skip_confirmations;
+    app.monochrome = monochrome || app.config.ui.monochrome;
+    app.refresh_options();
+    app.compact = inline || app.config.ui.compact;
+    load_keymap(&mut app);
+    load_icons(&mut app);
+    let mut theme = load_theme(&app).unwrap_or_else(|e| {
+        logging::warn(&format!("{:#}", e));
+        app.set_error(&format!("{:#}", e));
+        Theme::default()
+    });
+
+    let (events, mut event_rx) = mpsc::unbounded_channel();
+    app.detecting = true;
+    probe::spawn(&events);
+    check_config_files(&mut app);
+
+    app.on_ac = Some(rules::is_on_ac());
+    app.outputs = rules::connected_outputs();
+    app.dgpu_power = system::dgpu_power();
+    let mut timers = poll::Timers::new();
+    let mut last_file_check = Instant::now();
+    let mut config_watcher = config::FileWatcher::new(config::config_path());
+    let mut theme_watcher = config::FileWatcher::new(theme::theme_path());
+
+    let mut input = EventStream::new();
+    let mut last_spinner_tick = Instant::now();
+
+    if let Some(path) = record {
+        let size = terminal.size()?;
+        session::start_recording(path, &app, (size.width, size.height));
+    }
+    let mut recorded_state = app.state;
+    session::spawn_replay(&events);
 
     while !app.should_quit {
+        if app.state != recorded_state {
+            recorded_state = app.state;
+            session::record(session::Entry::State {
+                state: format!("{:?}", recorded_state),
+            });
+        }
+        if app.animating() && last_spinner_tick.elapsed() >= app.frame_interval() {
+            last_spinner_tick = Instant::now();
+            app.tick_spinner();
+        }
         terminal.draw(|f| ui::render(f, &app, &theme))?;
 
-        if let Event::Key(key) = event::read()? {
-            if key.kind != KeyEventKind::Press {
-                continue;
-            }
-
-            if app.state == AppState::ConfirmingSwitch {
-                match key.code {
-                    KeyCode::Char('y') | KeyCode::Char('s') | KeyCode::Enter => {
-                        let selected = app.pending_mode.unwrap_or(app.selected_mode());
-                        let options = envycontrol::SwitchOptions {
-                            mode: selected,
-                            rtd3_enabled: app.rtd3_enabled,
-                            rtd3_level: app.rtd3_level,
-                            force_comp: app.force_comp,
-                            coolbits_enabled: app.coolbits_enabled,
-                            coolbits_value: app.coolbits_value,
-                        };
-
-                        app.set_loading("Applying changes...");
-
-                        let (tx, rx) = mpsc::channel();
-                        thread::spawn(move || {
-                            let result = envycontrol::switch_mode(options);
-                            let _ = tx.send((result, selected));
-                        });
+        if app.skip_confirmations && auto_confirm(&mut app, &events) {
+            continue;
+        }
+
+        if app.state == AppState::Normal && timers.power_source.due() {
+            poll::spawn(&events, &mut timers.power_source, poll::power_source);
+        }
+
+        // Only sampled while the tab is open: nvidia-smi keeps the dGPU awake.
+        #[cfg(feature = "monitor")]
+        if app.active_tab == AppTab::Monitor
+            && app.current_mode != Some(GraphicsMode::Integrated)
+            && timers.monitor.due()
+        {
+            // The monitor sample refreshes the header too.
+            timers.gpu_info.restart();
+            let csv_log = (!app.csv_log_failed)
+                .then(|| app.config.monitor.csv_log.clone())
+                .flatten();
+            poll::spawn(&events, &mut timers.monitor, move || poll::monitor(csv_log));
+        }
+
+        if timers.outputs.due() {
+            let previous = app.outputs.clone();
+            poll::spawn(&events, &mut timers.outputs, move || {
+                poll::outputs(previous)
+            });
+        }
+
+        if timers.dgpu_power.due() {
+            poll::spawn(&events, &mut timers.dgpu_power, poll::dgpu_power);
+        }
+
+        // Keeps the header's temperature, memory and load current. nvidia-smi
+        // would keep a hybrid dGPU from suspending, so there only the Monitor
+        // tab and R refresh them.
+        if app.current_mode == Some(GraphicsMode::Nvidia)
+            && app.gpu_info.is_some()
+            && session::replay_snapshot().is_none()
+            && timers.gpu_info.due()
+        {
+            poll::spawn(&events, &mut timers.gpu_info, poll::gpu_info);
+        }
+
+        if app.active_tab == AppTab::Logs && timers.logs.due() {
+            poll::spawn(&events, &mut timers.logs, poll::logs);
+        }
+
+        // Keep looking for envycontrol so installing it in another terminal
+        // unlocks the UI without a restart.
+        if !app.envycontrol_installed
+            && !app.detecting
+            && session::replay_snapshot().is_none()
+            && timers.envycontrol.due()
+        {
+            poll::spawn(&events, &mut timers.envycontrol, poll::envycontrol);
+        }
 
-                        loop {
-                            terminal.draw(|f| ui::render(f, &app, &theme))?;
-
-                            match rx.try_recv() {
-                                Ok((result, mode)) => {
-                                    match result {
-                                        Ok(_) => {
-                                            app.current_mode = Some(mode);
-                                            app.pending_mode = None;
-                                            app.state = AppState::ConfirmingReboot;
-                                            app.message = "Mode changed successfully! Do you want to reboot now?".to_string();
-                                        }
-                                        Err(e) => {
-                                            app.pending_mode = None;
-                                            app.set_error(&e.to_string());
-                                        }
-                                    }
-                                    break;
-                                }
-                                Err(mpsc::TryRecvError::Empty) => {
-                                    app.tick_spinner();
-                                    thread::sleep(Duration::from_millis(100));
-                                }
-                                Err(mpsc::TryRecvError::Disconnected) => {
-                                    app.set_error("Command failed unexpectedly");
-                                    break;
-                                }
-                            }
-                        }
+        if last_file_check.elapsed() >= FILE_WATCH_INTERVAL {
+            last_file_check = Instant::now();
+            let config_changed = config_watcher.changed();
+            let mut theme_changed = theme_watcher.changed();
+            if config_changed {
+                match config::load() {
+                    Ok(config) => {
+                        logging::configure(&config.logs, config.monitor.csv_log.as_deref());
+                        app.config = config;
+                        app.skip_confirmations =
+                            skip_confirmations || app.config.skip_confirmations;
+                        let was_monochrome = app.monochrome;
+                        app.monochrome = monochrome || app.config.ui.monochrome;
+                        theme_changed |= app.monochrome != was_monochrome;
+                        load_keymap(&mut app);
+                        load_icons(&mut app);
+                        app.refresh_options();
+                        logging::info("Reloaded config");
                     }
-                    KeyCode::Char('n') | KeyCode::Esc => {
-                        app.pending_mode = None;
-                        app.clear_message();
+                    Err(e) => {
+                        logging::warn(&format!("{:#}", e));
+                        app.set_error(&format!("{:#}", e));
+                    }
+                }
+            }
+            if theme_changed {
+                match load_theme(&app) {
+                    Ok(loaded) => theme = loaded,
+                    Err(e) => {
+                        logging::warn(&format!("{:#}", e));
+                        app.set_error(&format!("{:#}", e));
                     }
-                    _ => {}
                 }
-                continue;
             }
+            if config_changed || theme_changed {
+                check_config_files(&mut app);
+            }
+        }
 
-            if app.state == AppState::ConfirmingReboot {
-                match key.code {
-                    KeyCode::Char('y') | KeyCode::Char('s') | KeyCode::Enter => {
-                        if let Err(e) = envycontrol::reboot() {
-                            app.set_error(&format!("Failed to reboot: {}", e));
-                        }
+        let timeout = if app.animating() {
+            app.frame_interval()
+        } else {
+            TICK_RATE.max(app.frame_interval())
+        };
+        tokio::select! {
+            event = input.next() => match event {
+                Some(Ok(Event::Key(key))) => {
+                    // Only Enter and Esc of a password prompt are kept.
+                    let secret = app.input.as_ref().is_some_and(|p| p.widget.is_masked())
+                        && !matches!(key.code, KeyCode::Enter | KeyCode::Esc);
+                    if key.kind == KeyEventKind::Press
+                        && keymap::key_name(key.code).is_some()
+                        && !secret
+                    {
+                        session::record(session::Entry::Key {
+                            code: key.code,
+                            ctrl: key.modifiers.contains(KeyModifiers::CONTROL),
+                        });
                     }
-                    KeyCode::Char('n') | KeyCode::Esc => {
-                        app.set_success(
-                            "Changes applied. Reboot the computer for changes to take effect.",
-                        );
+                    handle_key(&mut app, &events, key);
+                }
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e.into()),
+                None => break,
+            },
+            Some(event) = event_rx.recv() => match event {
+                AppEvent::Output(line) => {
+                    session::record(session::Entry::Output { line: line.clone() });
+                    app.push_progress_line(line);
+                }
+                AppEvent::Finished(task, result) => {
+                    session::record(session::Entry::Finished {
+                        ok: result.is_ok(),
+                        message: match &result {
+                            Ok(message) => message.clone(),
+                            Err(e) => e.to_string(),
+                        },
+                    });
+                    finish_task(&mut app, &events, task, result);
+                }
+                AppEvent::Probed(probe) => apply_probe(&mut app, probe),
+                #[cfg(feature = "overclock")]
+                AppEvent::ClockOffsets(offsets) => open_overclock(&mut app, offsets),
+                AppEvent::Polled(poll) => {
+                    timers.finish(poll.kind());
+                    apply_poll(&mut app, &events, poll);
+                }
+                AppEvent::SwitchChecked(check) => apply_switch_check(&mut app, check),
+                AppEvent::About(about) => {
+                    if app.state == AppState::Normal {
+                        app.about = Some(*about);
+                        app.state = AppState::About;
                     }
-                    _ => {}
                 }
-                continue;
+                AppEvent::Replay(entry) => replay(&mut app, &events, entry),
+            },
+            _ = time::sleep(timeout) => {}
+        }
+    }
+
+    #[cfg(feature = "monitor")]
+    if let Err(e) = monitor::save_history(&app.history) {
+        logging::warn(&format!("Failed to save the GPU history: {}", e));
+    }
+    Ok(app.summary)
+}
+
+// Answers the prompt for --yes. A switch waits for the running task, since
+// starting it then would only queue it and leave the prompt up. Returns
+// whether the prompt went away.
+fn auto_confirm(app: &mut App, events: &Events) -> bool {
+    let prompt = app.state;
+    match prompt {
+        AppState::ConfirmingSwitch if app.running.is_none() => start_switch(app, events),
+        AppState::ConfirmingReboot => {
+            if let Err(e) = envycontrol::reboot() {
+                app.set_error(&format!("Failed to reboot: {}", e));
+            } else {
+                app.set_success("Rebooting...");
+            }
+        }
+        _ => return false,
+    }
+    app.state != prompt
+}
+
+// Looks for running games each time, since the user may have just closed one.
+// A check that comes back after the switch was cancelled is dropped.
+fn apply_switch_check(app: &mut App, check: SwitchCheck) {
+    if app.pending_mode != Some(check.mode)
+        || !matches!(app.state, AppState::Normal | AppState::ConfirmingProcesses)
+    {
+        return;
+    }
+    if !check.processes.is_empty() {
+        app.warn_gpu_processes(check.mode, check.processes);
+        return;
+    }
+    app.running_games = check.running_games;
+    app.login_note = check.login_note;
+    app.applied_args = check.applied_args;
+    app.confirm_switch(check.mode);
+}
+
+// The processes the user may signal are killed right away, the others through
+// the password prompt like any privileged command.
+fn terminate_processes(app: &mut App, events: &Events, mode: GraphicsMode, pids: Vec<u32>) {
+    let mut refused = Vec::new();
+    for pid in pids {
+        match envycontrol::terminate_process(pid) {
+            Ok(true) => {}
+            Ok(false) => refused.push(pid),
+            Err(e) => {
+                app.pending_mode = None;
+                app.set_error(&format!("Could not terminate process {}: {}", pid, e));
+                return;
             }
+        }
+    }
+    if refused.is_empty() {
+        probe::check_switch(events, mode, true, PROCESS_SETTLE);
+        return;
+    }
+    run_privileged_task(
+        app,
+        events,
+        "Terminating processes...",
+        Task::Terminate(mode),
+        move |output| envycontrol::terminate_privileged(&refused, output),
+    );
+}
 
-            if app.state != AppState::Normal {
+fn handle_key(app: &mut App, events: &Events, key: KeyEvent) {
+    if key.kind != KeyEventKind::Press {
+        return;
+    }
+    app.last_input = Instant::now();
+
+    if app.state == AppState::Input {
+        handle_input_key(app, events, key.code);
+        return;
+    }
+
+    if scroll_popup(app, key.code) {
+        return;
+    }
+
+    if app.state == AppState::ConfirmingProcesses {
+        let mode = app.pending_mode.unwrap_or(app.selected_mode());
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => app.previous_process(),
+            KeyCode::Down | KeyCode::Char('j') => app.next_process(),
+            KeyCode::Char('x') => {
+                let selected = app.gpu_processes.get(app.selected_process_index);
+                let pids = selected.map(|process| process.pid).into_iter().collect();
+                terminate_processes(app, events, mode, pids);
+            }
+            KeyCode::Char('X') => {
+                let pids = app
+                    .gpu_processes
+                    .iter()
+                    .map(|process| process.pid)
+                    .collect();
+                terminate_processes(app, events, mode, pids);
+            }
+            KeyCode::Char('c') | KeyCode::Enter => {
+                probe::check_switch(events, mode, false, Duration::ZERO)
+            }
+            KeyCode::Char('n') | KeyCode::Esc => {
+                app.pending_mode = None;
+                app.gpu_processes.clear();
                 app.clear_message();
-                continue;
-            }
-
-            match key.code {
-                KeyCode::Char('q') | KeyCode::Esc => {
-                    app.should_quit = true;
-                }
-                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                    app.should_quit = true;
-                }
-                KeyCode::Tab => {
-                    app.toggle_panel();
-                }
-                KeyCode::Up | KeyCode::Char('k') => match app.active_panel {
-                    AppPanel::ModeSelection => app.previous_mode(),
-                    AppPanel::Options => app.previous_option(),
-                },
-                KeyCode::Down | KeyCode::Char('j') => match app.active_panel {
-                    AppPanel::ModeSelection => app.next_mode(),
-                    AppPanel::Options => app.next_option(),
-                },
-                KeyCode::Char(' ') => {
-                    if app.active_panel == AppPanel::Options {
-                        app.toggle_current_option();
-                    }
+            }
+            _ => {}
+        }
+        return;
+    }
+
+    if app.state == AppState::ConfirmingSwitch {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('s') | KeyCode::Enter => {
+                start_switch(app, events);
+            }
+            KeyCode::Char('n') | KeyCode::Esc => {
+                app.pending_mode = None;
+                app.suggested_profile = None;
+                app.clear_message();
+            }
+            _ => {}
+        }
+        return;
+    }
+
+    if app.state == AppState::ConfirmingReboot {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('s') | KeyCode::Enter => {
+                if let Err(e) = envycontrol::reboot() {
+                    app.set_error(&format!("Failed to reboot: {}", e));
                 }
-                KeyCode::Enter => {
-                    let selected = app.selected_mode();
-                    app.pending_mode = Some(selected);
-                    app.state = AppState::ConfirmingSwitch;
-                    app.message = format!("Switch to {} mode? (y/n)", selected);
+            }
+            KeyCode::Char('i') => {
+                if let Some(tool) = app.initramfs_tool {
+                    run_privileged_task(
+                        app,
+                        events,
+                        "Rebuilding the initramfs...",
+                        Task::RebuildInitramfs,
+                        move |output| system::rebuild_initramfs(tool, output),
+                    );
                 }
-                KeyCode::Char('r') => {
-                    app.set_loading("Resetting...");
+            }
+            KeyCode::Char('n') | KeyCode::Esc => match app.initramfs_tool.take() {
+                Some(tool) => app.set_success(&format!(
+                    "Changes applied. Run `{}` and reboot for changes to take effect.",
+                    tool.command_line()
+                )),
+                None => app.set_success(
+                    "Changes applied. Reboot the computer for changes to take effect.",
+                ),
+            },
+            _ => {}
+        }
+        return;
+    }
 
-                    let (tx, rx) = mpsc::channel();
-                    thread::spawn(move || {
-                        let result = envycontrol::reset();
-                        let _ = tx.send(result);
-                    });
+    // Keys can't interrupt a running privileged command; it reports back
+    // through a Finished event. A switch only starts once it was confirmed.
+    if app.state == AppState::Loading {
+        app.set_flash("Waiting for the running command to finish");
+        return;
+    }
 
-                    loop {
-                        terminal.draw(|f| ui::render(f, &app, &theme))?;
-
-                        match rx.try_recv() {
-                            Ok(result) => {
-                                match result {
-                                    Ok(msg) => {
-                                        app.current_mode = None;
-                                        app.set_success(&msg);
-                                    }
-                                    Err(e) => app.set_error(&e.to_string()),
-                                }
-                                break;
-                            }
-                            Err(mpsc::TryRecvError::Empty) => {
-                                app.tick_spinner();
-                                thread::sleep(Duration::from_millis(100));
-                            }
-                            Err(mpsc::TryRecvError::Disconnected) => {
-                                app.set_error("Command failed unexpectedly");
-                                break;
-                            }
-                        }
-                    }
+    if app.state == AppState::Error && key.code == KeyCode::Char('r') {
+        match app.retry.take() {
+            Some(Retry::Switch(options)) => {
+                app.pending_mode = Some(options.mode);
+                run_switch(app, events, options);
+            }
+            Some(Retry::Reset) => start_reset(app, events),
+            None => app.clear_message(),
+        }
+        return;
+    }
+
+    if app.state == AppState::Error && key.code == KeyCode::Char('i') {
+        report_issue(app);
+        return;
+    }
+
+    if app.state == AppState::Help {
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                app.help_page = app.help_page.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                app.help_page = (app.help_page + 1).min(help::PAGES.len() - 1);
+            }
+            _ => app.clear_message(),
+        }
+        return;
+    }
+
+    if app.state == AppState::About {
+        handle_about_key(app, key.code);
+        return;
+    }
+
+    if app.state == AppState::ThemeEditor {
+        handle_theme_editor_key(app, key.code);
+        return;
+    }
+
+    #[cfg(feature = "overclock")]
+    if app.state == AppState::Overclock {
+        handle_overclock_key(app, events, key.code);
+        return;
+    }
+
+    if app.state != AppState::Normal {
+        app.clear_message();
+        return;
+    }
+
+    if app.active_tab == AppTab::Logs && app.log_search_active {
+        handle_log_search_key(app, key.code);
+        return;
+    }
+
+    if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+        app.should_quit = true;
+        return;
+    }
+
+    match app.keymap.action(app.active_tab, key.code, |action| {
+        app.action_available(action)
+    }) {
+        Some(Action::Quit) => app.should_quit = true,
+        Some(Action::NextTab) => {
+            app.next_tab();
+            app.set_log_lines(logging::read_recent());
+        }
+        Some(Action::PreviousTab) => {
+            app.previous_tab();
+            app.set_log_lines(logging::read_recent());
+        }
+        Some(Action::About) => probe::spawn_about(events),
+        Some(Action::Help) => app.state = AppState::Help,
+        Some(Action::EditTheme) if app.action_available(Action::EditTheme) => match Theme::load() {
+            Ok(theme) => {
+                app.theme_editor = Some(ThemeEditor::new(theme));
+                app.state = AppState::ThemeEditor;
+            }
+            Err(e) => app.set_error(&format!("Fix theme.toml before editing it: {:#}", e)),
+        },
+        Some(Action::UpdateEnvycontrol) if app.action_available(Action::UpdateEnvycontrol) => {
+            run_task(
+                app,
+                events,
+                "Updating envycontrol...",
+                Task::UpdateEnvycontrol,
+                envycontrol::update,
+            );
+        }
+        Some(Action::Refresh) => {
+            app.detecting = true;
+            probe::refresh(events);
+            app.set_log_lines(logging::read_recent());
+        }
+        Some(action) => match app.active_tab {
+            AppTab::Switch => handle_switch_action(app, events, action),
+            AppTab::System => handle_system_action(app, events, action),
+            #[cfg(feature = "monitor")]
+            AppTab::Monitor => {}
+            AppTab::Logs => handle_logs_action(app, action),
+        },
+        None => {}
+    }
+}
+
+// Popups too long for the terminal scroll before other keys act on them.
+fn scroll_popup(app: &mut App, code: KeyCode) -> bool {
+    let scrolls = matches!(
+        app.state,
+        AppState::Success
+            | AppState::Error
+            | AppState::ConfirmingSwitch
+            | AppState::ConfirmingReboot
+            | AppState::About
+            | AppState::OptionDetails
+            | AppState::ConfigProblems
+    );
+    let max_scroll = app.popup_max_scroll.get();
+    if !scrolls || max_scroll == 0 {
+        return false;
+    }
+    app.popup_scroll = match code {
+        KeyCode::Up | KeyCode::Char('k') => app.popup_scroll.saturating_sub(1),
+        KeyCode::Down | KeyCode::Char('j') => (app.popup_scroll + 1).min(max_scroll),
+        _ => return false,
+    };
+    true
+}
+
+// Privileged commands never overlap: a switch requested while another command
+// runs is queued and started once it finishes, anything else is refused.
+fn run_task<F>(app: &mut App, events: &Events, message: &str, task: Task, work: F)
+where
+    F: FnOnce(&dyn Fn(String)) -> Result<String> + Send + 'static,
+{
+    if let Some(running) = app.running {
+        match task {
+            Task::Switch(options) => {
+                app.queued_switch = Some(options.mode);
+                app.set_flash(&format!(
+                    "Switch to {} mode queued until {} finishes",
+                    options.mode, running
+                ));
+            }
+            _ => app.set_flash(&format!("Wait for {} to finish", running)),
+        }
+        return;
+    }
+
+    app.running = Some(task.label());
+    app.set_loading(message);
+    task::spawn(events, task, work);
+}
+
+// Without pkexec, sudo's password is asked for first; the task is held until
+// it is entered.
+fn run_privileged_task<F>(app: &mut App, events: &Events, message: &str, task: Task, work: F)
+where
+    F: FnOnce(&dyn Fn(String)) -> Result<String> + Send + 'static,
+{
+    if app.running.is_none() && system::needs_password() {
+        app.password_task = Some(task::Held {
+            message: message.to_string(),
+            task,
+            work: Box::new(work),
+        });
+        app.open_input(
+            InputPurpose::Password,
+            "Password for sudo",
+            InputWidget::Text(TextInput::new().masked()),
+        );
+        return;
+    }
+    run_task(app, events, message, task, work);
+}
+
+fn start_switch(app: &mut App, events: &Events) {
+    let mode = app.pending_mode.unwrap_or(app.selected_mode());
+    if let Some(profile) = app.suggested_profile.take().filter(|p| p.mode == mode) {
+        app.load_profile(&profile);
+    }
+    let options = app.switch_options(mode);
+    run_switch(app, events, options);
+}
+
+fn run_switch(app: &mut App, events: &Events, options: SwitchOptions) {
+    if let Some(immutable) = app.immutable {
+        app.set_error(immutable.explanation());
+        return;
+    }
+    run_privileged_task(
+        app,
+        events,
+        "Applying changes...",
+        Task::Switch(options.clone()),
+        move |output| envycontrol::switch_mode(options, output),
+    );
+}
+
+fn start_reset(app: &mut App, events: &Events) {
+    if let Some(immutable) = app.immutable {
+        app.set_error(immutable.explanation());
+        return;
+    }
+    run_privileged_task(app, events, "Resetting...", Task::Reset, envycontrol::reset);
+}
+
+fn finish_task(app: &mut App, events: &Events, task: Task, result: Result<String>) {
+    app.running = None;
+    let succeeded = result.is_ok();
+    match (task, result) {
+        (Task::Switch(options), Ok(_)) => {
+            let mode = options.mode;
+            let flags = options.flags();
+            let from = app
+                .current_mode
+                .map(|m| m.to_string())
+                .unwrap_or_else(|| "unknown".to_string());
+            let with = if flags.is_empty() {
+                String::new()
+            } else {
+                format!(" with {}", flags.join(" "))
+            };
+            app.record(format!(
+                "switched {}→{}{}; reboot pending",
+                from, mode, with
+            ));
+            let _ = state::record_switch(mode, &flags);
+            #[cfg(feature = "monitor")]
+            {
+                app.switches = state::load_switch_history();
+            }
+            app.reboot_pending = Some(mode);
+            app.pending_mode = None;
+            let changes = envycontrol::SwitchChanges::parse(&app.task_output);
+            let problems = if host::sees_host_files() {
+                envycontrol::verify_switch(Path::new("/"), &options, app.display_manager.as_deref())
+            } else {
+                Vec::new()
+            };
+            for problem in &problems {
+                logging::warn(&format!("After switching to {} mode: {}", mode, problem));
+            }
+            app.show_switch_summary(mode, &changes, &problems);
+            app.initramfs_tool = None;
+            if let Some(hint) = distro::current().reboot_hint(mode) {
+                app.message.push('\n');
+                app.message.push_str(hint);
+            }
+            if app.phases.contains(&envycontrol::Phase::Initramfs) {
+                app.message.push_str("\nenvycontrol rebuilt the initramfs.");
+            } else if let Some(tool) = system::initramfs_tool() {
+                app.initramfs_tool = Some(tool);
+                app.message.push_str(&format!(
+                    "\nThe initramfs was not rebuilt; the old mode may still load until it is.\nPress i to run `{}` first.",
+                    tool.command_line()
+                ));
+            }
+        }
+        (Task::Switch(options), Err(e)) => {
+            let mode = options.mode;
+            app.pending_mode = None;
+            let mut message = e.to_string();
+            if mode != GraphicsMode::Integrated && system::nvidia_driver_version().is_none() {
+                message.push_str(&format!(
+                    "\nThe NVIDIA driver is not installed. {}",
+                    distro::current().install_driver()
+                ));
+            }
+            app.set_retryable_error(&message, Retry::Switch(options));
+        }
+        (Task::Terminate(mode), Ok(_)) => {
+            app.clear_message();
+            probe::check_switch(events, mode, true, PROCESS_SETTLE);
+        }
+        (Task::Terminate(_), Err(e)) => {
+            app.pending_mode = None;
+            app.set_error(&e.to_string());
+        }
+        (Task::Reset, Ok(msg)) => {
+            state::clear_pending_switch();
+            app.current_mode = None;
+            app.reboot_pending = None;
+            app.record("reset envycontrol to its defaults".to_string());
+            app.set_success(&msg);
+        }
+        (Task::Reset, Err(e)) => app.set_retryable_error(&e.to_string(), Retry::Reset),
+        (Task::UpdateEnvycontrol, Ok(msg)) => {
+            app.detecting = true;
+            probe::refresh(events);
+            app.record(msg.clone());
+            app.set_success(&msg);
+        }
+        (Task::System, Ok(msg)) => {
+            app.detecting = true;
+            probe::refresh(events);
+            app.record(msg.clone());
+            app.set_success(&msg);
+        }
+        (Task::RebuildInitramfs, Ok(msg)) => {
+            app.initramfs_tool = None;
+            app.record("rebuilt the initramfs".to_string());
+            app.state = AppState::ConfirmingReboot;
+            app.message = format!("{} Do you want to reboot now?", msg);
+            app.popup_scroll = 0;
+        }
+        (Task::UpdateEnvycontrol | Task::System | Task::RebuildInitramfs, Err(e)) => {
+            app.set_error(&e.to_string())
+        }
+    }
+
+    if let Some(mode) = app.queued_switch.take() {
+        if succeeded {
+            app.pending_mode = Some(mode);
+            start_switch(app, events);
+        } else {
+            logging::warn(&format!(
+                "Dropped the queued switch to {} mode after a failure",
+                mode
+            ));
+        }
+    }
+}
+
+fn report_issue(app: &mut App) {
+    let error = app.message.clone();
+    match report::write_issue_report(&error, app.current_mode) {
+        Ok(report) => {
+            logging::info(&format!("Wrote issue report to {}", report.path.display()));
+            let copied = if clipboard::copy(&report.url).is_ok() {
+                " (copied to the clipboard)"
+            } else {
+                ""
+            };
+            app.set_success(&format!(
+                "Issue report written.\nReport: {}\nOpen{}: {}\nPaste the report into the issue body.",
+                report.path.display(),
+                copied,
+                report.url
+            ));
+        }
+        Err(e) => app.set_error(&format!("Failed to write issue report: {}", e)),
+    }
+}
+
+fn check_power_source(app: &mut App, events: &Events, on_ac: bool) {
+    if app.immutable.is_some() {
+        return;
+    }
+    // Asking now would replace the running command's popup, so unless the
+    // switch is automatic the change is handled once it finishes.
+    if app.on_ac == Some(on_ac) || (app.running.is_some() && !app.config.power.auto_apply) {
+        return;
+    }
+    let first_check = app.on_ac.is_none();
+    app.on_ac = Some(on_ac);
+    if first_check {
+        return;
+    }
+
+    let power = &app.config.power;
+    let (source, profile_name) = if on_ac {
+        ("AC power", power.ac.clone())
+    } else {
+        ("battery power", power.battery.clone())
+    };
+    let Some(profile_name) = profile_name else {
+        return;
+    };
+    let Some(profile) = app.config.profiles.get(&profile_name).cloned() else {
+        app.set_error(&format!("Unknown profile '{}' in [power]", profile_name));
+        return;
+    };
+    if app.reboot_pending.or(app.current_mode) == Some(profile.mode) {
+        return;
+    }
+
+    app.suggested_profile = Some(profile.clone());
+    if app.running.is_some() {
+        // Goes through the queue.
+        app.pending_mode = Some(profile.mode);
+        start_switch(app, events);
+        return;
+    }
+    app.confirm_switch(profile.mode);
+    if app.config.power.auto_apply {
+        start_switch(app, events);
+        return;
+    }
+    app.message = format!(
+        "Switched to {}: apply profile '{}' ({} mode)? (y/n)",
+        source, profile_name, profile.mode
+    );
+}
+
+fn apply_poll(app: &mut App, events: &Events, poll: Poll) {
+    match poll {
+        // Only acted on while nothing else is on screen; otherwise the next
+        // poll sees the same change.
+        Poll::PowerSource(on_ac) => {
+            if app.state == AppState::Normal {
+                check_power_source(app, events, on_ac);
+            }
+        }
+        #[cfg(feature = "monitor")]
+        Poll::Monitor {
+            info,
+            sample,
+            csv_error,
+            gamemode,
+        } => {
+            if app.current_mode == Some(GraphicsMode::Integrated) {
+                return;
+            }
+            app.gpu_info = info.map(|info| *info);
+            app.gamemode = gamemode;
+            if let Some(sample) = sample {
+                app.history.push(sample);
+            }
+            if let Some(error) = csv_error {
+                logging::warn(&error);
+                app.csv_log_failed = true;
+            }
+        }
+        Poll::Outputs { outputs, providers } => {
+            update_outputs(app, outputs);
+            if let Some(providers) = providers {
+                app.providers = providers;
+            }
+        }
+        Poll::DgpuPower(power) => app.dgpu_power = power,
+        Poll::GpuInfo(info) => {
+            if app.current_mode == Some(GraphicsMode::Nvidia) {
+                app.gpu_info = info.map(|info| *info);
+            }
+        }
+        Poll::Logs(lines) => {
+            if app.active_tab == AppTab::Logs {
+                app.set_log_lines(lines);
+            }
+        }
+        Poll::Envycontrol(found) => {
+            if found && !app.envycontrol_installed {
+                logging::info("envycontrol detected");
+                if app.state == AppState::Error && app.message == envycontrol::not_found_message() {
+                    app.clear_message();
+                }
+                app.detecting = true;
+                probe::spawn(events);
+                if app.state == AppState::Normal {
+                    app.set_flash("envycontrol detected");
                 }
-                _ => {}
             }
         }
     }
+}
 
-    Ok(())
+fn update_outputs(app: &mut App, outputs: Vec<Output>) {
+    if outputs == app.outputs {
+        return;
+    }
+
+    let added: Vec<&str> = outputs
+        .iter()
+        .filter(|o| !app.outputs.contains(o))
+        .map(|o| o.connector.as_str())
+        .collect();
+    let removed: Vec<&str> = app
+        .outputs
+        .iter()
+        .filter(|o| !outputs.contains(o))
+        .map(|o| o.connector.as_str())
+        .collect();
+    let message = match (added.is_empty(), removed.is_empty()) {
+        (false, _) => format!("Display connected: {}", added.join(", ")),
+        (true, false) => format!("Display disconnected: {}", removed.join(", ")),
+        (true, true) => String::new(),
+    };
+    if !message.is_empty() {
+        logging::info(&message);
+        if app.state == AppState::Normal {
+            app.set_flash(&message);
+        }
+    }
+
+    app.outputs = outputs;
+}
+
+// Lists every problem in config.toml and theme.toml, in place of the popup
+// for the first load error.
+fn check_config_files(app: &mut App) {
+    app.config_problems = validate::check();
+    if app.config_problems.is_empty() {
+        if app.state == AppState::ConfigProblems {
+            app.clear_message();
+        }
+        return;
+    }
+    for problem in &app.config_problems {
+        logging::warn(&format!("{}: {}", problem.location(), problem.message));
+    }
+    if matches!(
+        app.state,
+        AppState::Normal | AppState::Error | AppState::ConfigProblems
+    ) {
+        app.retry = None;
+        app.state = AppState::ConfigProblems;
+    }
+}
+
+// Monochrome ignores theme.toml and the icon set: its markers have to read
+// the same on any terminal.
+fn load_theme(app: &App) -> Result<Theme> {
+    if app.monochrome {
+        Ok(Theme::monochrome())
+    } else {
+        Theme::load()
+    }
+}
+
+fn load_icons(app: &mut App) {
+    let mut icons = app.config.icons.clone();
+    if app.monochrome {
+        icons.set = IconSet::Ascii;
+    }
+    app.icons = Icons::from_config(&icons);
+}
+
+fn load_keymap(app: &mut App) {
+    match Keymap::from_config(&app.config.keys) {
+        Ok(keymap) => app.keymap = keymap,
+        Err(e) => {
+            logging::warn(&format!("{:#}", e));
+            app.set_error(&format!("{:#}", e));
+        }
+    }
+}
+
+fn handle_switch_action(app: &mut App, events: &Events, action: Action) {
+    match action {
+        Action::SwitchPanel => {
+            app.toggle_panel();
+        }
+        Action::Up => match app.active_panel {
+            AppPanel::ModeSelection => app.previous_mode(),
+            AppPanel::Options => app.previous_option(),
+        },
+        Action::Down => match app.active_panel {
+            AppPanel::ModeSelection => app.next_mode(),
+            AppPanel::Options => app.next_option(),
+        },
+        Action::Toggle
+            if app.active_panel == AppPanel::Options
+                && app.selected_option() == Some(OptionKind::PowerMizer) =>
+        {
+            let max_performance = app.powermizer_max_performance != Some(true);
+            run_task(
+                app,
+                events,
+                "Updating PowerMizer...",
+                Task::System,
+                move |_| envycontrol::set_powermizer_mode(max_performance),
+            );
+        }
+        Action::Toggle if app.active_panel == AppPanel::Options => {
+            app.toggle_current_option();
+        }
+        #[cfg(feature = "overclock")]
+        Action::Overclock => {
+            app.set_flash("Reading the clock offsets...");
+            overclock::spawn_query(events);
+        }
+        Action::Edit if app.active_panel == AppPanel::Options => match app.editable_option() {
+            Some(InputPurpose::Rtd3Level) => {
+                let levels = Rtd3Level::ALL;
+                let selected = levels
+                    .iter()
+                    .position(|&l| l == app.rtd3_level)
+                    .unwrap_or(0);
+                let items = levels.iter().map(|l| l.to_string()).collect();
+                app.open_input(
+                    InputPurpose::Rtd3Level,
+                    "RTD3 Level",
+                    InputWidget::Select(SelectList::new(items, selected)),
+                );
+            }
+            Some(InputPurpose::CoolbitsValue) => app.open_input(
+                InputPurpose::CoolbitsValue,
+                "Coolbits Value",
+                InputWidget::Number(NumberInput::new(i64::from(app.coolbits_value), 0, 31)),
+            ),
+            _ => {}
+        },
+        Action::OptionDetails => app.state = AppState::OptionDetails,
+        Action::Compact => app.compact = !app.compact,
+        Action::SaveProfile => app.open_input(
+            InputPurpose::ProfileName,
+            "Save Profile As",
+            InputWidget::Text(TextInput::new().max_len(32)),
+        ),
+        Action::ExportCommand => {
+            let command = format!(
+                "sudo {}",
+                app.switch_options(app.selected_mode()).command_line()
+            );
+            let copied = if clipboard::copy(&command).is_ok() {
+                "Copied to the clipboard"
+            } else {
+                "Could not copy it to the clipboard"
+            };
+            app.set_success(&format!(
+                "{}\n\n{}; it is also printed when envy-tui exits.",
+                command, copied
+            ));
+            app.record(command);
+        }
+        Action::Apply => {
+            if app.detecting {
+                app.set_flash("Still detecting the current mode");
+                return;
+            }
+            if let Some(immutable) = app.immutable {
+                app.set_error(immutable.explanation());
+                return;
+            }
+            let selected = app.selected_mode();
+            app.pending_mode = Some(selected);
+            let processes = app.current_mode != Some(GraphicsMode::Integrated);
+            if processes {
+                app.set_flash("Checking for processes on the dGPU...");
+            }
+            probe::check_switch(events, selected, processes, Duration::ZERO);
+        }
+        Action::Reset => start_reset(app, events),
+        _ => {}
+    }
+}
+
+fn handle_system_action(app: &mut App, events: &Events, action: Action) {
+    match action {
+        Action::EnableServices => {
+            let services = app.disabled_suspend_services();
+            if services.is_empty() {
+                return;
+            }
+            run_privileged_task(
+                app,
+                events,
+                "Enabling services...",
+                Task::System,
+                move |_| system::enable_services(&services),
+            );
+        }
+        Action::TogglePersistenced => {
+            let enable = !app.persistenced.as_ref().is_some_and(|s| s.is_on());
+            run_privileged_task(
+                app,
+                events,
+                "Updating nvidia-persistenced...",
+                Task::System,
+                move |_| system::set_service_enabled(system::PERSISTENCED_SERVICE, enable),
+            );
+        }
+        Action::TogglePersistenceMode if app.persistence_mode.is_some() => {
+            let enable = app.persistence_mode != Some(true);
+            run_privileged_task(
+                app,
+                events,
+                "Updating persistence mode...",
+                Task::System,
+                move |_| envycontrol::set_persistence_mode(enable),
+            );
+        }
+        _ => {}
+    }
+}
+
+fn verify_pending_switch(app: &mut App) {
+    let Some(pending) = state::load_pending_switch() else {
+        return;
+    };
+    if !pending.rebooted_since() {
+        app.reboot_pending = Some(pending.mode);
+        return;
+    }
+    state::clear_pending_switch();
+
+    if app.current_mode == Some(pending.mode) {
+        logging::info(&format!(
+            "Verified switch to {} mode after reboot",
+            pending.mode
+        ));
+        app.set_success(&format!(
+            "Switch to {} mode took effect after reboot.",
+            pending.mode
+        ));
+        return;
+    }
+
+    let actual = app
+        .current_mode
+        .map(|m| m.to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    let mut causes: Vec<String> = system::kernel_param_issues(&app.kernel_params, pending.mode)
+        .into_iter()
+        .chain(system::modprobe_conflicts(
+            &app.modprobe_entries,
+            pending.mode,
+        ))
+        .collect();
+    if causes.is_empty() {
+        causes.push("The initramfs may not have been rebuilt after the switch.".to_string());
+        causes
+            .push("Another GPU switching tool may have overridden the configuration.".to_string());
+    }
+
+    let mut message = format!(
+        "Switch to {} mode did not take effect (current: {}).\nLikely causes:",
+        pending.mode, actual
+    );
+    for cause in causes {
+        message.push_str(&format!("\n• {}", cause));
+    }
+    logging::warn(&message);
+    app.set_error(&message);
+}
+
+fn handle_input_key(app: &mut App, events: &Events, code: KeyCode) {
+    let Some(prompt) = app.input.as_mut() else {
+        app.clear_message();
+        return;
+    };
+
+    match prompt.widget.handle_key(code) {
+        InputEvent::Cancelled => {
+            if app.password_task.take().is_some() {
+                app.pending_mode = None;
+            }
+            app.close_input();
+        }
+        InputEvent::Submitted => submit_input(app, events),
+        InputEvent::Changed => prompt.error = None,
+        InputEvent::Ignored => {}
+    }
+}
+
+fn submit_input(app: &mut App, events: &Events) {
+    let Some(prompt) = app.input.clone() else {
+        return;
+    };
+
+    match (prompt.purpose, &prompt.widget) {
+        (InputPurpose::Rtd3Level, InputWidget::Select(list)) => {
+            app.rtd3_level = Rtd3Level::ALL[list.selected()];
+            app.rtd3_enabled = true;
+            app.refresh_options();
+            app.close_input();
+        }
+        (InputPurpose::CoolbitsValue, InputWidget::Number(number)) => {
+            if let Some(value) = number.value() {
+                app.coolbits_value = value as u8;
+                app.coolbits_enabled = true;
+                app.refresh_options();
+                app.close_input();
+            }
+        }
+        (InputPurpose::ProfileName, InputWidget::Text(text)) => {
+            let name = text.value().trim();
+            let error = if name.is_empty() {
+                Some("Enter a name for the profile.".to_string())
+            } else if !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                Some("Use letters, digits, '-' and '_' only.".to_string())
+            } else if app.config.profiles.contains_key(name) {
+                Some(format!("Profile '{}' already exists.", name))
+            } else {
+                None
+            };
+            if let Some(error) = error {
+                if let Some(prompt) = app.input.as_mut() {
+                    prompt.error = Some(error);
+                }
+                return;
+            }
+
+            let profile = app.current_profile();
+            let name = name.to_string();
+            app.input = None;
+            match config::save_profile(&name, &profile) {
+                Ok(path) => {
+                    app.config.profiles.insert(name.clone(), profile);
+                    app.record(format!("saved profile '{}'", name));
+                    app.set_success(&format!("Saved profile '{}' to {}", name, path.display()));
+                }
+                Err(e) => app.set_error(&format!("Failed to save profile: {}", e)),
+            }
+        }
+        (InputPurpose::Password, InputWidget::Text(text)) => {
+            let password = text.value().to_string();
+            app.close_input();
+            if let Some(held) = app.password_task.take() {
+                let work = held.work;
+                run_task(app, events, &held.message, held.task, move |output| {
+                    system::authenticate(&password)?;
+                    work(output)
+                });
+            }
+        }
+        _ => app.close_input(),
+    }
+}
+
+// The About popup lists where the config and state files live; these keys
+// get the user there.
+fn handle_about_key(app: &mut App, code: KeyCode) {
+    let about = app.about.clone().unwrap_or_default();
+    app.clear_message();
+    let open = |dir: Option<PathBuf>, name: &str| match dir {
+        Some(dir) => match system::open_directory(&dir) {
+            Ok(()) => format!("Opened {}", dir.display()),
+            Err(e) => format!("Couldn't open {}: {}", dir.display(), e),
+        },
+        None => format!("Could not determine the {} directory", name),
+    };
+    let flash = match code {
+        KeyCode::Char('o') => open(about.config_dir, "config"),
+        KeyCode::Char('s') => open(about.state_dir, "state"),
+        KeyCode::Char('y') => {
+            let paths: Vec<String> = [about.config_dir, about.state_dir]
+                .into_iter()
+                .flatten()
+                .map(|dir| dir.display().to_string())
+                .collect();
+            match clipboard::copy(&paths.join("\n")) {
+                Ok(()) => "Copied the config and state directories".to_string(),
+                Err(e) => format!("{}: {}", e, paths.join(", ")),
+            }
+        }
+        _ => return,
+    };
+    app.set_flash(&flash);
+}
+
+fn handle_theme_editor_key(app: &mut App, code: KeyCode) {
+    let Some(editor) = app.theme_editor.as_mut() else {
+        app.clear_message();
+        return;
+    };
+    match code {
+        KeyCode::Up | KeyCode::Char('k') => editor.select_previous(),
+        KeyCode::Down | KeyCode::Char('j') => editor.select_next(),
+        KeyCode::Left | KeyCode::Char('h') => editor.cycle(false),
+        KeyCode::Right | KeyCode::Char('l') => editor.cycle(true),
+        KeyCode::Char('+') | KeyCode::Char('=') => editor.adjust(THEME_ADJUST_STEP),
+        KeyCode::Char('-') => editor.adjust(-THEME_ADJUST_STEP),
+        KeyCode::Char('r') => editor.revert(),
+        KeyCode::Enter | KeyCode::Char('s') => {
+            let changed = editor.changed();
+            let result = (!changed.is_empty()).then(|| theme::save(&editor.theme, &changed));
+            app.theme_editor = None;
+            app.clear_message();
+            // The file watcher picks the saved theme up like any other edit.
+            match result {
+                Some(Ok(path)) => {
+                    app.record(format!("saved {} theme colors", changed.len()));
+                    app.set_flash(&format!(
+                        "Saved {} to {}",
+                        changed.join(", "),
+                        path.display()
+                    ));
+                }
+                Some(Err(e)) => app.set_error(&format!("Failed to save the theme: {:#}", e)),
+                None => {}
+            }
+        }
+        KeyCode::Esc | KeyCode::Char('q') => {
+            app.theme_editor = None;
+            app.clear_message();
+        }
+        _ => {}
+    }
+}
+
+// Opens the panel once nvidia-settings answered, unless something else took
+// the screen in the meantime.
+#[cfg(feature = "overclock")]
+fn open_overclock(app: &mut App, offsets: Option<Offsets>) {
+    if app.state != AppState::Normal {
+        return;
+    }
+    app.flash = None;
+    match offsets {
+        Some(offsets) => {
+            app.overclock = Some(OverclockPanel::new(offsets));
+            app.state = AppState::Overclock;
+        }
+        None => app.set_error(
+            "Could not read the clock offsets: nvidia-settings needs an X session on the NVIDIA GPU.",
+        ),
+    }
+}
+
+// Offsets only reach the driver on Enter; r goes straight back to stock.
+#[cfg(feature = "overclock")]
+fn handle_overclock_key(app: &mut App, events: &Events, code: KeyCode) {
+    let Some(panel) = app.overclock.as_mut() else {
+        app.clear_message();
+        return;
+    };
+    let offsets = match code {
+        KeyCode::Up | KeyCode::Char('k') => {
+            panel.select_previous();
+            return;
+        }
+        KeyCode::Down | KeyCode::Char('j') => {
+            panel.select_next();
+            return;
+        }
+        KeyCode::Left | KeyCode::Char('h') | KeyCode::Char('-') => {
+            panel.adjust(false);
+            return;
+        }
+        KeyCode::Right | KeyCode::Char('l') | KeyCode::Char('+') | KeyCode::Char('=') => {
+            panel.adjust(true);
+            return;
+        }
+        KeyCode::Char('r') if panel.applied.is_stock() => {
+            panel.offsets = Offsets::default();
+            return;
+        }
+        KeyCode::Char('r') => Offsets::default(),
+        KeyCode::Enter if panel.offsets != panel.applied => panel.offsets,
+        KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q') => {
+            app.overclock = None;
+            app.clear_message();
+            return;
+        }
+        _ => return,
+    };
+    app.overclock = None;
+    app.clear_message();
+    run_task(
+        app,
+        events,
+        "Setting clock offsets...",
+        Task::System,
+        move |_| overclock::apply(offsets),
+    );
+}
+
+fn handle_log_search_key(app: &mut App, code: KeyCode) {
+    match app.log_search.handle_key(code) {
+        InputEvent::Submitted => {
+            app.log_search_active = false;
+            app.jump_to_log_match(true);
+        }
+        InputEvent::Cancelled => {
+            app.log_search_active = false;
+            app.log_search.clear();
+        }
+        InputEvent::Changed | InputEvent::Ignored => {}
+    }
+}
+
+fn handle_logs_action(app: &mut App, action: Action) {
+    let page = app.log_view_height.get().max(1);
+    match action {
+        Action::ScrollUp => app.scroll_log_up(1),
+        Action::ScrollDown => app.scroll_log_down(1),
+        Action::PageUp => app.scroll_log_up(page),
+        Action::PageDown => app.scroll_log_down(page),
+        Action::Top => app.scroll_log_to_top(),
+        Action::Bottom => app.log_scroll = 0,
+        Action::Search => {
+            app.log_search.clear();
+            app.log_search_active = true;
+        }
+        Action::NextMatch => app.jump_to_log_match(true),
+        Action::PreviousMatch => app.jump_to_log_match(false),
+        Action::CopyVisible => {
+            let visible = app.log_lines[app.visible_log_range()].join("\n");
+            match clipboard::copy(&visible) {
+                Ok(()) => app.set_success("Copied visible log lines to the clipboard."),
+                Err(e) => app.set_error(&e.to_string()),
+            }
+        }
+        _ => {}
+    }
+}
+
+// One entry of a --replay session or --script. Recorded states are compared
+// rather than applied, so a difference from the recording shows up in the log.
+fn replay(app: &mut App, events: &Events, entry: Option<session::Entry>) {
+    match entry {
+        Some(session::Entry::Key { code, ctrl }) => {
+            // So a scripted run can be recorded too.
+            session::record(session::Entry::Key { code, ctrl });
+            handle_key(app, events, session::key_event(code, ctrl));
+        }
+        Some(session::Entry::State { state }) => {
+            let current = format!("{:?}", app.state);
+            if current != state {
+                session::note_divergence(&format!("recorded {}, now {}", state, current));
+            }
+        }
+        Some(session::Entry::Output { line }) => app.push_progress_line(line),
+        Some(session::Entry::Finished { ok, message }) => match session::take_task() {
+            Some(task) => {
+                let result = if ok {
+                    Ok(message)
+                } else {
+                    Err(anyhow!(message))
+                };
+                finish_task(app, events, task, result);
+            }
+            None => session::note_divergence("a task finished but none is running"),
+        },
+        None if session::scripted() => app.should_quit = true,
+        None => match session::divergences() {
+            0 => app.set_flash("Replay finished"),
+            n => app.set_flash(&format!(
+                "Replay finished; {} steps differ from the recording (see Logs)",
+                n
+            )),
+        },
+    }
+}
+
+fn apply_probe(app: &mut App, probe: Probe) {
+    match probe {
+        Probe::Envycontrol {
+            installed,
+            version,
+            install,
+        } => {
+            app.envycontrol_installed = installed;
+            app.envycontrol_version = version;
+            app.envycontrol_install = install;
+            if !installed {
+                app.current_mode = None;
+                app.set_error(&envycontrol::not_found_message());
+            }
+        }
+        // envycontrol reports the configured mode; until the reboot the old
+        // one is still running.
+        Probe::Mode(Ok(mode)) => {
+            if app.reboot_pending.is_none() || app.current_mode.is_none() {
+                app.current_mode = mode;
+            }
+        }
+        Probe::Mode(Err(e)) => app.set_error(&format!("Failed to query mode: {}", e)),
+        Probe::GpuSettings {
+            persistence_mode,
+            powermizer_max_performance,
+            #[cfg(feature = "overclock")]
+            overclock_unlocked,
+        } => {
+            app.persistence_mode = persistence_mode;
+            app.powermizer_max_performance = powermizer_max_performance;
+            #[cfg(feature = "overclock")]
+            {
+                app.overclock_unlocked = overclock_unlocked;
+            }
+        }
+        Probe::Gpu { info, hardware } => {
+            app.gpu_info = info.map(|info| *info);
+            app.dgpu_hardware = hardware;
+        }
+        Probe::Services {
+            suspend,
+            persistenced,
+        } => {
+            app.services = suspend;
+            app.persistenced = Some(persistenced);
+        }
+        Probe::Setup {
+            kernel_params,
+            modprobe_entries,
+            conflicts,
+            display_manager,
+        } => {
+            app.kernel_params = kernel_params;
+            app.modprobe_entries = modprobe_entries;
+            app.conflicts = conflicts;
+            app.display_manager = display_manager;
+        }
+        Probe::Providers(providers) => app.providers = providers,
+        Probe::Done { refresh } => {
+            // A replay shows the recorded machine instead.
+            if let Some(snapshot) = session::replay_snapshot() {
+                snapshot.apply(app);
+                if !app.envycontrol_installed {
+                    app.set_error(&envycontrol::not_found_message());
+                }
+            }
+            // The GPU was queried before the mode was known.
+            if app.current_mode == Some(GraphicsMode::Integrated) || !app.envycontrol_installed {
+                app.gpu_info = None;
+            }
+            app.detecting = false;
+            if refresh {
+                if app.state == AppState::Normal {
+                    app.set_flash("Refreshed");
+                }
+            } else {
+                if app.state == AppState::Normal {
+                    verify_pending_switch(app);
+                }
+                session::record_snapshot(app);
+            }
+        }
+    }
+    app.refresh_options();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_confirm_waits_for_the_running_task() {
+        let (events, _event_rx) = mpsc::unbounded_channel();
+        let mut app = App::new();
+        app.skip_confirmations = true;
+        app.running = Some("the reset");
+        app.confirm_switch(GraphicsMode::Nvidia);
+
+        assert!(!auto_confirm(&mut app, &events));
+        assert_eq!(app.state, AppState::ConfirmingSwitch);
+        assert_eq!(app.queued_switch, None);
+    }
+
+    #[test]
+    fn a_cancelled_switch_check_is_dropped() {
+        let mut app = App::new();
+        app.warn_gpu_processes(GraphicsMode::Nvidia, Vec::new());
+        // n on the process warning.
+        app.pending_mode = None;
+        app.clear_message();
+
+        apply_switch_check(
+            &mut app,
+            SwitchCheck {
+                mode: GraphicsMode::Nvidia,
+                processes: Vec::new(),
+                running_games: Vec::new(),
+                login_note: None,
+                applied_args: Vec::new(),
+            },
+        );
+        assert_eq!(app.state, AppState::Normal);
+    }
 }
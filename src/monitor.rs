@@ -0,0 +1,332 @@
+use crate::app::GraphicsMode;
+use crate::envycontrol::GpuInfo;
+use crate::host;
+use crate::logging;
+use crate::state;
+use crate::system;
+use anyhow::Result;
+use std::collections::VecDeque;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Ten minutes at the Monitor tab's poll interval.
+const MAX_SAMPLES: usize = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sample {
+    pub time: u64,
+    pub temperature: u32,
+    pub utilization: u32,
+    pub power: Option<u32>,
+}
+
+impl Sample {
+    pub fn now(temperature: u32, utilization: u32, power: Option<u32>) -> Self {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Sample {
+            time,
+            temperature,
+            utilization,
+            power,
+        }
+    }
+
+    fn to_csv(self) -> String {
+        format!(
+            "{},{},{},{}",
+            self.time,
+            self.temperature,
+            self.utilization,
+            self.power
+                .map(|power| power.to_string())
+                .unwrap_or_default()
+        )
+    }
+
+    fn from_csv(line: &str) -> Option<Self> {
+        let mut fields = line.split(',').map(|field| field.trim().parse().ok());
+        Some(Sample {
+            time: fields.next()??,
+            temperature: fields.next()?? as u32,
+            utilization: fields.next()?? as u32,
+            // Empty when nvidia-smi doesn't report it, missing before state
+            // version 2.
+            power: fields.next().flatten().map(|power| power as u32),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    pub samples: VecDeque<Sample>,
+}
+
+impl History {
+    pub fn push(&mut self, sample: Sample) {
+        self.samples.push_back(sample);
+        while self.samples.len() > MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn temperatures(&self) -> Vec<u64> {
+        self.samples.iter().map(|s| s.temperature as u64).collect()
+    }
+
+    pub fn utilizations(&self) -> Vec<u64> {
+        self.samples.iter().map(|s| s.utilization as u64).collect()
+    }
+}
+
+// Averages over the samples taken in one mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModeStats {
+    // None for samples older than the switch history.
+    pub mode: Option<GraphicsMode>,
+    pub samples: usize,
+    pub temperature: u32,
+    pub utilization: u32,
+    pub power: Option<u32>,
+}
+
+impl ModeStats {
+    fn of(mode: Option<GraphicsMode>, samples: &[&Sample]) -> Option<Self> {
+        let average = |values: Vec<u32>| {
+            (!values.is_empty()).then(|| {
+                let sum: u64 = values.iter().map(|&v| u64::from(v)).sum();
+                (sum as f64 / values.len() as f64).round() as u32
+            })
+        };
+        Some(ModeStats {
+            mode,
+            samples: samples.len(),
+            temperature: average(samples.iter().map(|s| s.temperature).collect())?,
+            utilization: average(samples.iter().map(|s| s.utilization).collect())?,
+            power: average(samples.iter().filter_map(|s| s.power).collect()),
+        })
+    }
+}
+
+// The dGPU before and after a switch, to tell whether it helped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Comparison {
+    pub before: ModeStats,
+    pub after: ModeStats,
+}
+
+// Compares the mode running now with the one before it. A switch only takes
+// effect after the reboot, so the running mode is the last switch before this
+// boot (later ones are still pending) and its samples start at the boot; the
+// ones between the switch and the reboot were taken in the previous mode.
+pub fn compare(
+    history: &History,
+    switches: &[(u64, GraphicsMode)],
+    boot_time: u64,
+) -> Option<Comparison> {
+    let i = switches.iter().rposition(|&(time, _)| time < boot_time)?;
+    let (time, mode) = switches[i];
+    let previous = i.checked_sub(1).map(|j| switches[j]);
+    let since = previous.map_or(0, |(time, _)| time);
+    let between = |from: u64, to: u64| -> Vec<&Sample> {
+        history
+            .samples
+            .iter()
+            .filter(|s| s.time >= from && s.time < to)
+            .collect()
+    };
+    Some(Comparison {
+        before: ModeStats::of(previous.map(|(_, mode)| mode), &between(since, time))?,
+        after: ModeStats::of(Some(mode), &between(boot_time, u64::MAX))?,
+    })
+}
+
+// Kept in the state directory so the charts still show what happened before a
+// restart, e.g. around a mode switch and reboot.
+pub fn load_history() -> History {
+    let Some(contents) = state::state_dir()
+        .and_then(|dir| fs::read_to_string(dir.join(state::GPU_HISTORY_FILE)).ok())
+    else {
+        return History::default();
+    };
+    let mut history = History::default();
+    for sample in contents.lines().filter_map(Sample::from_csv) {
+        history.push(sample);
+    }
+    history
+}
+
+pub fn save_history(history: &History) -> Result<()> {
+    let mut contents = format!("{}\n", state::GPU_HISTORY_HEADER);
+    for sample in &history.samples {
+        contents.push_str(&sample.to_csv());
+        contents.push('\n');
+    }
+    fs::write(state::state_file(state::GPU_HISTORY_FILE)?, contents)?;
+    Ok(())
+}
+
+const CSV_HEADER: &str =
+    "time,temperature,utilization,encoder,decoder,graphics_clock,memory_clock,pstate";
+
+// Appends one row per Monitor tab sample to the [monitor] csv_log file, for
+// graphing outside envy-tui. Fields nvidia-smi doesn't report stay empty.
+pub fn append_csv(path: &Path, sample: Sample, gpu: &GpuInfo) -> Result<()> {
+    logging::rotate(path);
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    if file.metadata()?.len() == 0 {
+        writeln!(file, "{}", CSV_HEADER)?;
+    }
+    let field = |value: Option<u32>| value.map(|v| v.to_string()).unwrap_or_default();
+    let clocks = gpu.clocks.as_ref();
+    writeln!(
+        file,
+        "{},{},{},{},{},{},{},{}",
+        sample.time,
+        sample.temperature,
+        sample.utilization,
+        field(gpu.encoder),
+        field(gpu.decoder),
+        field(clocks.map(|c| c.graphics)),
+        field(clocks.map(|c| c.memory)),
+        clocks.map(|c| c.pstate.as_str()).unwrap_or_default()
+    )?;
+    Ok(())
+}
+
+// Feral GameMode, to explain a dGPU that stays awake while a game runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameMode {
+    pub active: bool,
+    pub clients: Vec<GameModeClient>,
+    // apply_gpu_optimisations is accepted in gamemode.ini.
+    pub gpu_optimisations: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameModeClient {
+    pub name: String,
+    // Started with PRIME render offload, i.e. asked for the dGPU.
+    pub offload: bool,
+}
+
+const OFFLOAD_VARIABLES: [&str; 3] = [
+    "__NV_PRIME_RENDER_OFFLOAD=1",
+    "DRI_PRIME=1",
+    "__GLX_VENDOR_LIBRARY_NAME=nvidia",
+];
+
+// None when gamemoded isn't installed.
+pub fn gamemode() -> Option<GameMode> {
+    static INSTALLED: OnceLock<bool> = OnceLock::new();
+    if !*INSTALLED.get_or_init(|| system::command_exists("gamemoded")) {
+        return None;
+    }
+    let active = host::command("gamemoded")
+        .arg("-s")
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains("is active"));
+    Some(GameMode {
+        active,
+        clients: if active {
+            gamemode_clients()
+        } else {
+            Vec::new()
+        },
+        gpu_optimisations: gamemode_gpu_optimisations(),
+    })
+}
+
+// Processes with libgamemode loaded, like gamemodelist finds them.
+fn gamemode_clients() -> Vec<GameModeClient> {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().parse::<u32>().is_ok())
+        .map(|entry| entry.path())
+        .filter(|proc| {
+            fs::read_to_string(proc.join("maps")).is_ok_and(|maps| maps.contains("libgamemode.so"))
+        })
+        .filter_map(|proc| {
+            let name = fs::read_to_string(proc.join("comm")).ok()?;
+            let environ = fs::read(proc.join("environ")).unwrap_or_default();
+            let offload = environ.split(|&byte| byte == 0).any(|variable| {
+                OFFLOAD_VARIABLES
+                    .iter()
+                    .any(|offload| variable == offload.as_bytes())
+            });
+            Some(GameModeClient {
+                name: name.trim().to_string(),
+                offload,
+            })
+        })
+        .collect()
+}
+
+fn gamemode_gpu_optimisations() -> bool {
+    let user = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .map(|dir| dir.join("gamemode.ini"));
+    // The user's file overrides /etc, which overrides the packaged defaults.
+    [
+        user,
+        Some(PathBuf::from("/etc/gamemode.ini")),
+        Some(PathBuf::from("/usr/share/gamemode/gamemode.ini")),
+    ]
+    .into_iter()
+    .flatten()
+    .filter_map(|path| fs::read_to_string(path).ok())
+    .find_map(|contents| {
+        contents.lines().find_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            (key.trim() == "apply_gpu_optimisations")
+                .then(|| value.trim() == "accept-responsibility")
+        })
+    })
+    .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_before_the_reboot_stay_with_the_old_mode() {
+        let mut history = History::default();
+        // Hybrid until the reboot at 100, nvidia after it; the switch was at 50.
+        for time in (0..200).step_by(10) {
+            let nvidia = time >= 100;
+            history.push(Sample {
+                time,
+                temperature: if nvidia { 70 } else { 40 },
+                utilization: 0,
+                power: Some(if nvidia { 30 } else { 5 }),
+            });
+        }
+        let switches = [(0, GraphicsMode::Hybrid), (50, GraphicsMode::Nvidia)];
+
+        let comparison = compare(&history, &switches, 100).unwrap();
+        assert_eq!(comparison.before.mode, Some(GraphicsMode::Hybrid));
+        assert_eq!(
+            (comparison.before.samples, comparison.before.temperature),
+            (5, 40)
+        );
+        assert_eq!(comparison.after.mode, Some(GraphicsMode::Nvidia));
+        assert_eq!(
+            (comparison.after.samples, comparison.after.temperature),
+            (10, 70)
+        );
+
+        // Not rebooted since the switch: nvidia isn't running yet.
+        assert_eq!(compare(&history, &switches, 40), None);
+    }
+}
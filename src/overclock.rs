@@ -0,0 +1,181 @@
+use crate::envycontrol;
+use crate::host;
+use crate::logging;
+use crate::task::{AppEvent, Events};
+use anyhow::{anyhow, Result};
+
+// Coolbits bit 3 unlocks the clock offsets in nvidia-settings.
+pub const COOLBITS_CLOCKS: u8 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Clock {
+    Graphics,
+    Memory,
+}
+
+impl Clock {
+    pub const ALL: [Clock; 2] = [Clock::Graphics, Clock::Memory];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Clock::Graphics => "GPU clock",
+            Clock::Memory => "Memory transfer rate",
+        }
+    }
+
+    fn attribute(self) -> &'static str {
+        match self {
+            Clock::Graphics => "GPUGraphicsClockOffsetAllPerformanceLevels",
+            Clock::Memory => "GPUMemoryTransferRateOffsetAllPerformanceLevels",
+        }
+    }
+
+    // Kept well inside what nvidia-settings accepts: enough for a modest
+    // overclock or underclock, not for finding the card's limits.
+    pub fn limit(self) -> i32 {
+        match self {
+            Clock::Graphics => 150,
+            Clock::Memory => 500,
+        }
+    }
+
+    pub fn step(self) -> i32 {
+        match self {
+            Clock::Graphics => 15,
+            Clock::Memory => 50,
+        }
+    }
+}
+
+// Offsets in MHz; the default is stock clocks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Offsets {
+    pub graphics: i32,
+    pub memory: i32,
+}
+
+impl Offsets {
+    pub fn get(&self, clock: Clock) -> i32 {
+        match clock {
+            Clock::Graphics => self.graphics,
+            Clock::Memory => self.memory,
+        }
+    }
+
+    fn slot(&mut self, clock: Clock) -> &mut i32 {
+        match clock {
+            Clock::Graphics => &mut self.graphics,
+            Clock::Memory => &mut self.memory,
+        }
+    }
+
+    pub fn is_stock(&self) -> bool {
+        *self == Offsets::default()
+    }
+}
+
+// Whether the applied xorg.conf has the clock Coolbit.
+pub fn unlocked() -> bool {
+    envycontrol::applied_coolbits().is_some_and(|coolbits| coolbits & COOLBITS_CLOCKS != 0)
+}
+
+// None unless the clocks are unlocked and nvidia-settings can reach the X
+// server on the NVIDIA GPU.
+pub fn query() -> Option<Offsets> {
+    if !unlocked() {
+        return None;
+    }
+    let read = |clock: Clock| {
+        let output = host::command("nvidia-settings")
+            .args(["-t", "-q", &format!("[gpu:0]/{}", clock.attribute())])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+    };
+    Some(Offsets {
+        graphics: read(Clock::Graphics)?,
+        memory: read(Clock::Memory)?,
+    })
+}
+
+// nvidia-settings runs only when the panel is opened, and off the event loop.
+pub fn spawn_query(events: &Events) {
+    let events = events.clone();
+    tokio::spawn(async move {
+        if let Ok(offsets) = tokio::task::spawn_blocking(query).await {
+            let _ = events.send(AppEvent::ClockOffsets(offsets));
+        }
+    });
+}
+
+// Offsets set this way last until the X server restarts.
+pub fn apply(offsets: Offsets) -> Result<String> {
+    let assignments: Vec<String> = Clock::ALL
+        .iter()
+        .map(|&clock| format!("[gpu:0]/{}={}", clock.attribute(), offsets.get(clock)))
+        .collect();
+    logging::info(&format!(
+        "Running: nvidia-settings -a {}",
+        assignments.join(" -a ")
+    ));
+    let mut command = host::command("nvidia-settings");
+    for assignment in &assignments {
+        command.args(["-a", assignment]);
+    }
+    let output = command.output()?;
+
+    if output.status.success() {
+        Ok(if offsets.is_stock() {
+            "Clock offsets reset to stock.".to_string()
+        } else {
+            format!(
+                "Clock offsets set: GPU {:+} MHz, memory {:+} MHz.",
+                offsets.graphics, offsets.memory
+            )
+        })
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        logging::error(&format!("Failed to set clock offsets: {}", stderr));
+        Err(anyhow!("Failed to set clock offsets: {}", stderr))
+    }
+}
+
+// The overclocking popup: the offsets being edited next to the ones the
+// driver is running with.
+pub struct OverclockPanel {
+    pub applied: Offsets,
+    pub offsets: Offsets,
+    pub selected: usize,
+}
+
+impl OverclockPanel {
+    pub fn new(applied: Offsets) -> Self {
+        Self {
+            applied,
+            offsets: applied,
+            selected: 0,
+        }
+    }
+
+    pub fn clock(&self) -> Clock {
+        Clock::ALL[self.selected.min(Clock::ALL.len() - 1)]
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(Clock::ALL.len() - 1);
+    }
+
+    pub fn adjust(&mut self, up: bool) {
+        let clock = self.clock();
+        let step = if up { clock.step() } else { -clock.step() };
+        let slot = self.offsets.slot(clock);
+        *slot = (*slot + step).clamp(-clock.limit(), clock.limit());
+    }
+}
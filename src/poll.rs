@@ -0,0 +1,215 @@
+use crate::envycontrol::{self, GpuInfo};
+use crate::logging;
+#[cfg(feature = "monitor")]
+use crate::monitor::{self, GameMode, Sample};
+use crate::rules::{self, Output};
+use crate::system::{self, DgpuPower, Provider};
+use crate::task::{AppEvent, Events};
+#[cfg(feature = "monitor")]
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+const POWER_SOURCE_INTERVAL: Duration = Duration::from_secs(5);
+#[cfg(feature = "monitor")]
+const MONITOR_INTERVAL: Duration = Duration::from_secs(2);
+const OUTPUTS_INTERVAL: Duration = Duration::from_secs(2);
+const DGPU_POWER_INTERVAL: Duration = Duration::from_secs(2);
+const GPU_INFO_INTERVAL: Duration = Duration::from_secs(3);
+const LOGS_INTERVAL: Duration = Duration::from_secs(1);
+const ENVYCONTROL_INTERVAL: Duration = Duration::from_secs(3);
+
+// The periodic checks, run off the event loop like the startup probes: even a
+// sysfs read can stall, and nvidia-smi or xrandr take long enough to drop keys.
+pub enum Poll {
+    PowerSource(bool),
+    #[cfg(feature = "monitor")]
+    Monitor {
+        info: Option<Box<GpuInfo>>,
+        sample: Option<Sample>,
+        csv_error: Option<String>,
+        gamemode: Option<GameMode>,
+    },
+    Outputs {
+        outputs: Vec<Output>,
+        // Only queried when the outputs changed.
+        providers: Option<Option<Vec<Provider>>>,
+    },
+    DgpuPower(Option<DgpuPower>),
+    GpuInfo(Option<Box<GpuInfo>>),
+    Logs(Vec<String>),
+    Envycontrol(bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    PowerSource,
+    #[cfg(feature = "monitor")]
+    Monitor,
+    Outputs,
+    DgpuPower,
+    GpuInfo,
+    Logs,
+    Envycontrol,
+}
+
+impl Poll {
+    pub fn kind(&self) -> Kind {
+        match self {
+            Poll::PowerSource(_) => Kind::PowerSource,
+            #[cfg(feature = "monitor")]
+            Poll::Monitor { .. } => Kind::Monitor,
+            Poll::Outputs { .. } => Kind::Outputs,
+            Poll::DgpuPower(_) => Kind::DgpuPower,
+            Poll::GpuInfo(_) => Kind::GpuInfo,
+            Poll::Logs(_) => Kind::Logs,
+            Poll::Envycontrol(_) => Kind::Envycontrol,
+        }
+    }
+}
+
+// A poll is due once its interval has passed and the previous one reported
+// back, so a slow command never piles up behind itself.
+pub struct Timer {
+    interval: Duration,
+    last: Option<Instant>,
+    running: bool,
+}
+
+impl Timer {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last: Some(Instant::now()),
+            running: false,
+        }
+    }
+
+    // Due on the first check.
+    #[cfg(feature = "monitor")]
+    pub fn immediate(interval: Duration) -> Self {
+        Self {
+            last: None,
+            ..Self::new(interval)
+        }
+    }
+
+    pub fn due(&self) -> bool {
+        !self.running && self.last.is_none_or(|last| last.elapsed() >= self.interval)
+    }
+
+    // Counts as a run without polling, e.g. after a refresh that got the same data.
+    pub fn restart(&mut self) {
+        self.last = Some(Instant::now());
+    }
+
+    fn start(&mut self) {
+        self.running = true;
+        self.restart();
+    }
+
+    fn finish(&mut self) {
+        self.running = false;
+    }
+}
+
+pub struct Timers {
+    pub power_source: Timer,
+    #[cfg(feature = "monitor")]
+    pub monitor: Timer,
+    pub outputs: Timer,
+    pub dgpu_power: Timer,
+    pub gpu_info: Timer,
+    pub logs: Timer,
+    pub envycontrol: Timer,
+}
+
+impl Timers {
+    pub fn new() -> Self {
+        Self {
+            power_source: Timer::new(POWER_SOURCE_INTERVAL),
+            #[cfg(feature = "monitor")]
+            monitor: Timer::immediate(MONITOR_INTERVAL),
+            outputs: Timer::new(OUTPUTS_INTERVAL),
+            dgpu_power: Timer::new(DGPU_POWER_INTERVAL),
+            gpu_info: Timer::new(GPU_INFO_INTERVAL),
+            logs: Timer::new(LOGS_INTERVAL),
+            envycontrol: Timer::new(ENVYCONTROL_INTERVAL),
+        }
+    }
+
+    pub fn finish(&mut self, kind: Kind) {
+        match kind {
+            Kind::PowerSource => &mut self.power_source,
+            #[cfg(feature = "monitor")]
+            Kind::Monitor => &mut self.monitor,
+            Kind::Outputs => &mut self.outputs,
+            Kind::DgpuPower => &mut self.dgpu_power,
+            Kind::GpuInfo => &mut self.gpu_info,
+            Kind::Logs => &mut self.logs,
+            Kind::Envycontrol => &mut self.envycontrol,
+        }
+        .finish();
+    }
+}
+
+pub fn spawn<F>(events: &Events, timer: &mut Timer, work: F)
+where
+    F: FnOnce() -> Poll + Send + 'static,
+{
+    timer.start();
+    let events = events.clone();
+    tokio::spawn(async move {
+        if let Ok(poll) = tokio::task::spawn_blocking(work).await {
+            let _ = events.send(AppEvent::Polled(poll));
+        }
+    });
+}
+
+pub fn power_source() -> Poll {
+    Poll::PowerSource(rules::is_on_ac())
+}
+
+// One sample for the Monitor tab, also appended to [monitor] csv_log, and
+// the GameMode status shown next to it.
+#[cfg(feature = "monitor")]
+pub fn monitor(csv_log: Option<PathBuf>) -> Poll {
+    let info = envycontrol::query_gpu_info();
+    let sample = info.as_ref().and_then(GpuInfo::sample);
+    let csv_error = match (&csv_log, sample, &info) {
+        (Some(path), Some(sample), Some(gpu)) => monitor::append_csv(path, sample, gpu)
+            .err()
+            .map(|e| format!("Failed to write GPU metrics to {}: {}", path.display(), e)),
+        _ => None,
+    };
+    Poll::Monitor {
+        info: info.map(Box::new),
+        sample,
+        csv_error,
+        gamemode: monitor::gamemode(),
+    }
+}
+
+// Reading the connector status files is cheap, so hot-plugs are picked up by
+// polling sysfs; xrandr only runs once something changed.
+pub fn outputs(previous: Vec<Output>) -> Poll {
+    let outputs = rules::connected_outputs();
+    let providers = (outputs != previous).then(system::query_providers);
+    Poll::Outputs { outputs, providers }
+}
+
+pub fn dgpu_power() -> Poll {
+    Poll::DgpuPower(system::dgpu_power())
+}
+
+// Keeps the header's GPU line current in nvidia mode.
+pub fn gpu_info() -> Poll {
+    Poll::GpuInfo(envycontrol::query_gpu_info().map(Box::new))
+}
+
+pub fn logs() -> Poll {
+    Poll::Logs(logging::read_recent())
+}
+
+pub fn envycontrol() -> Poll {
+    Poll::Envycontrol(envycontrol::is_envycontrol_installed())
+}
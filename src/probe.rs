@@ -0,0 +1,237 @@
+use crate::app::{AboutInfo, GraphicsMode};
+use crate::config;
+use crate::distro;
+use crate::envycontrol::{self, GpuInfo, GpuProcess, InstallMethod};
+#[cfg(feature = "overclock")]
+use crate::overclock;
+use crate::session;
+use crate::state;
+use crate::system::{self, KernelParam, ModprobeEntry, PciGpu, Provider, ServiceStatus};
+use crate::task::{AppEvent, Events};
+use anyhow::Result;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+// Startup detection, run off the event loop so the UI shows right away:
+// nvidia-smi alone can take seconds to wake the dGPU. Independent checks run
+// in parallel and each result is sent as soon as it is known.
+pub enum Probe {
+    Envycontrol {
+        installed: bool,
+        version: Option<String>,
+        install: Option<InstallMethod>,
+    },
+    Mode(Result<Option<GraphicsMode>>),
+    // Settings that are only queried in the modes they apply to.
+    GpuSettings {
+        persistence_mode: Option<bool>,
+        powermizer_max_performance: Option<bool>,
+        #[cfg(feature = "overclock")]
+        overclock_unlocked: bool,
+    },
+    Gpu {
+        info: Option<Box<GpuInfo>>,
+        hardware: Option<PciGpu>,
+    },
+    Services {
+        suspend: Vec<ServiceStatus>,
+        persistenced: ServiceStatus,
+    },
+    Setup {
+        kernel_params: Vec<KernelParam>,
+        modprobe_entries: Vec<ModprobeEntry>,
+        conflicts: Vec<String>,
+        display_manager: Option<String>,
+    },
+    Providers(Option<Vec<Provider>>),
+    // Every probe has reported; checks that need the whole picture can run.
+    // `refresh` is set when R asked for them again.
+    Done {
+        refresh: bool,
+    },
+}
+
+// Each probe sends its results as it gets them.
+type Detect = fn(&dyn Fn(Probe));
+
+const PROBES: [Detect; 5] = [
+    detect_envycontrol,
+    detect_gpu,
+    detect_services,
+    detect_setup,
+    detect_providers,
+];
+
+pub fn spawn(events: &Events) {
+    run(events, false);
+}
+
+// Everything the startup detection found, detected again.
+pub fn refresh(events: &Events) {
+    run(events, true);
+}
+
+fn run(events: &Events, refresh: bool) {
+    // A replay shows the recorded machine instead.
+    let probes: &[_] = if session::replay_snapshot().is_some() {
+        &[]
+    } else {
+        &PROBES
+    };
+    let events = events.clone();
+    tokio::spawn(async move {
+        let handles: Vec<_> = probes
+            .iter()
+            .map(|probe| {
+                let events = events.clone();
+                let probe = *probe;
+                tokio::task::spawn_blocking(move || {
+                    probe(&|result| {
+                        let _ = events.send(AppEvent::Probed(result));
+                    })
+                })
+            })
+            .collect();
+        for handle in handles {
+            let _ = handle.await;
+        }
+        let _ = events.send(AppEvent::Probed(Probe::Done { refresh }));
+    });
+}
+
+// What the switch confirmation needs to know about the machine.
+pub struct SwitchCheck {
+    pub mode: GraphicsMode,
+    pub processes: Vec<GpuProcess>,
+    pub running_games: Vec<String>,
+    pub login_note: Option<String>,
+    pub applied_args: Vec<String>,
+}
+
+// Gathered off the event loop when a switch is about to be confirmed, since
+// nvidia-smi may have to wake the dGPU. `settle` gives processes that were
+// just killed a moment to release the device.
+pub fn check_switch(events: &Events, mode: GraphicsMode, processes: bool, settle: Duration) {
+    let events = events.clone();
+    tokio::spawn(async move {
+        let check = tokio::task::spawn_blocking(move || {
+            thread::sleep(settle);
+            SwitchCheck {
+                mode,
+                processes: if processes {
+                    envycontrol::query_gpu_processes()
+                } else {
+                    Vec::new()
+                },
+                running_games: system::running_games(),
+                login_note: (mode == GraphicsMode::Nvidia)
+                    .then(system::gdm_nvidia_note)
+                    .flatten(),
+                applied_args: envycontrol::applied_option_args(Path::new("/"), mode),
+            }
+        });
+        if let Ok(check) = check.await {
+            let _ = events.send(AppEvent::SwitchChecked(check));
+        }
+    });
+}
+
+// The About popup asks envycontrol and supergfxctl for their versions.
+pub fn spawn_about(events: &Events) {
+    let events = events.clone();
+    tokio::spawn(async move {
+        let about = tokio::task::spawn_blocking(|| AboutInfo {
+            envycontrol_version: envycontrol::version(),
+            supergfx_version: system::supergfx_version(),
+            session_type: system::session_type(),
+            desktop: system::desktop(),
+            distro: distro::current().pretty_name.clone(),
+            config_dir: config::config_dir(),
+            state_dir: state::state_dir(),
+        });
+        if let Ok(about) = about.await {
+            let _ = events.send(AppEvent::About(Box::new(about)));
+        }
+    });
+}
+
+fn detect_envycontrol(send: &dyn Fn(Probe)) {
+    let installed = envycontrol::is_envycontrol_installed();
+    if !installed {
+        send(Probe::Envycontrol {
+            installed,
+            version: None,
+            install: None,
+        });
+        return;
+    }
+
+    let (version, install, mode) = thread::scope(|scope| {
+        let version = scope.spawn(envycontrol::version);
+        let install = scope.spawn(envycontrol::install_method);
+        let mode = envycontrol::query_mode();
+        (
+            version.join().ok().flatten(),
+            install.join().ok().flatten(),
+            mode,
+        )
+    });
+    send(Probe::Envycontrol {
+        installed,
+        version,
+        install,
+    });
+    let current = match mode {
+        Ok(current) => {
+            send(Probe::Mode(Ok(current)));
+            current
+        }
+        Err(e) => {
+            send(Probe::Mode(Err(e)));
+            return;
+        }
+    };
+
+    send(Probe::GpuSettings {
+        persistence_mode: (current != Some(GraphicsMode::Integrated))
+            .then(envycontrol::query_persistence_mode)
+            .flatten(),
+        powermizer_max_performance: (current == Some(GraphicsMode::Nvidia))
+            .then(envycontrol::query_powermizer_mode)
+            .flatten(),
+        #[cfg(feature = "overclock")]
+        overclock_unlocked: current == Some(GraphicsMode::Nvidia) && overclock::unlocked(),
+    });
+}
+
+// Doesn't wait for the mode: in integrated mode there is no driver to answer,
+// and the result is dropped once the mode is known.
+fn detect_gpu(send: &dyn Fn(Probe)) {
+    let info = envycontrol::query_gpu_info();
+    let hardware = info.is_none().then(system::lspci_nvidia_gpu).flatten();
+    send(Probe::Gpu {
+        info: info.map(Box::new),
+        hardware,
+    });
+}
+
+fn detect_services(send: &dyn Fn(Probe)) {
+    send(Probe::Services {
+        suspend: system::query_suspend_services(),
+        persistenced: system::query_service(system::PERSISTENCED_SERVICE),
+    });
+}
+
+fn detect_setup(send: &dyn Fn(Probe)) {
+    send(Probe::Setup {
+        kernel_params: system::read_kernel_params(),
+        modprobe_entries: system::scan_modprobe_dirs(),
+        conflicts: system::conflicting_switchers(),
+        display_manager: system::display_manager(),
+    });
+}
+
+fn detect_providers(send: &dyn Fn(Probe)) {
+    send(Probe::Providers(system::query_providers()));
+}
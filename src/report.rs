@@ -0,0 +1,64 @@
+use crate::app::GraphicsMode;
+use crate::distro;
+use crate::envycontrol;
+use crate::logging;
+use crate::state;
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+pub const ISSUES_URL: &str = "https://github.com/tassiovirginio/envy-tui/issues/new";
+const REPORT_FILE: &str = "issue-report.md";
+const REPORT_LOG_LINES: usize = 30;
+
+pub struct IssueReport {
+    pub path: PathBuf,
+    pub url: String,
+}
+
+pub fn write_issue_report(error: &str, current_mode: Option<GraphicsMode>) -> Result<IssueReport> {
+    let path = state::state_file(REPORT_FILE)?;
+
+    let log = logging::read_recent();
+    // switch_mode and reset log the command line right before running it.
+    let command = log
+        .iter()
+        .rev()
+        .find_map(|line| line.split_once("Running: ").map(|(_, cmd)| cmd.to_string()))
+        .unwrap_or_else(|| "unknown".to_string());
+    let recent = &log[log.len().saturating_sub(REPORT_LOG_LINES)..];
+
+    let body = format!(
+        "## Environment\n\n\
+         - envy-tui: {}\n\
+         - Distribution: {}\n\
+         - Backend: envycontrol {}\n\
+         - Current mode: {}\n\n\
+         ## Command\n\n```\n{}\n```\n\n\
+         ## Error\n\n```\n{}\n```\n\n\
+         ## Recent log\n\n```\n{}\n```\n",
+        env!("CARGO_PKG_VERSION"),
+        distro::current().name(),
+        envycontrol::version().unwrap_or_else(|| "(version unknown)".to_string()),
+        current_mode.map_or("unknown".to_string(), |m| m.to_string()),
+        command,
+        error.trim(),
+        recent.join("\n"),
+    );
+    fs::write(&path, body)?;
+
+    let title = error.lines().next().unwrap_or("Error");
+    let url = format!("{}?title={}", ISSUES_URL, percent_encode(title));
+    Ok(IssueReport { path, url })
+}
+
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
@@ -0,0 +1,113 @@
+use crate::config::{Condition, Config, Profile};
+use std::fs;
+use std::path::Path;
+
+// Built-in panels; anything else connected counts as an external display.
+const INTERNAL_CONNECTORS: [&str; 3] = ["eDP", "LVDS", "DSI"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Environment {
+    pub on_ac: bool,
+    pub docked: bool,
+}
+
+impl Environment {
+    pub fn satisfies(&self, condition: Condition) -> bool {
+        match condition {
+            Condition::Ac => self.on_ac,
+            Condition::Battery => !self.on_ac,
+            Condition::Docked => self.docked,
+            Condition::Undocked => !self.docked,
+        }
+    }
+}
+
+pub fn detect_environment() -> Environment {
+    Environment {
+        on_ac: is_on_ac(),
+        docked: has_external_display(),
+    }
+}
+
+pub fn is_on_ac() -> bool {
+    let Ok(entries) = fs::read_dir("/sys/class/power_supply") else {
+        return true;
+    };
+
+    let mut found_mains = false;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if read_trimmed(&path.join("type")).as_deref() != Some("Mains") {
+            continue;
+        }
+        found_mains = true;
+        if read_trimmed(&path.join("online")).as_deref() == Some("1") {
+            return true;
+        }
+    }
+
+    // Machines without a mains supply entry (desktops) are always on AC.
+    !found_mains
+}
+
+pub fn has_external_display() -> bool {
+    connected_outputs().iter().any(|output| output.external)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Output {
+    pub connector: String,
+    pub external: bool,
+    // Wired to the NVIDIA GPU rather than the iGPU.
+    pub nvidia: bool,
+}
+
+pub fn connected_outputs() -> Vec<Output> {
+    let Ok(entries) = fs::read_dir("/sys/class/drm") else {
+        return Vec::new();
+    };
+
+    let mut outputs: Vec<Output> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            // Connector directories look like "card1-HDMI-A-1".
+            let (card, connector) = name.split_once('-')?;
+            if read_trimmed(&entry.path().join("status")).as_deref() != Some("connected") {
+                return None;
+            }
+            let driver =
+                fs::read_link(Path::new("/sys/class/drm").join(card).join("device/driver"))
+                    .ok()
+                    .and_then(|target| target.file_name().map(|n| n.to_string_lossy().to_string()));
+            Some(Output {
+                connector: connector.to_string(),
+                external: !INTERNAL_CONNECTORS.iter().any(|c| connector.starts_with(c)),
+                nvidia: driver.as_deref() == Some("nvidia"),
+            })
+        })
+        .collect();
+    outputs.sort_by(|a, b| a.connector.cmp(&b.connector));
+    outputs
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+pub fn matching_login_profile<'a>(
+    config: &'a Config,
+    environment: &Environment,
+) -> Option<(&'a str, &'a Profile)> {
+    config
+        .login
+        .rules
+        .iter()
+        .find(|rule| rule.when.iter().all(|&c| environment.satisfies(c)))
+        .and_then(|rule| {
+            config
+                .profiles
+                .get_key_value(&rule.profile)
+                .map(|(name, profile)| (name.as_str(), profile))
+        })
+}
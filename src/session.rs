@@ -0,0 +1,484 @@
+use crate::app::{App, GraphicsMode};
+use crate::config;
+use crate::host;
+use crate::keymap;
+use crate::logging;
+use crate::task::{AppEvent, Events, Task};
+use anyhow::{anyhow, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::io;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+// --record writes a TUI session to a JSON file: key presses, state changes and
+// the output of privileged commands, with the user's name, host and home
+// directory redacted. --replay plays it back against the mock backend, so a
+// UI bug can be reproduced exactly without a screen recording.
+const FORMAT_VERSION: i64 = 1;
+const SCRIPT_STEP: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Entry {
+    Key {
+        #[serde(rename = "key", with = "key_name")]
+        code: KeyCode,
+        ctrl: bool,
+    },
+    // The Debug name of the AppState the session moved to.
+    State {
+        state: String,
+    },
+    Output {
+        line: String,
+    },
+    Finished {
+        ok: bool,
+        message: String,
+    },
+}
+
+impl Entry {
+    fn redacted(self) -> Self {
+        match self {
+            Entry::Output { line } => Entry::Output {
+                line: redact(&line),
+            },
+            Entry::Finished { ok, message } => Entry::Finished {
+                ok,
+                message: redact(&message),
+            },
+            entry => entry,
+        }
+    }
+}
+
+// Keys are saved by the names keymap.toml uses.
+mod key_name {
+    use crate::keymap;
+    use crossterm::event::KeyCode;
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(code: &KeyCode, serializer: S) -> Result<S::Ok, S::Error> {
+        keymap::key_name(*code).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<KeyCode, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        keymap::parse_key(&name).map_err(de::Error::custom)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Event {
+    // Milliseconds since the recording started.
+    time: u64,
+    #[serde(flatten)]
+    entry: Entry,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Session {
+    envy_tui_session: i64,
+    version: String,
+    terminal: TerminalSize,
+    #[serde(default)]
+    config: String,
+    snapshot: Snapshot,
+    events: Vec<Event>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TerminalSize {
+    width: u16,
+    height: u16,
+}
+
+// What the backend reported when the recording started. A replay shows this
+// instead of probing the machine it runs on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    envycontrol_installed: bool,
+    envycontrol_version: Option<String>,
+    current_mode: Option<GraphicsMode>,
+    reboot_pending: Option<GraphicsMode>,
+}
+
+impl Snapshot {
+    fn of(app: &App) -> Self {
+        Snapshot {
+            envycontrol_installed: app.envycontrol_installed,
+            envycontrol_version: app.envycontrol_version.clone(),
+            current_mode: app.current_mode,
+            reboot_pending: app.reboot_pending,
+        }
+    }
+
+    // The machine a --script runs on: envycontrol installed, hybrid mode.
+    fn demo() -> Self {
+        Snapshot {
+            envycontrol_installed: true,
+            envycontrol_version: None,
+            current_mode: Some(GraphicsMode::Hybrid),
+            reboot_pending: None,
+        }
+    }
+
+    pub fn apply(&self, app: &mut App) {
+        app.envycontrol_installed = self.envycontrol_installed;
+        app.envycontrol_version = self.envycontrol_version.clone();
+        app.current_mode = self.current_mode;
+        app.reboot_pending = self.reboot_pending;
+    }
+}
+
+struct Recorder {
+    path: PathBuf,
+    started: Instant,
+    session: Session,
+}
+
+static RECORDER: Mutex<Option<Recorder>> = Mutex::new(None);
+
+pub fn start_recording(path: PathBuf, app: &App, terminal: (u16, u16)) {
+    let config = config::config_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .unwrap_or_default();
+    let recorder = Recorder {
+        path,
+        started: Instant::now(),
+        session: Session {
+            envy_tui_session: FORMAT_VERSION,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            terminal: TerminalSize {
+                width: terminal.0,
+                height: terminal.1,
+            },
+            config: redact(&config),
+            snapshot: Snapshot::of(app),
+            events: Vec::new(),
+        },
+    };
+    if let Ok(mut slot) = RECORDER.lock() {
+        *slot = Some(recorder);
+    }
+    // Writing the header right away reports an unwritable path at startup.
+    save_recording();
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        // The panicking code may hold the lock.
+        if let Ok(mut slot) = RECORDER.try_lock() {
+            write_recording(&mut slot);
+        }
+        previous(info);
+    }));
+}
+
+pub fn record(entry: Entry) {
+    let Ok(mut slot) = RECORDER.lock() else {
+        return;
+    };
+    let Some(recorder) = slot.as_mut() else {
+        return;
+    };
+    let time = recorder.started.elapsed().as_millis() as u64;
+    recorder.session.events.push(Event {
+        time,
+        entry: entry.redacted(),
+    });
+}
+
+// The snapshot is taken again once startup detection has finished.
+pub fn record_snapshot(app: &App) {
+    if let Ok(mut slot) = RECORDER.lock() {
+        if let Some(recorder) = slot.as_mut() {
+            recorder.session.snapshot = Snapshot::of(app);
+        }
+    }
+}
+
+// The session is kept in memory and written when the TUI exits, or from the
+// panic hook if it crashes.
+pub fn save_recording() {
+    if let Ok(mut slot) = RECORDER.lock() {
+        write_recording(&mut slot);
+    }
+}
+
+fn write_recording(slot: &mut Option<Recorder>) {
+    let Some(recorder) = slot.as_ref() else {
+        return;
+    };
+    let written = serde_json::to_string(&recorder.session)
+        .map_err(io::Error::from)
+        .and_then(|json| fs::write(&recorder.path, json + "\n"));
+    if let Err(e) = written {
+        logging::warn(&format!(
+            "Stopped recording to {}: {}",
+            recorder.path.display(),
+            e
+        ));
+        *slot = None;
+    }
+}
+
+// Home directory, user and host names can show up in command output and
+// paths in config.toml.
+fn redact(text: &str) -> String {
+    let mut text = text.to_string();
+    if let Some(home) = env::var("HOME").ok().filter(|home| home.len() > 1) {
+        text = text.replace(&home, "~");
+    }
+    // Very short names would match inside unrelated words.
+    if let Some(user) = env::var("USER").ok().filter(|user| user.len() > 2) {
+        text = text.replace(&user, "<user>");
+    }
+    let host = fs::read_to_string("/proc/sys/kernel/hostname").unwrap_or_default();
+    let host = host.trim();
+    if !host.is_empty() {
+        text = text.replace(host, "<host>");
+    }
+    text
+}
+
+struct Replay {
+    events: Vec<(u64, Entry)>,
+    // When the None entry that ends the playback is sent.
+    end: u64,
+}
+
+static REPLAY: Mutex<Option<Replay>> = Mutex::new(None);
+static SNAPSHOT: OnceLock<Snapshot> = OnceLock::new();
+static HOLD_TASKS: AtomicBool = AtomicBool::new(false);
+static SCRIPTED: AtomicBool = AtomicBool::new(false);
+static HELD_TASK: Mutex<Option<Task>> = Mutex::new(None);
+static DIVERGENCES: AtomicUsize = AtomicUsize::new(0);
+
+// Loads a recording and sets up the sandbox it plays in, with the recorded
+// config.toml. The sandbox is deleted when the returned directory is dropped.
+pub fn start_replay(path: &Path) -> Result<TempDir> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| anyhow!("Could not read {}: {}", path.display(), e))?;
+    let invalid =
+        |e: serde_json::Error| anyhow!("{} is not a valid session file: {}", path.display(), e);
+    let session: serde_json::Value = serde_json::from_str(&contents).map_err(invalid)?;
+    // Checked first, so an old or newer file says so instead of failing to parse.
+    match session
+        .get("envy_tui_session")
+        .and_then(serde_json::Value::as_i64)
+    {
+        Some(FORMAT_VERSION) => {}
+        Some(version) => {
+            return Err(anyhow!(
+                "{} is session format {}; this envy-tui reads format {}",
+                path.display(),
+                version,
+                FORMAT_VERSION
+            ))
+        }
+        None => return Err(anyhow!("{} is not an envy-tui session", path.display())),
+    }
+    let session: Session = serde_json::from_value(session).map_err(invalid)?;
+    let events: Vec<(u64, Entry)> = session
+        .events
+        .into_iter()
+        .map(|event| (event.time, event.entry))
+        .collect();
+
+    let scratch = sandbox(Some(&session.config))?;
+    HOLD_TASKS.store(true, Ordering::Relaxed);
+    let _ = SNAPSHOT.set(session.snapshot);
+    if let Ok(mut slot) = REPLAY.lock() {
+        let end = events.last().map_or(0, |(time, _)| *time);
+        *slot = Some(Replay { events, end });
+    }
+    Ok(scratch)
+}
+
+// --script drives the TUI with a fixed key sequence, e.g. "3,Enter,y,n,q", or
+// the keys listed in a file (comma or line separated, # starts a comment).
+// Keys are sent SCRIPT_STEP apart; "1500ms" waits longer, "ctrl+r" holds Ctrl.
+// It runs against the mock backend on a demo machine and quits at the end.
+pub fn start_script(script: &str) -> Result<TempDir> {
+    let path = Path::new(script);
+    let script = if path.is_file() {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Could not read {}: {}", path.display(), e))?;
+        contents
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join(",")
+    } else {
+        script.to_string()
+    };
+
+    let mut time = 0;
+    let mut events = Vec::new();
+    for step in script.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        if let Some(ms) = step
+            .strip_suffix("ms")
+            .and_then(|ms| ms.parse::<u64>().ok())
+        {
+            time += ms;
+            continue;
+        }
+        let (key, ctrl) = match step.get(..5) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("ctrl+") => (&step[5..], true),
+            _ => (step, false),
+        };
+        let code =
+            keymap::parse_key(key).map_err(|_| anyhow!("Unknown key '{}' in the script", step))?;
+        time += SCRIPT_STEP.as_millis() as u64;
+        events.push((time, Entry::Key { code, ctrl }));
+    }
+
+    let scratch = sandbox(None)?;
+    let _ = SNAPSHOT.set(Snapshot::demo());
+    SCRIPTED.store(true, Ordering::Relaxed);
+    if let Ok(mut slot) = REPLAY.lock() {
+        // One more step so the last key's result is on screen before quitting.
+        *slot = Some(Replay {
+            events,
+            end: time + SCRIPT_STEP.as_millis() as u64,
+        });
+    }
+    Ok(scratch)
+}
+
+// Scratch state and config directories and the mock backend, so neither a
+// replay nor a script can change anything on this machine.
+fn sandbox(config: Option<&str>) -> Result<TempDir> {
+    let scratch = tempfile::Builder::new()
+        .prefix("envy-tui-replay-")
+        .tempdir()?;
+    let config_dir = scratch.path().join("config");
+    fs::create_dir_all(config_dir.join("envy-tui"))?;
+    if let Some(config) = config {
+        fs::write(config_dir.join("envy-tui/config.toml"), config)?;
+    }
+    env::set_var("XDG_CONFIG_HOME", &config_dir);
+    env::set_var("XDG_STATE_HOME", scratch.path().join("state"));
+    host::use_mock_backend();
+    Ok(scratch)
+}
+
+pub fn replay_snapshot() -> Option<&'static Snapshot> {
+    SNAPSHOT.get()
+}
+
+pub fn scripted() -> bool {
+    SCRIPTED.load(Ordering::Relaxed)
+}
+
+// Privileged tasks don't run during a replay; the recorded result finishes
+// them instead. A script runs them against the mock backend.
+pub fn holds_tasks() -> bool {
+    HOLD_TASKS.load(Ordering::Relaxed)
+}
+
+pub fn hold_task(task: Task) {
+    if let Ok(mut held) = HELD_TASK.lock() {
+        *held = Some(task);
+    }
+}
+
+pub fn take_task() -> Option<Task> {
+    HELD_TASK.lock().ok()?.take()
+}
+
+pub fn note_divergence(what: &str) {
+    DIVERGENCES.fetch_add(1, Ordering::Relaxed);
+    logging::warn(&format!("Replay differs from the recording: {}", what));
+}
+
+pub fn divergences() -> usize {
+    DIVERGENCES.load(Ordering::Relaxed)
+}
+
+// Sends the recorded entries at their recorded times, then None.
+pub fn spawn_replay(events: &Events) {
+    let Some(replay) = REPLAY.lock().ok().and_then(|mut slot| slot.take()) else {
+        return;
+    };
+    let events = events.clone();
+    tokio::spawn(async move {
+        let started = tokio::time::Instant::now();
+        for (time, entry) in replay.events {
+            tokio::time::sleep_until(started + Duration::from_millis(time)).await;
+            if events.send(AppEvent::Replay(Some(entry))).is_err() {
+                return;
+            }
+        }
+        tokio::time::sleep_until(started + Duration::from_millis(replay.end)).await;
+        let _ = events.send(AppEvent::Replay(None));
+    });
+}
+
+pub fn key_event(code: KeyCode, ctrl: bool) -> KeyEvent {
+    let modifiers = if ctrl {
+        KeyModifiers::CONTROL
+    } else {
+        KeyModifiers::NONE
+    };
+    KeyEvent::new(code, modifiers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_file_round_trip() {
+        let session = Session {
+            envy_tui_session: FORMAT_VERSION,
+            version: "0.1.4".to_string(),
+            terminal: TerminalSize {
+                width: 120,
+                height: 40,
+            },
+            config: "[ui]\ncompact = true\n".to_string(),
+            snapshot: Snapshot::demo(),
+            events: vec![
+                Event {
+                    time: 500,
+                    entry: Entry::Key {
+                        code: KeyCode::Char(' '),
+                        ctrl: true,
+                    },
+                },
+                Event {
+                    time: 900,
+                    entry: Entry::Finished {
+                        ok: false,
+                        message: "quote \" tab \t".to_string(),
+                    },
+                },
+            ],
+        };
+        let json = serde_json::to_string(&session).unwrap();
+        assert!(json.starts_with("{\"envy_tui_session\":1,"));
+        assert!(json.contains("{\"time\":500,\"type\":\"key\",\"key\":\"space\",\"ctrl\":true}"));
+        assert!(json.contains("\"current_mode\":\"hybrid\""));
+
+        let read: Session = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.snapshot, session.snapshot);
+        assert_eq!(
+            read.events.iter().map(|e| &e.entry).collect::<Vec<_>>(),
+            session.events.iter().map(|e| &e.entry).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let event = r#"{"time":0,"type":"key","key":"hyper","ctrl":false}"#;
+        assert!(serde_json::from_str::<Event>(event).is_err());
+    }
+}
@@ -0,0 +1,276 @@
+use crate::app::GraphicsMode;
+use crate::logging;
+use anyhow::{anyhow, Result};
+use std::env;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Layout version of the state directory, kept in its VERSION_FILE. Bump it
+// and add a step to `migrate` whenever a file's format changes.
+const STATE_VERSION: u32 = 2;
+const VERSION_FILE: &str = "version";
+const PENDING_SWITCH_FILE: &str = "pending-switch";
+const SWITCH_HISTORY_FILE: &str = "switch-history.csv";
+const SWITCH_HISTORY_HEADER: &str = "time,mode,flags";
+const MAX_SWITCH_HISTORY: usize = 200;
+// Written by the Monitor tab; migrated here so builds without it still can.
+pub const GPU_HISTORY_FILE: &str = "gpu-history.csv";
+pub const GPU_HISTORY_HEADER: &str = "time,temperature,utilization,power";
+const LOCK_FILE: &str = "envy-tui.lock";
+
+pub fn state_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))?;
+    Some(base.join("envy-tui"))
+}
+
+// A file in the state directory, creating the directory first.
+pub fn state_file(name: &str) -> Result<PathBuf> {
+    let dir = state_dir().ok_or_else(|| anyhow!("Could not determine state directory"))?;
+    fs::create_dir_all(&dir)?;
+    Ok(dir.join(name))
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// Brings files written by older versions up to the current layout.
+fn migrate() -> Result<()> {
+    let Some(dir) = state_dir() else {
+        return Ok(());
+    };
+    let version_path = dir.join(VERSION_FILE);
+    let version = match fs::read_to_string(&version_path) {
+        Ok(contents) => contents
+            .trim()
+            .parse()
+            .map_err(|_| anyhow!("{} is not a version number", version_path.display()))?,
+        // Before the version file, the same files lived unversioned in the
+        // same directory. Without any of them it's a fresh install.
+        Err(_) if dir.join(PENDING_SWITCH_FILE).exists() || dir.join(GPU_HISTORY_FILE).exists() => {
+            0
+        }
+        Err(_) => STATE_VERSION,
+    };
+    if version > STATE_VERSION {
+        return Err(anyhow!(
+            "{} was written by a newer envy-tui (state version {}, this one reads {})",
+            dir.display(),
+            version,
+            STATE_VERSION
+        ));
+    }
+    for from in version..STATE_VERSION {
+        match from {
+            0 => migrate_v0(&dir)?,
+            1 => migrate_v1(&dir)?,
+            _ => {}
+        }
+        logging::info(&format!("Migrated state directory to version {}", from + 1));
+    }
+    fs::write(version_path, format!("{}\n", STATE_VERSION))?;
+    Ok(())
+}
+
+// Version 1 added a header row to gpu-history.csv and the switch history, which
+// starts with the switch still waiting for a reboot, if any.
+fn migrate_v0(dir: &Path) -> Result<()> {
+    let history = dir.join(GPU_HISTORY_FILE);
+    if let Ok(contents) = fs::read_to_string(&history) {
+        if !contents.starts_with(GPU_HISTORY_HEADER) {
+            fs::write(&history, format!("{}\n{}", GPU_HISTORY_HEADER, contents))?;
+        }
+    }
+    let switches = dir.join(SWITCH_HISTORY_FILE);
+    if !switches.exists() {
+        let mut contents = format!("{}\n", SWITCH_HISTORY_HEADER);
+        let pending = dir.join(PENDING_SWITCH_FILE);
+        if let Some(mode) = load_pending_switch().map(|pending| pending.mode) {
+            let time = fs::metadata(&pending)
+                .and_then(|meta| meta.modified())
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            contents.push_str(&format!("{},{},\n", time, mode));
+        }
+        fs::write(switches, contents)?;
+    }
+    Ok(())
+}
+
+// Version 2 added the power draw to gpu-history.csv; older rows leave it out.
+fn migrate_v1(dir: &Path) -> Result<()> {
+    let history = dir.join(GPU_HISTORY_FILE);
+    if let Ok(contents) = fs::read_to_string(&history) {
+        if let Some(rows) = contents.strip_prefix("time,temperature,utilization\n") {
+            fs::write(&history, format!("{}\n{}", GPU_HISTORY_HEADER, rows))?;
+        }
+    }
+    Ok(())
+}
+
+pub fn current_boot_id() -> Option<String> {
+    fs::read_to_string("/proc/sys/kernel/random/boot_id")
+        .ok()
+        .map(|id| id.trim().to_string())
+}
+
+// When the running kernel booted, as a Unix time.
+#[cfg(feature = "monitor")]
+pub fn boot_time() -> Option<u64> {
+    fs::read_to_string("/proc/stat")
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("btime "))?
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[derive(Debug, Clone)]
+pub struct PendingSwitch {
+    pub mode: GraphicsMode,
+    pub boot_id: String,
+}
+
+impl PendingSwitch {
+    pub fn rebooted_since(&self) -> bool {
+        current_boot_id().is_some_and(|id| id != self.boot_id)
+    }
+}
+
+// After a successful switch: marks the reboot as pending and adds the switch
+// to switch-history.csv.
+pub fn record_switch(mode: GraphicsMode, flags: &[String]) -> Result<()> {
+    save_pending_switch(mode)?;
+    append_switch_history(mode, flags)
+}
+
+fn save_pending_switch(mode: GraphicsMode) -> Result<()> {
+    let boot_id = current_boot_id().unwrap_or_default();
+    fs::write(
+        state_file(PENDING_SWITCH_FILE)?,
+        format!("mode={}\nboot_id={}\n", mode, boot_id),
+    )?;
+    Ok(())
+}
+
+fn append_switch_history(mode: GraphicsMode, flags: &[String]) -> Result<()> {
+    let path = state_file(SWITCH_HISTORY_FILE)?;
+    let contents = fs::read_to_string(&path).unwrap_or_default();
+    let mut rows: Vec<String> = contents
+        .lines()
+        .filter(|line| *line != SWITCH_HISTORY_HEADER && !line.is_empty())
+        .map(str::to_string)
+        .collect();
+    // Commas would split the flags field; the labels never need them.
+    rows.push(format!(
+        "{},{},{}",
+        unix_time(),
+        mode,
+        flags.join(" ").replace(',', " ")
+    ));
+    let start = rows.len().saturating_sub(MAX_SWITCH_HISTORY);
+    let mut contents = format!("{}\n", SWITCH_HISTORY_HEADER);
+    for row in &rows[start..] {
+        contents.push_str(row);
+        contents.push('\n');
+    }
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+// Time and mode of each recorded switch, oldest first.
+#[cfg(feature = "monitor")]
+pub fn load_switch_history() -> Vec<(u64, GraphicsMode)> {
+    let Some(contents) =
+        state_dir().and_then(|dir| fs::read_to_string(dir.join(SWITCH_HISTORY_FILE)).ok())
+    else {
+        return Vec::new();
+    };
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(',');
+            Some((fields.next()?.parse().ok()?, fields.next()?.parse().ok()?))
+        })
+        .collect()
+}
+
+pub fn load_pending_switch() -> Option<PendingSwitch> {
+    let contents = fs::read_to_string(state_dir()?.join(PENDING_SWITCH_FILE)).ok()?;
+
+    let mut mode = None;
+    let mut boot_id = String::new();
+    for line in contents.lines() {
+        match line.split_once('=') {
+            Some(("mode", value)) => mode = value.parse().ok(),
+            Some(("boot_id", value)) => boot_id = value.to_string(),
+            _ => {}
+        }
+    }
+
+    Some(PendingSwitch {
+        mode: mode?,
+        boot_id,
+    })
+}
+
+pub fn clear_pending_switch() {
+    if let Some(dir) = state_dir() {
+        let _ = fs::remove_file(dir.join(PENDING_SWITCH_FILE));
+    }
+}
+
+// Held for the lifetime of the process; the OS drops the lock on exit, even
+// after a crash.
+pub struct InstanceLock {
+    _file: File,
+}
+
+// Only one envy-tui may run envycontrol at a time: two concurrent switches
+// can corrupt the generated configs.
+pub fn acquire_instance_lock() -> Result<InstanceLock> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(state_file(LOCK_FILE)?)?;
+
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            let mut pid = String::new();
+            let _ = file.read_to_string(&mut pid);
+            let pid = match pid.trim() {
+                "" => String::new(),
+                pid => format!(" (PID {})", pid),
+            };
+            return Err(anyhow!(
+                "envy-tui is already running{}. Close it before starting another instance.",
+                pid
+            ));
+        }
+        Err(TryLockError::Error(e)) => return Err(e.into()),
+    }
+
+    file.set_len(0)?;
+    file.rewind()?;
+    write!(file, "{}", process::id())?;
+    // Under the lock, so two instances never migrate at once.
+    if let Err(e) = migrate() {
+        logging::warn(&format!("State directory not migrated: {}", e));
+    }
+    Ok(InstanceLock { _file: file })
+}
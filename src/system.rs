@@ -0,0 +1,1142 @@
+use crate::app::GraphicsMode;
+use crate::distro;
+use crate::host;
+use crate::logging;
+use anyhow::{anyhow, Result};
+use std::cell::Cell;
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+
+pub const SUSPEND_SERVICES: [&str; 3] = [
+    "nvidia-suspend.service",
+    "nvidia-resume.service",
+    "nvidia-hibernate.service",
+];
+
+pub const PERSISTENCED_SERVICE: &str = "nvidia-persistenced.service";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServiceState {
+    Enabled,
+    Disabled,
+    Missing,
+    Other(String),
+}
+
+impl ServiceState {
+    pub fn label(&self) -> &str {
+        match self {
+            ServiceState::Enabled => "enabled",
+            ServiceState::Disabled => "disabled",
+            ServiceState::Missing => "not installed",
+            ServiceState::Other(state) => state,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ServiceStatus {
+    pub name: &'static str,
+    pub state: ServiceState,
+    pub active: bool,
+}
+
+impl ServiceStatus {
+    pub fn is_on(&self) -> bool {
+        self.active || self.state == ServiceState::Enabled
+    }
+}
+
+pub fn query_service(name: &'static str) -> ServiceStatus {
+    let state = match host::command("systemctl")
+        .args(["is-enabled", name])
+        .output()
+    {
+        Ok(output) => {
+            let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            match stdout.as_str() {
+                "enabled" | "enabled-runtime" => ServiceState::Enabled,
+                "disabled" => ServiceState::Disabled,
+                "not-found" | "" => ServiceState::Missing,
+                other => ServiceState::Other(other.to_string()),
+            }
+        }
+        Err(_) => ServiceState::Missing,
+    };
+
+    let active = host::command("systemctl")
+        .args(["is-active", "--quiet", name])
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false);
+
+    ServiceStatus {
+        name,
+        state,
+        active,
+    }
+}
+
+pub fn query_suspend_services() -> Vec<ServiceStatus> {
+    SUSPEND_SERVICES
+        .iter()
+        .map(|name| query_service(name))
+        .collect()
+}
+
+pub fn enable_services(names: &[&str]) -> Result<String> {
+    logging::info(&format!("Running: systemctl enable {}", names.join(" ")));
+    let output = privileged()
+        .arg("systemctl")
+        .arg("enable")
+        .args(names)
+        .output()?;
+
+    if output.status.success() {
+        Ok(format!("Enabled {}.", names.join(", ")))
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        logging::error(&format!("Failed to enable services: {}", stderr));
+        Err(anyhow!("Failed to enable services: {}", stderr))
+    }
+}
+
+pub fn set_service_enabled(name: &str, enabled: bool) -> Result<String> {
+    let action = if enabled { "enable" } else { "disable" };
+    logging::info(&format!("Running: systemctl {} --now {}", action, name));
+    let output = privileged()
+        .args(["systemctl", action, "--now", name])
+        .output()?;
+
+    if output.status.success() {
+        Ok(format!(
+            "{} {}.",
+            if enabled { "Enabled" } else { "Disabled" },
+            name
+        ))
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        logging::error(&format!("Failed to {} {}: {}", action, name, stderr));
+        Err(anyhow!("Failed to {} {}: {}", action, name, stderr))
+    }
+}
+
+const KNOWN_KERNEL_PARAMS: [(&str, &str); 8] = [
+    (
+        "nvidia-drm.modeset",
+        "Kernel modesetting for the NVIDIA driver",
+    ),
+    ("nvidia-drm.fbdev", "NVIDIA framebuffer console"),
+    ("nouveau.modeset", "Kernel modesetting for nouveau"),
+    ("nomodeset", "Disables kernel modesetting for every GPU"),
+    ("ibt", "Indirect Branch Tracking (older drivers need off)"),
+    ("modprobe.blacklist", "Modules blocked from loading"),
+    ("module_blacklist", "Modules blocked from loading"),
+    ("rd.driver.blacklist", "Modules blocked in the initramfs"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KernelParam {
+    pub key: String,
+    pub value: Option<String>,
+}
+
+impl KernelParam {
+    pub fn description(&self) -> Option<&'static str> {
+        let key = self.key.replace('_', "-");
+        KNOWN_KERNEL_PARAMS
+            .iter()
+            .find(|(known, _)| known.replace('_', "-") == key)
+            .map(|(_, description)| *description)
+            .or_else(|| {
+                (key.starts_with("nvidia") || key.starts_with("nouveau"))
+                    .then_some("NVIDIA/nouveau module option")
+            })
+    }
+
+    pub fn display(&self) -> String {
+        match &self.value {
+            Some(value) => format!("{}={}", self.key, value),
+            None => self.key.clone(),
+        }
+    }
+
+    fn is(&self, key: &str, value: &str) -> bool {
+        self.key.replace('_', "-") == key && self.value.as_deref() == Some(value)
+    }
+
+    fn blacklists(&self, module: &str) -> bool {
+        matches!(
+            self.key.as_str(),
+            "modprobe.blacklist" | "module_blacklist" | "rd.driver.blacklist"
+        ) && self
+            .value
+            .as_deref()
+            .is_some_and(|v| v.split(',').any(|m| m == module))
+    }
+}
+
+pub fn parse_kernel_cmdline(cmdline: &str) -> Vec<KernelParam> {
+    cmdline
+        .split_whitespace()
+        .map(|token| match token.split_once('=') {
+            Some((key, value)) => KernelParam {
+                key: key.to_string(),
+                value: Some(value.to_string()),
+            },
+            None => KernelParam {
+                key: token.to_string(),
+                value: None,
+            },
+        })
+        .filter(|param| param.description().is_some())
+        .collect()
+}
+
+pub fn read_kernel_params() -> Vec<KernelParam> {
+    fs::read_to_string("/proc/cmdline")
+        .map(|cmdline| parse_kernel_cmdline(&cmdline))
+        .unwrap_or_default()
+}
+
+pub fn kernel_param_issues(params: &[KernelParam], mode: GraphicsMode) -> Vec<String> {
+    let mut issues = Vec::new();
+
+    for param in params {
+        match mode {
+            GraphicsMode::Hybrid | GraphicsMode::Nvidia => {
+                if param.key == "nomodeset" {
+                    issues.push(
+                        "nomodeset prevents the NVIDIA driver from setting up displays".to_string(),
+                    );
+                }
+                if param.is("nvidia-drm.modeset", "0") {
+                    issues.push(
+                        "nvidia-drm.modeset=0 breaks Wayland and PRIME synchronization".to_string(),
+                    );
+                }
+                if param.is("nouveau.modeset", "1") {
+                    issues.push(
+                        "nouveau.modeset=1 lets nouveau claim the dGPU before the NVIDIA driver"
+                            .to_string(),
+                    );
+                }
+                if param.blacklists("nvidia") || param.blacklists("nvidia_drm") {
+                    issues.push(format!(
+                        "{} blocks the NVIDIA driver this mode needs",
+                        param.display()
+                    ));
+                }
+            }
+            GraphicsMode::Integrated => {
+                if param.is("nouveau.modeset", "1") {
+                    issues.push(
+                        "nouveau.modeset=1 can load nouveau and keep the dGPU powered".to_string(),
+                    );
+                }
+            }
+        }
+    }
+
+    issues
+}
+
+const MODPROBE_DIRS: [&str; 2] = ["/etc/modprobe.d", "/usr/lib/modprobe.d"];
+
+#[derive(Debug, Clone)]
+pub struct ModprobeEntry {
+    pub file: PathBuf,
+    pub line_number: usize,
+    pub directive: String,
+    pub module: String,
+    pub line: String,
+}
+
+impl ModprobeEntry {
+    pub fn location(&self) -> String {
+        format!("{}:{}", self.file.display(), self.line_number)
+    }
+
+    fn disables(&self, module_prefix: &str) -> bool {
+        let disabling = match self.directive.as_str() {
+            "blacklist" => true,
+            "install" => self.line.contains("/bin/false") || self.line.contains("/bin/true"),
+            _ => false,
+        };
+        disabling && self.module.starts_with(module_prefix)
+    }
+
+    // The key=value pairs of an options line.
+    fn parameters(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.line
+            .split_whitespace()
+            .skip(2)
+            .filter_map(|word| word.split_once('='))
+    }
+}
+
+fn is_gpu_module(module: &str) -> bool {
+    module.starts_with("nvidia") || module.starts_with("nouveau")
+}
+
+pub fn parse_modprobe_conf(file: &Path, contents: &str) -> Vec<ModprobeEntry> {
+    contents
+        .lines()
+        .enumerate()
+        .filter_map(|(i, raw)| {
+            let line = raw.trim();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let mut words = line.split_whitespace();
+            let directive = words.next()?;
+            if !matches!(directive, "blacklist" | "options" | "install" | "alias") {
+                return None;
+            }
+            let module = words.next()?.replace('-', "_");
+            if !is_gpu_module(&module) {
+                return None;
+            }
+            Some(ModprobeEntry {
+                file: file.to_path_buf(),
+                line_number: i + 1,
+                directive: directive.to_string(),
+                module,
+                line: line.to_string(),
+            })
+        })
+        .collect()
+}
+
+pub fn scan_modprobe_dirs() -> Vec<ModprobeEntry> {
+    let mut seen_names = Vec::new();
+    let mut entries = Vec::new();
+
+    // Files in /etc shadow files with the same name in /usr/lib, so scan /etc first.
+    for dir in MODPROBE_DIRS {
+        let Ok(read_dir) = fs::read_dir(dir) else {
+            continue;
+        };
+        let mut paths: Vec<PathBuf> = read_dir
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "conf"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let name = path.file_name().map(|n| n.to_os_string());
+            if seen_names.contains(&name) {
+                continue;
+            }
+            seen_names.push(name);
+            if let Ok(contents) = fs::read_to_string(&path) {
+                entries.extend(parse_modprobe_conf(&path, &contents));
+            }
+        }
+    }
+
+    entries
+}
+
+pub fn modprobe_conflicts(entries: &[ModprobeEntry], mode: GraphicsMode) -> Vec<String> {
+    let mut conflicts = Vec::new();
+
+    if mode != GraphicsMode::Integrated {
+        for entry in entries.iter().filter(|e| e.disables("nvidia")) {
+            conflicts.push(format!(
+                "{} blocks {} ({})",
+                entry.directive,
+                entry.module,
+                entry.location()
+            ));
+        }
+    }
+
+    // Several options lines for one module are normal; only a parameter set
+    // to two different values is a conflict.
+    let options: Vec<(&ModprobeEntry, &str, &str)> = entries
+        .iter()
+        .filter(|e| e.directive == "options")
+        .flat_map(|e| e.parameters().map(move |(key, value)| (e, key, value)))
+        .collect();
+    for (i, (entry, key, value)) in options.iter().enumerate() {
+        let duplicate = options[..i].iter().find(|(other, other_key, other_value)| {
+            other.module == entry.module && other_key == key && other_value != value
+        });
+        if let Some((other, _, other_value)) = duplicate {
+            conflicts.push(format!(
+                "{} {} set to {} in {} and to {} in {}",
+                entry.module,
+                key,
+                other_value,
+                other.location(),
+                value,
+                entry.location()
+            ));
+        }
+    }
+
+    conflicts
+}
+
+pub fn session_type() -> Option<String> {
+    env::var("XDG_SESSION_TYPE").ok().filter(|s| !s.is_empty())
+}
+
+pub fn desktop() -> Option<String> {
+    env::var("XDG_CURRENT_DESKTOP")
+        .ok()
+        .filter(|s| !s.is_empty())
+}
+
+pub fn supergfx_version() -> Option<String> {
+    let output = host::command("supergfxctl")
+        .arg("--version")
+        .output()
+        .ok()?;
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !version.is_empty()).then_some(version)
+}
+
+// Shows a directory in the desktop's file manager, creating it first since
+// the config directory only exists once something was saved.
+pub fn open_directory(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    let mut child = host::command("xdg-open")
+        .arg(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| anyhow!("xdg-open: {}", e))?;
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+// The initramfs generator the host uses. The nvidia modules and the modprobe.d
+// blacklists are baked into the initramfs on these setups, so a switch only
+// takes effect after regenerating it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitramfsTool {
+    UpdateInitramfs,
+    Mkinitcpio,
+    Dracut,
+}
+
+impl InitramfsTool {
+    pub fn command_line(self) -> &'static str {
+        match self {
+            InitramfsTool::UpdateInitramfs => "update-initramfs -u -k all",
+            InitramfsTool::Mkinitcpio => "mkinitcpio -P",
+            InitramfsTool::Dracut => "dracut --force --regenerate-all",
+        }
+    }
+}
+
+// The distro's own tool comes first since dracut is often installed alongside
+// mkinitcpio or update-initramfs without being used.
+pub fn initramfs_tool() -> Option<InitramfsTool> {
+    let mut tools = vec![
+        ("update-initramfs", InitramfsTool::UpdateInitramfs),
+        ("mkinitcpio", InitramfsTool::Mkinitcpio),
+        ("dracut", InitramfsTool::Dracut),
+    ];
+    if let Some(preferred) = distro::current().initramfs_tool() {
+        tools.sort_by_key(|(_, tool)| *tool != preferred);
+    }
+    tools
+        .into_iter()
+        .find(|(program, _)| command_exists(program))
+        .map(|(_, tool)| tool)
+}
+
+pub fn command_exists(program: &str) -> bool {
+    host::command("sh")
+        .arg("-c")
+        .arg(format!("command -v {}", program))
+        .stdout(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+// pkexec, or sudo where there is no pkexec. sudo never prompts: the TUI
+// asks for the password first and only hands it to authenticate().
+fn privilege_prefix() -> &'static [&'static str] {
+    static PKEXEC: OnceLock<bool> = OnceLock::new();
+    if *PKEXEC.get_or_init(|| command_exists("pkexec")) {
+        &["pkexec"]
+    } else {
+        &["sudo", "-n"]
+    }
+}
+
+pub fn privileged() -> Command {
+    let prefix = privilege_prefix();
+    let mut command = host::command(prefix[0]);
+    command.args(&prefix[1..]);
+    command
+}
+
+// Whether sudo wants a password before the next privileged command.
+pub fn needs_password() -> bool {
+    privilege_prefix()[0] == "sudo"
+        && !host::command("sudo")
+            .args(["-n", "true"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+}
+
+// Has sudo cache the credentials for the privileged commands that follow.
+pub fn authenticate(password: &str) -> Result<()> {
+    let mut child = host::command("sudo")
+        .args(["-S", "-v", "-p", ""])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        writeln!(stdin, "{}", password)?;
+    }
+    if child.wait()?.success() {
+        Ok(())
+    } else {
+        logging::warn("sudo did not accept the password");
+        Err(anyhow!("sudo did not accept the password"))
+    }
+}
+
+// The privileged command for steps that must not be cut short (switch, reset,
+// initramfs rebuild), under systemd-inhibit where it exists so idling or
+// closing the lid can't suspend or power off the laptop halfway. The inhibitor
+// belongs to the command, so it is released however the command ends.
+fn inhibited_privileged(why: &str) -> Option<Command> {
+    static SYSTEMD_INHIBIT: OnceLock<bool> = OnceLock::new();
+    if !*SYSTEMD_INHIBIT.get_or_init(|| command_exists("systemd-inhibit")) {
+        return None;
+    }
+    let mut command = host::command("systemd-inhibit");
+    command
+        .arg("--what=idle:sleep:shutdown:handle-lid-switch")
+        .arg("--who=envy-tui")
+        .arg(format!("--why={}", why))
+        .arg("--mode=block")
+        .args(privilege_prefix());
+    Some(command)
+}
+
+// systemd-inhibit needs logind on the system bus; without it (containers,
+// non-systemd inits) it fails before starting the command.
+fn inhibit_failed(error: &str) -> bool {
+    error.starts_with("Failed to inhibit") || error.starts_with("Failed to connect to bus")
+}
+
+pub fn rebuild_initramfs(tool: InitramfsTool, output: &dyn Fn(String)) -> Result<String> {
+    match run_privileged(tool.command_line(), "Rebuilding the initramfs", output) {
+        Ok(()) => Ok("Rebuilt the initramfs.".to_string()),
+        Err(e) => {
+            logging::error(&format!("Failed to rebuild the initramfs: {}", e));
+            Err(anyhow!("Failed to rebuild the initramfs: {}", e))
+        }
+    }
+}
+
+// Runs a command line as root, answering yes to any prompt and passing each
+// line of its combined output on as it arrives. On failure the last line,
+// usually the actual error, is returned.
+pub fn run_privileged(
+    command_line: &str,
+    why: &str,
+    output: &dyn Fn(String),
+) -> Result<(), String> {
+    logging::info(&format!("Running: {}", command_line));
+    // Unbuffered so Python's prints arrive as they happen, not at exit.
+    let script = format!("yes | PYTHONUNBUFFERED=1 {} 2>&1", command_line);
+    if let Some(command) = inhibited_privileged(why) {
+        let started = Cell::new(false);
+        let forward = |line: String| {
+            started.set(true);
+            output(line);
+        };
+        match stream_privileged(command, &script, &forward) {
+            Err(e) if !started.get() && inhibit_failed(&e) => {
+                logging::warn(&format!("Running without systemd-inhibit: {}", e));
+            }
+            result => return result,
+        }
+    }
+    stream_privileged(privileged(), &script, output)
+}
+
+fn stream_privileged(
+    mut command: Command,
+    script: &str,
+    output: &dyn Fn(String),
+) -> Result<(), String> {
+    let mut child = command
+        .arg("sh")
+        .arg("-c")
+        .arg(script)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    let mut last_line = String::new();
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines().map_while(|l| l.ok()) {
+            if line.trim().is_empty() {
+                continue;
+            }
+            logging::info(&line);
+            last_line = line.clone();
+            output(line);
+        }
+    }
+
+    // pkexec's own errors (dismissed or failed authentication) and
+    // systemd-inhibit's go to stderr.
+    let result = child.wait_with_output().map_err(|e| e.to_string())?;
+    if result.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&result.stderr).trim().to_string();
+    Err(if stderr.is_empty() { last_line } else { stderr })
+}
+
+// The module is either loaded or at least installed for the running kernel;
+// in integrated mode it is blacklisted, so not being loaded is fine.
+pub fn nvidia_driver_version() -> Option<String> {
+    loaded_nvidia_version().or_else(installed_nvidia_version)
+}
+
+pub fn loaded_nvidia_version() -> Option<String> {
+    fs::read_to_string("/sys/module/nvidia/version")
+        .ok()
+        .map(|version| version.trim().to_string())
+}
+
+// The module modprobe would load for the running kernel.
+pub fn installed_nvidia_version() -> Option<String> {
+    let output = host::command("modinfo")
+        .args(["-F", "version", "nvidia"])
+        .output()
+        .ok()?;
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !version.is_empty()).then_some(version)
+}
+
+// The NVIDIA display controller's sysfs directory, e.g.
+// /sys/bus/pci/devices/0000:01:00.0. Gone in integrated mode, where envycontrol
+// removes the device from the bus.
+pub fn nvidia_pci_device() -> Option<PathBuf> {
+    fs::read_dir("/sys/bus/pci/devices")
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| {
+            let read = |file: &str| fs::read_to_string(path.join(file)).unwrap_or_default();
+            read("vendor").trim() == "0x10de" && read("class").trim().starts_with("0x03")
+        })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PciGpu {
+    pub name: String,
+    // Vendor and device ID, e.g. "10de:2560".
+    pub id: String,
+}
+
+// The NVIDIA card as lspci names it, e.g. "GA106M [GeForce RTX 3060 Mobile /
+// Max-Q]". Works without the driver, for the header in integrated mode.
+pub fn lspci_nvidia_gpu() -> Option<PciGpu> {
+    let output = host::command("lspci")
+        .args(["-nn", "-d", "10de:"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(parse_lspci_line)
+}
+
+// "01:00.0 VGA compatible controller [0300]: NVIDIA Corporation GA106M [...] [10de:2560] (rev a1)"
+fn parse_lspci_line(line: &str) -> Option<PciGpu> {
+    let (class, description) = line.split_once("]: ")?;
+    if !class.rsplit('[').next()?.starts_with("03") {
+        return None;
+    }
+    let id_start = description.rfind(" [10de:")?;
+    let id = description[id_start + 2..].split(']').next()?.to_string();
+    let name = description[..id_start]
+        .trim_start_matches("NVIDIA Corporation ")
+        .to_string();
+    Some(PciGpu { name, id })
+}
+
+// "active", "suspended", ... from runtime PM; reading it doesn't wake the GPU.
+pub fn dgpu_runtime_status() -> Option<String> {
+    fs::read_to_string(nvidia_pci_device()?.join("power/runtime_status"))
+        .ok()
+        .map(|status| status.trim().to_string())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DgpuPower {
+    // runtime_status: "suspended", "active", "unsupported", ...
+    pub status: String,
+    // Cumulative milliseconds in each state since boot.
+    pub suspended_ms: u64,
+    pub active_ms: u64,
+}
+
+impl DgpuPower {
+    pub fn suspended(&self) -> bool {
+        self.status == "suspended"
+    }
+
+    pub fn suspended_percent(&self) -> Option<u64> {
+        let total = self.suspended_ms + self.active_ms;
+        (total > 0).then(|| self.suspended_ms * 100 / total)
+    }
+
+    pub fn badge(&self) -> String {
+        match (self.status.as_str(), self.suspended_percent()) {
+            // Runtime PM is disabled for the device, so RTD3 isn't in effect.
+            ("unsupported", _) => "dGPU: always on".to_string(),
+            (status, Some(percent)) => {
+                format!("dGPU: {} (off {}% since boot)", status, percent)
+            }
+            (status, None) => format!("dGPU: {}", status),
+        }
+    }
+}
+
+// What the kernel says about the dGPU's runtime power state, which is the only
+// real proof that RTD3 works. Reading these files doesn't wake the device.
+pub fn dgpu_power() -> Option<DgpuPower> {
+    let power = nvidia_pci_device()?.join("power");
+    let read = |file: &str| fs::read_to_string(power.join(file)).ok();
+    let millis = |file: &str| read(file).and_then(|value| value.trim().parse().ok());
+    Some(DgpuPower {
+        status: read("runtime_status")?.trim().to_string(),
+        suspended_ms: millis("runtime_suspended_time").unwrap_or(0),
+        active_ms: millis("runtime_active_time").unwrap_or(0),
+    })
+}
+
+pub fn kernel_release() -> Option<String> {
+    fs::read_to_string("/proc/sys/kernel/osrelease")
+        .ok()
+        .map(|release| release.trim().to_string())
+}
+
+// Other GPU switchers rewrite the same modprobe, udev and Xorg files, so
+// running one next to envycontrol tends to break the next boot.
+pub fn running_switchers() -> Vec<String> {
+    [
+        ("optimus-manager.service", "optimus-manager"),
+        ("com.system76.PowerDaemon.service", "system76-power"),
+        ("supergfxd.service", "supergfxd"),
+    ]
+    .into_iter()
+    .filter(|(service, _)| query_service(service).is_on())
+    .map(|(_, name)| name.to_string())
+    .collect()
+}
+
+// Running switchers and leftover Bumblebee-era power-off setups, by name.
+pub fn conflicting_switchers() -> Vec<String> {
+    let mut conflicts = running_switchers();
+    for setup in legacy_power_off() {
+        if !conflicts.iter().any(|name| name == setup.name) {
+            conflicts.push(setup.name.to_string());
+        }
+    }
+    conflicts
+}
+
+// Bumblebee-era setups that cut the dGPU's power behind the driver's back:
+// they fight RTD3 and the udev rules envycontrol installs for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LegacyPowerOff {
+    pub name: &'static str,
+    // The file that sets it up, the service, or "module loaded".
+    pub source: String,
+}
+
+impl LegacyPowerOff {
+    pub fn removal(&self) -> &'static str {
+        match self.name {
+            "bbswitch" => "remove bbswitch from modules-load.d/modprobe.d, uninstall bbswitch (or bbswitch-dkms) and rebuild the initramfs",
+            "acpi_call" => "delete the units or tmpfiles.d entries writing to /proc/acpi/call and uninstall acpi_call (or acpi_call-dkms)",
+            _ => "sudo systemctl disable --now bumblebeed, then uninstall bumblebee",
+        }
+    }
+}
+
+// Files that load bbswitch or acpi_call at boot or call into them.
+const LEGACY_CONFIG_DIRS: [&str; 4] = [
+    "/etc/modules-load.d",
+    "/etc/modprobe.d",
+    "/etc/tmpfiles.d",
+    "/etc/systemd/system",
+];
+
+const LEGACY_MODULES: [&str; 2] = ["bbswitch", "acpi_call"];
+
+// The legacy modules a config file loads, configures or writes to. A
+// `blacklist bbswitch` line, which is what should be there, doesn't count.
+fn legacy_markers(dir: &str, contents: &str) -> Vec<&'static str> {
+    let mut found = Vec::new();
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let module = match words.as_slice() {
+            [module] if dir == "/etc/modules-load.d" => Some(*module),
+            ["options", module, ..] => Some(*module),
+            // e.g. ExecStart=/usr/bin/modprobe bbswitch load_state=0 in a unit,
+            // but not modprobe -r.
+            _ if words.iter().any(|word| word.ends_with("modprobe")) => {
+                let args: Vec<&str> = words
+                    .iter()
+                    .skip_while(|word| !word.ends_with("modprobe"))
+                    .skip(1)
+                    .copied()
+                    .collect();
+                if args.contains(&"-r") {
+                    None
+                } else {
+                    args.into_iter().find(|arg| !arg.starts_with('-'))
+                }
+            }
+            // The off-method scripts and tmpfiles.d entries.
+            _ if line.contains("/proc/acpi/bbswitch") => Some("bbswitch"),
+            _ if line.contains("/proc/acpi/call") => Some("acpi_call"),
+            _ => None,
+        };
+        if let Some(name) = LEGACY_MODULES
+            .into_iter()
+            .find(|name| Some(*name) == module)
+        {
+            if !found.contains(&name) {
+                found.push(name);
+            }
+        }
+    }
+    found
+}
+
+pub fn legacy_power_off() -> Vec<LegacyPowerOff> {
+    let mut found = Vec::new();
+    for (name, path) in [
+        ("bbswitch", "/proc/acpi/bbswitch"),
+        ("acpi_call", "/proc/acpi/call"),
+    ] {
+        if Path::new(path).exists() {
+            found.push(LegacyPowerOff {
+                name,
+                source: "module loaded".to_string(),
+            });
+        }
+    }
+    for dir in LEGACY_CONFIG_DIRS {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for path in entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+        {
+            // Directories such as *.wants fail to read and are skipped.
+            let Ok(contents) = fs::read_to_string(&path) else {
+                continue;
+            };
+            for name in legacy_markers(dir, &contents) {
+                found.push(LegacyPowerOff {
+                    name,
+                    source: path.display().to_string(),
+                });
+            }
+        }
+    }
+    if query_service("bumblebeed.service").is_on() {
+        found.push(LegacyPowerOff {
+            name: "Bumblebee",
+            source: "bumblebeed.service".to_string(),
+        });
+    }
+    found
+}
+
+// Launchers and compositors by process name, with the name shown to the user.
+const GAME_PROCESSES: [(&str, &str); 5] = [
+    ("steam", "Steam"),
+    ("gamescope", "gamescope"),
+    ("lutris", "Lutris"),
+    ("heroic", "Heroic"),
+    ("bottles", "Bottles"),
+];
+
+// Steam, other launchers and games installed through Steam, which a switch
+// and the reboot after it would close mid-session.
+pub fn running_games() -> Vec<String> {
+    let Ok(output) = host::command("ps").args(["-eo", "comm=,args="]).output() else {
+        return Vec::new();
+    };
+    let mut games: Vec<String> = Vec::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let (comm, args) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        let name = GAME_PROCESSES
+            .iter()
+            .find(|(process, _)| *process == comm)
+            .map(|(_, name)| name.to_string())
+            .or_else(|| steam_game(args));
+        if let Some(name) = name.filter(|name| !games.contains(name)) {
+            games.push(name);
+        }
+    }
+    games
+}
+
+// Steam installs these next to the games in steamapps/common.
+const STEAM_TOOLS: [&str; 4] = [
+    "Proton",
+    "SteamLinuxRuntime",
+    "Steam Linux Runtime",
+    "Steamworks Shared",
+];
+
+// The game's folder under steamapps/common.
+fn steam_game(args: &str) -> Option<String> {
+    args.split("/steamapps/common/")
+        .skip(1)
+        .filter_map(|rest| rest.split('/').next())
+        .find(|folder| !STEAM_TOOLS.iter().any(|tool| folder.starts_with(tool)))
+        .map(str::to_string)
+}
+
+pub fn display_manager() -> Option<String> {
+    let target = fs::read_link("/etc/systemd/system/display-manager.service").ok()?;
+    let name = target.file_stem()?.to_string_lossy().to_string();
+    Some(name)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GdmWayland {
+    DisabledInConfig(PathBuf),
+    DisabledByUdev,
+    Available,
+}
+
+// GDM hides Wayland sessions when custom.conf says so, or through its udev
+// rule when the NVIDIA driver runs without kernel modesetting.
+pub fn gdm_wayland() -> Option<GdmWayland> {
+    if !display_manager().is_some_and(|dm| dm.starts_with("gdm")) {
+        return None;
+    }
+
+    for path in [
+        "/etc/gdm/custom.conf",
+        "/etc/gdm3/custom.conf",
+        "/etc/gdm3/daemon.conf",
+    ] {
+        let Ok(contents) = fs::read_to_string(path) else {
+            continue;
+        };
+        let disabled = contents.lines().any(|line| {
+            let line = line.replace(' ', "");
+            line.eq_ignore_ascii_case("WaylandEnable=false")
+        });
+        if disabled {
+            return Some(GdmWayland::DisabledInConfig(PathBuf::from(path)));
+        }
+    }
+
+    let rule_masked = fs::read_link("/etc/udev/rules.d/61-gdm.rules")
+        .is_ok_and(|target| target == Path::new("/dev/null"));
+    let rule_present = [
+        "/usr/lib/udev/rules.d/61-gdm.rules",
+        "/lib/udev/rules.d/61-gdm.rules",
+    ]
+    .iter()
+    .any(|path| Path::new(path).exists());
+    let modeset = fs::read_to_string("/sys/module/nvidia_drm/parameters/modeset")
+        .is_ok_and(|value| value.trim() == "Y")
+        || read_kernel_params()
+            .iter()
+            .any(|param| param.key == "nvidia-drm.modeset" && param.value.as_deref() == Some("1"));
+    if rule_present && !rule_masked && !modeset {
+        return Some(GdmWayland::DisabledByUdev);
+    }
+    Some(GdmWayland::Available)
+}
+
+// What the login screen will offer after switching to nvidia mode.
+pub fn gdm_nvidia_note() -> Option<String> {
+    let wayland_now = session_type().as_deref() == Some("wayland");
+    let note = match gdm_wayland()? {
+        GdmWayland::DisabledInConfig(path) => format!(
+            "GDM: Wayland is disabled in {}, so you will log in to an X11 session.",
+            path.display()
+        ),
+        GdmWayland::DisabledByUdev => "GDM: nvidia-drm.modeset is off, so GDM's udev rule hides Wayland and you will log in to X11. Add nvidia-drm.modeset=1 to the kernel parameters to keep Wayland.".to_string(),
+        GdmWayland::Available => {
+            return Some("GDM: Wayland sessions stay available; pick the session with the gear icon on the login screen.".to_string());
+        }
+    };
+    Some(if wayland_now {
+        format!("{} Your current session is Wayland.", note)
+    } else {
+        note
+    })
+}
+
+// One line of `xrandr --listproviders`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Provider {
+    pub index: u32,
+    pub name: String,
+    pub capabilities: Vec<String>,
+    pub crtcs: u32,
+    pub outputs: u32,
+    pub associated: u32,
+}
+
+impl Provider {
+    pub fn is_nvidia(&self) -> bool {
+        self.name.to_lowercase().contains("nvidia")
+    }
+
+    pub fn can(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+}
+
+pub fn parse_providers(text: &str) -> Vec<Provider> {
+    text.lines()
+        .filter_map(|line| {
+            let rest = line.strip_prefix("Provider ")?;
+            let (index, rest) = rest.split_once(':')?;
+            let field = |key: &str| {
+                let start = rest.find(key)? + key.len();
+                rest[start..].split_whitespace().next()
+            };
+            let number = |key: &str| field(key).and_then(|v| v.parse().ok()).unwrap_or(0);
+            let capabilities = rest
+                .split_once("cap: ")
+                .and_then(|(_, caps)| caps.split_once(" crtcs:"))
+                .map(|(caps, _)| {
+                    caps.split(',')
+                        .skip(1)
+                        .map(|c| c.trim().to_string())
+                        .filter(|c| !c.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default();
+            Some(Provider {
+                index: index.trim().parse().ok()?,
+                name: rest.split_once("name:")?.1.trim().to_string(),
+                capabilities,
+                crtcs: number("crtcs: "),
+                outputs: number("outputs: "),
+                associated: number("associated providers: "),
+            })
+        })
+        .collect()
+}
+
+// None when there is no X server to ask, e.g. on the console.
+pub fn query_providers() -> Option<Vec<Provider>> {
+    let output = host::command("xrandr")
+        .arg("--listproviders")
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| parse_providers(&String::from_utf8_lossy(&output.stdout)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modprobe_options_conflict_per_parameter() {
+        let mut entries = parse_modprobe_conf(
+            Path::new("/etc/modprobe.d/nvidia.conf"),
+            "options nvidia-drm modeset=1\noptions nvidia NVreg_DynamicPowerManagement=0x02\n",
+        );
+        entries.extend(parse_modprobe_conf(
+            Path::new("/etc/modprobe.d/local.conf"),
+            "options nvidia_drm fbdev=1\noptions nvidia NVreg_DynamicPowerManagement=0x00\n",
+        ));
+        assert_eq!(
+            modprobe_conflicts(&entries, GraphicsMode::Hybrid),
+            ["nvidia NVreg_DynamicPowerManagement set to 0x02 in /etc/modprobe.d/nvidia.conf:2 and to 0x00 in /etc/modprobe.d/local.conf:2"]
+        );
+    }
+
+    #[test]
+    fn only_inhibitor_failures_fall_back() {
+        assert!(inhibit_failed(
+            "Failed to inhibit: The name org.freedesktop.login1 was not provided by any .service files"
+        ));
+        assert!(inhibit_failed(
+            "Failed to connect to bus: No such file or directory"
+        ));
+        assert!(!inhibit_failed(
+            "Error executing command as another user: Request dismissed"
+        ));
+        assert!(!inhibit_failed("sudo: a password is required"));
+    }
+
+    #[test]
+    fn blacklisting_bbswitch_is_not_legacy_setup() {
+        let modprobe =
+            "blacklist bbswitch\n# options bbswitch load_state=0\noptions nouveau modeset=0\n";
+        assert!(legacy_markers("/etc/modprobe.d", modprobe).is_empty());
+        assert_eq!(
+            legacy_markers(
+                "/etc/modprobe.d",
+                "options bbswitch load_state=0 unload_state=1\n"
+            ),
+            ["bbswitch"]
+        );
+        assert_eq!(
+            legacy_markers("/etc/modules-load.d", "bbswitch\n"),
+            ["bbswitch"]
+        );
+        assert!(legacy_markers("/etc/modprobe.d", "bbswitch\n").is_empty());
+        assert_eq!(
+            legacy_markers(
+                "/etc/systemd/system",
+                "[Service]\nExecStart=/usr/bin/modprobe bbswitch load_state=0\n"
+            ),
+            ["bbswitch"]
+        );
+        assert!(legacy_markers(
+            "/etc/systemd/system",
+            "ExecStop=/usr/bin/modprobe -r bbswitch\n"
+        )
+        .is_empty());
+        assert_eq!(
+            legacy_markers(
+                "/etc/tmpfiles.d",
+                "w /proc/acpi/call - - - - \\_SB.PCI0.PEG0.PEGP._OFF\n"
+            ),
+            ["acpi_call"]
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use crate::app::{AboutInfo, GraphicsMode};
+use crate::envycontrol::SwitchOptions;
+#[cfg(feature = "overclock")]
+use crate::overclock::Offsets;
+use crate::poll::Poll;
+use crate::probe::{Probe, SwitchCheck};
+use crate::session;
+use anyhow::{anyhow, Result};
+use tokio::sync::mpsc::UnboundedSender;
+
+// A privileged command running in the background; its result comes back to
+// the main loop as a Finished event.
+pub enum Task {
+    Switch(SwitchOptions),
+    // Kills the processes on the dGPU that need root; the switch to the mode
+    // is checked again afterwards.
+    Terminate(GraphicsMode),
+    Reset,
+    UpdateEnvycontrol,
+    System,
+    RebuildInitramfs,
+}
+
+impl Task {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Task::Switch(_) => "the mode switch",
+            Task::Terminate(_) => "terminating the processes",
+            Task::Reset => "the reset",
+            Task::UpdateEnvycontrol => "the envycontrol update",
+            Task::System => "the system change",
+            Task::RebuildInitramfs => "the initramfs rebuild",
+        }
+    }
+}
+
+pub type Work = Box<dyn FnOnce(&dyn Fn(String)) -> Result<String> + Send>;
+
+// A task waiting for the sudo password.
+pub struct Held {
+    pub message: String,
+    pub task: Task,
+    pub work: Work,
+}
+
+pub enum AppEvent {
+    Output(String),
+    Finished(Task, Result<String>),
+    Probed(Probe),
+    Polled(Poll),
+    SwitchChecked(SwitchCheck),
+    About(Box<AboutInfo>),
+    // The offsets read for the overclocking panel.
+    #[cfg(feature = "overclock")]
+    ClockOffsets(Option<Offsets>),
+    // The next entry of a --replay session; None once it has been played.
+    Replay(Option<session::Entry>),
+}
+
+pub type Events = UnboundedSender<AppEvent>;
+
+// Runs blocking work off the event loop. Output lines it reports are sent as
+// Output events, always before the Finished event.
+pub fn spawn<F>(events: &Events, task: Task, work: F)
+where
+    F: FnOnce(&dyn Fn(String)) -> Result<String> + Send + 'static,
+{
+    if session::holds_tasks() {
+        session::hold_task(task);
+        return;
+    }
+    let events = events.clone();
+    tokio::spawn(async move {
+        let lines = events.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            work(&|line| {
+                let _ = lines.send(AppEvent::Output(line));
+            })
+        })
+        .await
+        .unwrap_or_else(|_| Err(anyhow!("Command failed unexpectedly")));
+        let _ = events.send(AppEvent::Finished(task, result));
+    });
+}
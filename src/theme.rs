@@ -1,5 +1,41 @@
-use ratatui::style::Color;
+use crate::config;
+use anyhow::{anyhow, Context, Result};
+use ratatui::style::{Color, Modifier};
+use std::borrow::Cow;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
 
+const THEME_FILE: &str = "theme.toml";
+
+// Written by `envy-tui config init`, with the built-in colors commented out.
+pub const DEFAULT_THEME: &str = r##"# envy-tui colors. Uncomment a key to change it; values are "#rrggbb" or
+# color names such as "lightgreen". The values shown are the defaults.
+
+# Start from the terminal's 16 ANSI colors instead of the RGB palette below.
+# palette = "default"           # default or ansi
+
+# Take the accent and focused border colors from the running mode.
+# accent_follows_mode = false
+
+# bg = "#16161e"
+# fg = "#dcdce6"
+# accent = "#8b5cf6"
+# success = "#22c55e"
+# error = "#ef4444"
+# warning = "#eab308"
+# muted = "#646478"
+# integrated_color = "#3b82f6"
+# hybrid_color = "#10b981"
+# nvidia_color = "#76b900"
+# border = "#3c3c50"
+# border_focused = "#8b5cf6"
+# selection_bg = "#28283c"
+# pending = "#f97316"
+"##;
+
+#[derive(Clone)]
 pub struct Theme {
     pub bg: Color,
     pub fg: Color,
@@ -14,6 +50,9 @@ pub struct Theme {
     pub border: Color,
     pub border_focused: Color,
     pub selection_bg: Color,
+    pub pending: Color,
+    pub accent_follows_mode: bool,
+    pub monochrome: bool,
 }
 
 impl Default for Theme {
@@ -32,11 +71,182 @@ impl Default for Theme {
             border: Color::Rgb(60, 60, 80),
             border_focused: Color::Rgb(139, 92, 246),
             selection_bg: Color::Rgb(40, 40, 60),
+            pending: Color::Rgb(249, 115, 22),
+            accent_follows_mode: false,
+            monochrome: false,
         }
     }
 }
 
 impl Theme {
+    // Only the terminal's 16 ANSI colors, so the app follows the terminal's
+    // color scheme.
+    pub fn ansi() -> Self {
+        Self {
+            bg: Color::Reset,
+            fg: Color::Reset,
+            accent: Color::Magenta,
+            success: Color::Green,
+            error: Color::Red,
+            warning: Color::Yellow,
+            muted: Color::Gray,
+            integrated_color: Color::Blue,
+            hybrid_color: Color::Cyan,
+            nvidia_color: Color::LightGreen,
+            border: Color::DarkGray,
+            border_focused: Color::Magenta,
+            selection_bg: Color::DarkGray,
+            pending: Color::LightYellow,
+            accent_follows_mode: false,
+            monochrome: false,
+        }
+    }
+
+    // No colors at all: the terminal's own foreground and background, with
+    // states told apart by bold, reverse and underline.
+    pub fn monochrome() -> Self {
+        Self {
+            bg: Color::Reset,
+            fg: Color::Reset,
+            accent: Color::Reset,
+            success: Color::Reset,
+            error: Color::Reset,
+            warning: Color::Reset,
+            muted: Color::Reset,
+            integrated_color: Color::Reset,
+            hybrid_color: Color::Reset,
+            nvidia_color: Color::Reset,
+            border: Color::Reset,
+            border_focused: Color::Reset,
+            selection_bg: Color::Reset,
+            pending: Color::Reset,
+            accent_follows_mode: false,
+            monochrome: true,
+        }
+    }
+
+    // What a style adds in monochrome, where the color alone would have
+    // carried the state.
+    pub fn emphasis(&self, modifier: Modifier) -> Modifier {
+        if self.monochrome {
+            modifier
+        } else {
+            Modifier::empty()
+        }
+    }
+
+    pub fn load() -> Result<Theme> {
+        let Some(path) = theme_path().filter(|path| path.exists()) else {
+            return Ok(match env_palette().as_deref() {
+                Some("ansi") => Theme::ansi(),
+                _ => Theme::default(),
+            });
+        };
+
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut table: toml::Table = toml::from_str(&contents)
+            .with_context(|| format!("Invalid theme in {}", path.display()))?;
+
+        let palette = table.remove("palette");
+        let mut theme = match env_palette().map(toml::Value::String).or(palette) {
+            None => Theme::default(),
+            Some(palette) => match palette.as_str() {
+                Some("default") => Theme::default(),
+                Some("ansi") => Theme::ansi(),
+                _ => {
+                    return Err(anyhow!(
+                        "Invalid palette in {}: expected \"default\" or \"ansi\"",
+                        path.display()
+                    ))
+                }
+            },
+        };
+        if let Some(value) = table.remove("accent_follows_mode") {
+            theme.accent_follows_mode = value.as_bool().ok_or_else(|| {
+                anyhow!(
+                    "Invalid accent_follows_mode in {}: expected true or false",
+                    path.display()
+                )
+            })?;
+        }
+        for (key, value) in table {
+            let color = value
+                .as_str()
+                .and_then(|v| v.parse::<Color>().ok())
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Invalid color for '{}' in {}: expected \"#rrggbb\" or a color name",
+                        key,
+                        path.display()
+                    )
+                })?;
+            *theme
+                .slot(&key)
+                .ok_or_else(|| anyhow!("Unknown theme key '{}' in {}", key, path.display()))? =
+                color;
+        }
+        Ok(theme)
+    }
+
+    pub fn color(&self, key: &str) -> Option<Color> {
+        self.slot_ref(key).copied()
+    }
+
+    fn slot_ref(&self, key: &str) -> Option<&Color> {
+        Some(match key {
+            "bg" => &self.bg,
+            "fg" => &self.fg,
+            "accent" => &self.accent,
+            "success" => &self.success,
+            "error" => &self.error,
+            "warning" => &self.warning,
+            "muted" => &self.muted,
+            "integrated_color" => &self.integrated_color,
+            "hybrid_color" => &self.hybrid_color,
+            "nvidia_color" => &self.nvidia_color,
+            "border" => &self.border,
+            "border_focused" => &self.border_focused,
+            "selection_bg" => &self.selection_bg,
+            "pending" => &self.pending,
+            _ => return None,
+        })
+    }
+
+    pub fn slot(&mut self, key: &str) -> Option<&mut Color> {
+        Some(match key {
+            "bg" => &mut self.bg,
+            "fg" => &mut self.fg,
+            "accent" => &mut self.accent,
+            "success" => &mut self.success,
+            "error" => &mut self.error,
+            "warning" => &mut self.warning,
+            "muted" => &mut self.muted,
+            "integrated_color" => &mut self.integrated_color,
+            "hybrid_color" => &mut self.hybrid_color,
+            "nvidia_color" => &mut self.nvidia_color,
+            "border" => &mut self.border,
+            "border_focused" => &mut self.border_focused,
+            "selection_bg" => &mut self.selection_bg,
+            "pending" => &mut self.pending,
+            _ => return None,
+        })
+    }
+
+    // With accent_follows_mode the accent and focused borders take the color
+    // of the running mode.
+    pub fn for_mode(&self, mode: Option<crate::app::GraphicsMode>) -> Cow<'_, Theme> {
+        match mode {
+            Some(mode) if self.accent_follows_mode => {
+                let mut theme = self.clone();
+                theme.accent = self.mode_color(&mode);
+                theme.border_focused = theme.accent;
+                Cow::Owned(theme)
+            }
+            _ => Cow::Borrowed(self),
+        }
+    }
+
     pub fn mode_color(&self, mode: &crate::app::GraphicsMode) -> Color {
         match mode {
             crate::app::GraphicsMode::Integrated => self.integrated_color,
@@ -45,3 +255,208 @@ impl Theme {
         }
     }
 }
+
+pub const ENV_THEME: &str = "ENVY_TUI_THEME";
+
+// ENVY_TUI_THEME is either a palette name, applied on top of theme.toml, or
+// the path of a theme file to use instead of it.
+fn env_palette() -> Option<String> {
+    env::var(ENV_THEME)
+        .ok()
+        .filter(|value| matches!(value.as_str(), "default" | "ansi"))
+}
+
+pub fn theme_path() -> Option<PathBuf> {
+    match env::var_os(ENV_THEME).filter(|value| !value.is_empty()) {
+        Some(value) if env_palette().is_none() => Some(PathBuf::from(value)),
+        _ => config::config_dir().map(|dir| dir.join(THEME_FILE)),
+    }
+}
+
+// The color keys of theme.toml, in the order the theme editor lists them.
+pub const SLOTS: [&str; 14] = [
+    "bg",
+    "fg",
+    "accent",
+    "muted",
+    "border",
+    "border_focused",
+    "selection_bg",
+    "success",
+    "error",
+    "warning",
+    "pending",
+    "integrated_color",
+    "hybrid_color",
+    "nvidia_color",
+];
+
+// What the editor's palette picker cycles through: the terminal's own colors,
+// then the built-in RGB ones.
+const PALETTE: [Color; 30] = [
+    Color::Reset,
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Gray,
+    Color::DarkGray,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+    Color::White,
+    Color::Rgb(22, 22, 30),
+    Color::Rgb(40, 40, 60),
+    Color::Rgb(60, 60, 80),
+    Color::Rgb(100, 100, 120),
+    Color::Rgb(220, 220, 230),
+    Color::Rgb(139, 92, 246),
+    Color::Rgb(59, 130, 246),
+    Color::Rgb(16, 185, 129),
+    Color::Rgb(34, 197, 94),
+    Color::Rgb(118, 185, 0),
+    Color::Rgb(234, 179, 8),
+    Color::Rgb(249, 115, 22),
+    Color::Rgb(239, 68, 68),
+];
+
+// The in-app theme editor: a working copy that the whole UI previews, and
+// the theme as saved so only what changed is written back.
+pub struct ThemeEditor {
+    pub theme: Theme,
+    saved: Theme,
+    pub selected: usize,
+}
+
+impl ThemeEditor {
+    pub fn new(theme: Theme) -> Self {
+        Self {
+            saved: theme.clone(),
+            theme,
+            selected: 0,
+        }
+    }
+
+    pub fn palette() -> &'static [Color] {
+        &PALETTE
+    }
+
+    pub fn key(&self) -> &'static str {
+        SLOTS[self.selected.min(SLOTS.len() - 1)]
+    }
+
+    pub fn color(&self) -> Color {
+        self.theme.color(self.key()).unwrap_or_default()
+    }
+
+    fn set(&mut self, color: Color) {
+        if let Some(slot) = self.theme.slot(self.key()) {
+            *slot = color;
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(SLOTS.len() - 1);
+    }
+
+    // Moves through the palette; a color that isn't in it starts at either
+    // end.
+    pub fn cycle(&mut self, forward: bool) {
+        let index = match PALETTE.iter().position(|color| *color == self.color()) {
+            Some(i) if forward => (i + 1) % PALETTE.len(),
+            Some(i) => (i + PALETTE.len() - 1) % PALETTE.len(),
+            None if forward => 0,
+            None => PALETTE.len() - 1,
+        };
+        self.set(PALETTE[index]);
+    }
+
+    // Lightens or darkens an RGB color; named colors are left to the
+    // terminal.
+    pub fn adjust(&mut self, step: i16) {
+        if let Color::Rgb(r, g, b) = self.color() {
+            let shift = |channel: u8| (channel as i16 + step).clamp(0, 255) as u8;
+            self.set(Color::Rgb(shift(r), shift(g), shift(b)));
+        }
+    }
+
+    pub fn revert(&mut self) {
+        if let Some(color) = self.saved.color(self.key()) {
+            self.set(color);
+        }
+    }
+
+    pub fn is_changed(&self, key: &str) -> bool {
+        self.theme.color(key) != self.saved.color(key)
+    }
+
+    pub fn changed(&self) -> Vec<&'static str> {
+        SLOTS
+            .into_iter()
+            .filter(|key| self.is_changed(key))
+            .collect()
+    }
+}
+
+// How a color is written to theme.toml.
+pub fn color_name(color: Color) -> String {
+    match color {
+        Color::Rgb(r, g, b) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        color => color.to_string().to_lowercase(),
+    }
+}
+
+// Writes the given keys into theme.toml, replacing their line (commented out
+// or not) so the rest of the file and its comments stay as they are. A
+// missing file starts from the commented defaults.
+pub fn save(theme: &Theme, keys: &[&str]) -> Result<PathBuf> {
+    let path = theme_path().ok_or_else(|| anyhow!("Could not determine config directory"))?;
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => DEFAULT_THEME.to_string(),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read {}", path.display()));
+        }
+    };
+
+    let mut lines: Vec<String> = contents.lines().map(String::from).collect();
+    for key in keys {
+        let Some(color) = theme.color(key) else {
+            continue;
+        };
+        let line = format!("{} = \"{}\"", key, color_name(color));
+        let assigns = |line: &String, commented: bool| {
+            let mut rest = line.trim_start();
+            if commented {
+                rest = rest.trim_start_matches('#').trim_start();
+            }
+            rest.strip_prefix(key)
+                .is_some_and(|rest| rest.trim_start().starts_with('='))
+        };
+        match lines
+            .iter()
+            .position(|line| assigns(line, false))
+            .or_else(|| lines.iter().position(|line| assigns(line, true)))
+        {
+            Some(i) => lines[i] = line,
+            None => lines.push(line),
+        }
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, lines.join("\n") + "\n")
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
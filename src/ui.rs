@@ -1,15 +1,43 @@
 use ratatui::{
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Modifier, Style},
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
+    style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Clear, Padding, Paragraph, Wrap},
+    widgets::{
+        Block, BorderType, Borders, Clear, Padding, Paragraph, Scrollbar, ScrollbarOrientation,
+        ScrollbarState, Tabs, Wrap,
+    },
     Frame,
 };
+#[cfg(feature = "monitor")]
+use ratatui::{
+    symbols::Marker,
+    widgets::{Axis, Chart, Dataset, GraphType, Sparkline},
+};
+use std::borrow::Cow;
+use std::path::PathBuf;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-use crate::app::{App, AppPanel, AppState, GraphicsMode};
-use crate::theme::Theme;
+use crate::app::{App, AppPanel, AppState, AppTab, GraphicsMode};
+#[cfg(feature = "monitor")]
+use crate::config::ChartStyle;
+use crate::config::{BorderStyle, IconSet};
+use crate::envycontrol::{self, Phase};
+use crate::help;
+use crate::input::InputWidget;
+#[cfg(feature = "monitor")]
+use crate::monitor;
+#[cfg(feature = "overclock")]
+use crate::overclock::{Clock, OverclockPanel};
+use crate::system::{self, ServiceState};
+use crate::theme::{self, Theme, ThemeEditor};
 
 pub fn render(frame: &mut Frame, app: &App, theme: &Theme) {
+    // The theme editor previews its working copy on the whole UI.
+    let theme = match (&app.theme_editor, app.state) {
+        (Some(editor), AppState::ThemeEditor) => Cow::Borrowed(&editor.theme),
+        _ => theme.for_mode(app.current_mode),
+    };
+    let theme = &*theme;
     let area = frame.area();
 
     frame.render_widget(Block::default().style(Style::default().bg(theme.bg)), area);
@@ -18,14 +46,24 @@ pub fn render(frame: &mut Frame, app: &App, theme: &Theme) {
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(6),
+            Constraint::Length(1),
+            Constraint::Length(1),
             Constraint::Min(10),
             Constraint::Length(3),
         ])
         .split(area);
 
     render_header(frame, app, theme, chunks[0]);
-    render_main(frame, app, theme, chunks[1]);
-    render_footer(frame, theme, chunks[2]);
+    render_status_line(frame, app, theme, chunks[1]);
+    render_tabs(frame, app, theme, chunks[2]);
+    match app.active_tab {
+        AppTab::Switch => render_main(frame, app, theme, chunks[3]),
+        AppTab::System => render_system(frame, app, theme, chunks[3]),
+        #[cfg(feature = "monitor")]
+        AppTab::Monitor => render_monitor(frame, app, theme, chunks[3]),
+        AppTab::Logs => render_logs(frame, app, theme, chunks[3]),
+    }
+    render_footer(frame, app, theme, chunks[4]);
 
     if app.state != AppState::Normal {
         render_message(frame, app, theme, area);
@@ -33,8 +71,11 @@ pub fn render(frame: &mut Frame, app: &App, theme: &Theme) {
 }
 
 fn render_header(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
-    let title = Line::from(vec![
-        Span::styled("󰾲 ", Style::default().fg(theme.nvidia_color)),
+    let mut title = Line::from(vec![
+        Span::styled(
+            format!("{} ", app.icons.nvidia),
+            Style::default().fg(theme.nvidia_color),
+        ),
         Span::styled(
             "Envy",
             Style::default()
@@ -48,7 +89,8 @@ fn render_header(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
     ]);
 
     let current_mode_text = match &app.current_mode {
-        Some(mode) => format!("Current Mode: {} {}", mode.icon(), mode),
+        Some(mode) => format!("Current Mode: {} {}", app.icons.mode(*mode), mode),
+        None if app.detecting => "Current Mode: detecting…".to_string(),
         None => "Current Mode: Unknown".to_string(),
     };
 
@@ -58,6 +100,30 @@ fn render_header(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
         .map(|m| theme.mode_color(m))
         .unwrap_or(theme.muted);
 
+    if let Some(badge) = app.sandbox.badge() {
+        title.push_span(Span::styled(
+            format!("  [{}]", badge),
+            Style::default().fg(theme.muted),
+        ));
+    }
+    if let Some(immutable) = app.immutable {
+        title.push_span(Span::styled(
+            format!("  [{}]", immutable.badge()),
+            Style::default().fg(theme.warning),
+        ));
+    }
+    if let Some(power) = &app.dgpu_power {
+        let color = if power.suspended() {
+            theme.success
+        } else {
+            theme.warning
+        };
+        title.push_span(Span::styled(
+            format!("  [{}]", power.badge()),
+            Style::default().fg(color),
+        ));
+    }
+
     let mut content = vec![
         Line::from(""),
         title,
@@ -67,21 +133,154 @@ fn render_header(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
         )),
     ];
 
+    if !app.conflicts.is_empty() {
+        content.push(Line::from(Span::styled(
+            format!(
+                "{} Also active: {}. Mixed GPU switchers break boots, see envy-tui doctor",
+                app.icons.warning,
+                app.conflicts.join(", ")
+            ),
+            Style::default()
+                .fg(theme.error)
+                .add_modifier(Modifier::BOLD),
+        )));
+    }
+
+    if let Some(warning) = app.display_warning() {
+        content.push(Line::from(Span::styled(
+            format!("{} {}", app.icons.integrated, warning),
+            Style::default().fg(theme.warning),
+        )));
+    }
+
+    if app.envycontrol_outdated() {
+        let (major, minor, patch) = envycontrol::RECOMMENDED_VERSION;
+        let update = match &app.envycontrol_install {
+            Some(method) if method.update_command().is_none() => method.update_hint(),
+            _ => "press U to update".to_string(),
+        };
+        content.push(Line::from(Span::styled(
+            format!(
+                "envycontrol {} is older than {}.{}.{}: {}",
+                app.envycontrol_version.as_deref().unwrap_or_default(),
+                major,
+                minor,
+                patch,
+                update
+            ),
+            Style::default().fg(theme.warning),
+        )));
+    }
+
+    if !app.envycontrol_installed {
+        content.push(Line::from(Span::styled(
+            "envycontrol not found: install it and envy-tui will pick it up",
+            Style::default().fg(theme.warning),
+        )));
+    }
+
     if let Some(gpu) = &app.gpu_info {
-        content.push(Line::from(vec![
-            Span::styled("󰍹 ", Style::default().fg(theme.nvidia_color)),
-            Span::styled(&gpu.name, Style::default().fg(theme.muted)),
-            Span::styled(" │ ", Style::default().fg(theme.border)),
-            Span::styled("🌡 ", Style::default().fg(theme.warning)),
+        let icons = &app.icons;
+        let separator = format!(" {} ", icons.separator);
+        // A suspended dGPU has no load; the reading would be left over from
+        // before it slept.
+        let utilization = gpu.utilization.filter(|_| {
+            !app.dgpu_power
+                .as_ref()
+                .is_some_and(|power| power.suspended())
+        });
+        // Everything but the GPU name, which is elided to keep the line from
+        // wrapping and pushing the header border off-screen.
+        let mut fixed = vec![
+            icons.gpu.clone(),
+            " ".to_string(),
+            separator.clone(),
+            icons.temperature.clone(),
+            " ".to_string(),
+            gpu.temperature.clone(),
+            separator.clone(),
+            icons.memory.clone(),
+            " ".to_string(),
+            gpu.memory_display(),
+        ];
+        if let Some(percent) = utilization {
+            fixed.extend([
+                separator.clone(),
+                icons.utilization.clone(),
+                " ".to_string(),
+                format!("{}%", percent),
+            ]);
+        }
+        let fixed_width = fixed.iter().map(|part| part.width()).sum::<usize>();
+        let name = truncate_to_width(
+            &gpu.name,
+            (area.width as usize)
+                .saturating_sub(4)
+                .saturating_sub(fixed_width),
+        );
+        let mut line = Line::from(vec![
+            Span::styled(
+                format!("{} ", icons.gpu),
+                Style::default().fg(theme.nvidia_color),
+            ),
+            Span::styled(name, Style::default().fg(theme.muted)),
+            Span::styled(separator.clone(), Style::default().fg(theme.border)),
+            Span::styled(
+                format!("{} ", icons.temperature),
+                Style::default().fg(theme.warning),
+            ),
             Span::styled(&gpu.temperature, Style::default().fg(theme.muted)),
-            Span::styled(" │ ", Style::default().fg(theme.border)),
-            Span::styled("󰍛 ", Style::default().fg(theme.accent)),
+            Span::styled(separator.clone(), Style::default().fg(theme.border)),
+            Span::styled(
+                format!("{} ", icons.memory),
+                Style::default().fg(theme.accent),
+            ),
             Span::styled(gpu.memory_display(), Style::default().fg(theme.muted)),
+        ]);
+        if let Some(percent) = utilization {
+            let color = utilization_color(theme, percent);
+            line.push_span(Span::styled(separator, Style::default().fg(theme.border)));
+            line.push_span(Span::styled(
+                format!("{} ", icons.utilization),
+                Style::default().fg(color),
+            ));
+            line.push_span(Span::styled(
+                format!("{}%", percent),
+                Style::default().fg(color),
+            ));
+        }
+        content.push(line);
+    }
+
+    if app.detecting && app.gpu_info.is_none() && app.dgpu_hardware.is_none() {
+        content.push(Line::from(vec![
+            Span::styled(
+                format!("{} ", app.icons.gpu),
+                Style::default().fg(theme.muted),
+            ),
+            Span::styled("detecting…", Style::default().fg(theme.muted)),
+        ]));
+    }
+
+    if let (None, Some(hardware)) = (&app.gpu_info, &app.dgpu_hardware) {
+        content.push(Line::from(vec![
+            Span::styled(
+                format!("{} ", app.icons.gpu),
+                Style::default().fg(theme.muted),
+            ),
+            Span::styled(
+                format!("{} [{}]", hardware.name, hardware.id),
+                Style::default().fg(theme.muted),
+            ),
+            Span::styled(
+                format!(" {} ", app.icons.separator),
+                Style::default().fg(theme.border),
+            ),
+            Span::styled("driver not in use", Style::default().fg(theme.muted)),
         ]));
     }
 
-    let block = Block::default()
-        .borders(Borders::BOTTOM)
+    let block = bordered(app, Borders::BOTTOM)
         .border_style(Style::default().fg(theme.border))
         .padding(Padding::horizontal(2));
 
@@ -92,10 +291,131 @@ fn render_header(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
     frame.render_widget(paragraph, area);
 }
 
+// A glyph that isn't part of the [icons] set, or its stand-in with ASCII
+// icons.
+fn glyph<'a>(app: &App, glyph: &'a str, ascii: &'a str) -> &'a str {
+    if app.icons.set == IconSet::Ascii {
+        ascii
+    } else {
+        glyph
+    }
+}
+
+fn truncate_to_width(text: &str, max_width: usize) -> String {
+    if text.width() <= max_width {
+        return text.to_string();
+    }
+    let mut truncated = String::new();
+    let mut width = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if width + char_width + 1 > max_width {
+            break;
+        }
+        truncated.push(c);
+        width += char_width;
+    }
+    if max_width > 0 {
+        truncated.push('…');
+    }
+    truncated
+}
+
+// Busy is expected under load, so only a dGPU near its limit stands out.
+fn utilization_color(theme: &Theme, percent: u32) -> Color {
+    match percent {
+        90.. => theme.error,
+        60.. => theme.warning,
+        _ => theme.success,
+    }
+}
+
+fn render_status_line(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    if let Some(flash) = app.active_flash() {
+        let line = Line::from(vec![
+            Span::styled(glyph(app, " ", ""), Style::default().fg(theme.success)),
+            Span::styled(flash, Style::default().fg(theme.success)),
+        ]);
+        frame.render_widget(Paragraph::new(line).alignment(Alignment::Center), area);
+        return;
+    }
+
+    let selected = app.selected_mode();
+    let (current_text, current_color) = match &app.current_mode {
+        Some(mode) => (mode.to_string(), theme.mode_color(mode)),
+        None if app.detecting => ("detecting…".to_string(), theme.muted),
+        None => ("unknown".to_string(), theme.muted),
+    };
+
+    let mut spans = vec![
+        Span::styled("Current: ", Style::default().fg(theme.muted)),
+        Span::styled(current_text, Style::default().fg(current_color)),
+        Span::styled(glyph(app, " → ", " -> "), Style::default().fg(theme.muted)),
+        Span::styled("Selected: ", Style::default().fg(theme.muted)),
+        Span::styled(
+            selected.to_string(),
+            Style::default()
+                .fg(theme.mode_color(&selected))
+                .add_modifier(Modifier::BOLD),
+        ),
+    ];
+    let flags = app.switch_options(selected).summary();
+    if !flags.is_empty() {
+        spans.push(Span::styled(
+            format!(" ({})", flags.join(", ")),
+            Style::default().fg(theme.fg),
+        ));
+    }
+    if let Some(pending) = app.reboot_pending {
+        spans.push(Span::styled(
+            format!("  Pending: {} after reboot", pending),
+            Style::default()
+                .fg(theme.pending)
+                .add_modifier(theme.emphasis(Modifier::UNDERLINED)),
+        ));
+    }
+
+    frame.render_widget(
+        Paragraph::new(Line::from(spans)).alignment(Alignment::Center),
+        area,
+    );
+}
+
+fn render_tabs(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    let selected = AppTab::ALL
+        .iter()
+        .position(|&t| t == app.active_tab)
+        .unwrap_or(0);
+
+    let tabs = Tabs::new(AppTab::ALL.iter().map(|t| t.title()))
+        .select(selected)
+        .style(Style::default().fg(theme.muted))
+        .highlight_style(
+            Style::default()
+                .fg(theme.accent)
+                .add_modifier(Modifier::BOLD | theme.emphasis(Modifier::REVERSED)),
+        )
+        .divider(Span::styled(
+            app.icons.separator.as_str(),
+            Style::default().fg(theme.border),
+        ));
+
+    let tabs_area = Rect::new(
+        area.x + 2,
+        area.y,
+        area.width.saturating_sub(4),
+        area.height,
+    );
+    frame.render_widget(tabs, tabs_area);
+}
+
 fn render_main(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
     let chunks = Layout::default()
         .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .constraints([
+            Constraint::Percentage(app.config.ui.mode_panel_percent()),
+            Constraint::Fill(1),
+        ])
         .margin(1)
         .split(area);
 
@@ -111,24 +431,18 @@ fn render_mode_selection(frame: &mut Frame, app: &App, theme: &Theme, area: Rect
         theme.border
     };
 
-    let block = Block::default()
+    let block = bordered(app, Borders::ALL)
         .title(" Graphics Mode ")
-        .title_style(Style::default().fg(if is_focused {
-            theme.accent
-        } else {
-            theme.muted
-        }))
-        .borders(Borders::ALL)
+        .title_style(focus_style(theme, is_focused))
         .border_style(Style::default().fg(border_color))
         .padding(Padding::new(2, 2, 1, 1));
 
     let inner = block.inner(area);
     frame.render_widget(block, area);
 
-    let modes = GraphicsMode::all();
-    let mode_height = 4;
+    let mode_height = if app.compact { 2 } else { 4 };
 
-    for (i, mode) in modes.iter().enumerate() {
+    for (i, mode) in GraphicsMode::ALL.iter().enumerate() {
         let is_selected = i == app.selected_mode_index && is_focused;
         let is_current = app.current_mode.as_ref() == Some(mode);
         let mode_color = theme.mode_color(mode);
@@ -147,28 +461,55 @@ fn render_mode_selection(frame: &mut Frame, app: &App, theme: &Theme, area: Rect
         };
         let fg = if is_selected { mode_color } else { theme.fg };
 
-        let current_marker = if is_current { " ●" } else { "" };
-        let selector = if is_selected { "▶ " } else { "  " };
+        let current_marker = if is_current {
+            format!(" {}", app.icons.current)
+        } else {
+            String::new()
+        };
+        let pending_marker = if app.reboot_pending == Some(*mode) {
+            format!(" {} after reboot", app.icons.pending)
+        } else {
+            String::new()
+        };
+        let selector = if is_selected {
+            format!("{} ", app.icons.selector)
+        } else {
+            "  ".to_string()
+        };
 
         let lines = vec![
             Line::from(vec![
                 Span::styled(selector, Style::default().fg(theme.accent)),
-                Span::styled(format!("{} ", mode.icon()), Style::default().fg(mode_color)),
+                Span::styled(
+                    format!("{} ", app.icons.mode(*mode)),
+                    Style::default().fg(mode_color),
+                ),
                 Span::styled(
                     format!("{:?}", mode),
                     Style::default().fg(fg).add_modifier(Modifier::BOLD),
                 ),
                 Span::styled(current_marker, Style::default().fg(theme.success)),
+                Span::styled(
+                    pending_marker,
+                    Style::default()
+                        .fg(theme.pending)
+                        .add_modifier(theme.emphasis(Modifier::UNDERLINED)),
+                ),
             ]),
             Line::from(Span::styled(
-                format!("   {}", mode.description()),
+                format!("   {}", card_description(app, mode.description())),
                 Style::default().fg(theme.muted),
             )),
         ];
 
-        let paragraph = Paragraph::new(lines)
-            .style(Style::default().bg(bg))
-            .wrap(Wrap { trim: false });
+        let mut style = Style::default().bg(bg);
+        if is_selected {
+            style = style.add_modifier(theme.emphasis(Modifier::REVERSED));
+        }
+        let mut paragraph = Paragraph::new(lines).style(style);
+        if !app.compact {
+            paragraph = paragraph.wrap(Wrap { trim: false });
+        }
 
         frame.render_widget(paragraph, mode_area);
     }
@@ -182,64 +523,21 @@ fn render_options(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
         theme.border
     };
 
-    let block = Block::default()
-        .title(" Options ")
-        .title_style(Style::default().fg(if is_focused {
-            theme.accent
+    let block = bordered(app, Borders::ALL)
+        .title(if app.has_options() {
+            " Options "
         } else {
-            theme.muted
-        }))
-        .borders(Borders::ALL)
+            " Options (none for this mode) "
+        })
+        .title_style(focus_style(theme, is_focused))
         .border_style(Style::default().fg(border_color))
         .padding(Padding::new(2, 2, 1, 1));
 
     let inner = block.inner(area);
     frame.render_widget(block, area);
 
-    let selected_mode = app.selected_mode();
-
-    // (label, description, is_on, is_toggle)
-    let options: Vec<(String, &str, bool, bool)> = match selected_mode {
-        GraphicsMode::Hybrid => vec![
-            (
-                "RTD3 Power Management".to_string(),
-                "Enables Runtime D3 (RTD3) power management for the dGPU. Allows GPU to enter low-power state when idle.",
-                app.rtd3_enabled,
-                true,
-            ),
-            (
-                format!("RTD3 Level: {}", app.rtd3_level),
-                "Controls RTD3 aggressiveness. Higher levels save more power but may cause latency on GPU wake.",
-                false,
-                app.rtd3_enabled,
-            ),
-        ],
-        GraphicsMode::Nvidia => vec![
-            (
-                "Force Composition Pipeline".to_string(),
-                "Forces full composition pipeline. Fixes screen tearing but may reduce performance slightly.",
-                app.force_comp,
-                true,
-            ),
-            (
-                format!("Coolbits (value: {})", app.coolbits_value),
-                "Enables advanced GPU features like overclocking, fan control, and voltage adjustment.",
-                app.coolbits_enabled,
-                true,
-            ),
-        ],
-        GraphicsMode::Integrated => {
-            vec![(
-                "No additional options available".to_string(),
-                "Integrated mode uses only the iGPU. The dGPU is powered off to save battery.",
-                false,
-                false,
-            )]
-        }
-    };
-
-    let option_height = 4;
-    for (i, (label, description, is_on, is_toggle)) in options.iter().enumerate() {
+    let option_height = if app.compact { 2 } else { 4 };
+    for (i, option) in app.options.iter().enumerate() {
         let is_selected = i == app.selected_option_index && is_focused;
         let y = inner.y + (i as u16 * option_height);
 
@@ -256,166 +554,1710 @@ fn render_options(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
         };
         let fg = if is_selected { theme.accent } else { theme.fg };
 
-        let checkbox = if *is_toggle {
-            if *is_on {
-                "[✓] "
+        let checkbox = if option.is_toggle {
+            if option.is_on {
+                format!("[{}] ", app.icons.check)
             } else {
-                "[ ] "
+                "[ ] ".to_string()
             }
         } else {
-            "    "
+            "    ".to_string()
         };
 
-        let checkbox_color = if *is_on { theme.success } else { theme.muted };
+        let checkbox_color = if option.is_on {
+            theme.success
+        } else {
+            theme.muted
+        };
 
         let lines = vec![
             Line::from(vec![
                 Span::styled(checkbox, Style::default().fg(checkbox_color)),
                 Span::styled(
-                    label.as_str(),
+                    option.label.as_str(),
                     Style::default().fg(fg).add_modifier(Modifier::BOLD),
                 ),
             ]),
-            Line::from(Span::styled(
-                format!("    {}", description),
-                Style::default().fg(theme.muted),
-            )),
+            Line::from(vec![
+                Span::raw("    "),
+                Span::styled(
+                    card_description(app, option.description),
+                    Style::default().fg(theme.muted),
+                ),
+            ]),
         ];
 
-        let paragraph = Paragraph::new(lines)
-            .style(Style::default().bg(bg))
-            .wrap(Wrap { trim: false });
+        let mut style = Style::default().bg(bg);
+        if is_selected {
+            style = style.add_modifier(theme.emphasis(Modifier::REVERSED));
+        }
+        let mut paragraph = Paragraph::new(lines).style(style);
+        if !app.compact {
+            paragraph = paragraph.wrap(Wrap { trim: false });
+        }
         frame.render_widget(paragraph, option_area);
     }
 }
 
-fn render_footer(frame: &mut Frame, theme: &Theme, area: Rect) {
-    let keys = vec![
-        ("↑↓/jk", "Navigate"),
-        ("Tab", "Switch Panel"),
-        ("Enter", "Apply"),
-        ("Space", "Toggle"),
-        ("r", "Reset"),
-        ("q", "Quit"),
-    ];
+// Monochrome marks the focused panel's title, as the border color can't.
+fn focus_style(theme: &Theme, is_focused: bool) -> Style {
+    if is_focused {
+        Style::default()
+            .fg(theme.accent)
+            .add_modifier(theme.emphasis(Modifier::REVERSED))
+    } else {
+        Style::default().fg(theme.muted)
+    }
+}
 
-    let spans: Vec<Span> = keys
+// Compact cards only have room for the first sentence of a description.
+fn card_description<'a>(app: &App, description: &'a str) -> &'a str {
+    if !app.compact {
+        return description;
+    }
+    description
+        .split_once(". ")
+        .map_or(description, |(first, _)| first)
+}
+
+fn render_system(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .margin(1)
+        .split(area);
+
+    let left = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(app.services.len() as u16 + 6),
+            Constraint::Min(7),
+        ])
+        .split(columns[0]);
+
+    render_services(frame, app, theme, left[0]);
+    render_persistence(frame, app, theme, left[1]);
+    let right = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage(30),
+            Constraint::Percentage(35),
+            Constraint::Percentage(35),
+        ])
+        .split(columns[1]);
+
+    render_kernel_params(frame, app, theme, right[0]);
+    render_modprobe(frame, app, theme, right[1]);
+    render_providers(frame, app, theme, right[2]);
+}
+
+fn render_providers(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    let block = bordered(app, Borders::ALL)
+        .title(" xrandr Providers ")
+        .title_style(Style::default().fg(theme.accent))
+        .border_style(Style::default().fg(theme.border))
+        .padding(Padding::horizontal(2));
+
+    let mut lines = Vec::new();
+    match &app.providers {
+        None => lines.push(Line::from(Span::styled(
+            "No X server to ask (console or xrandr missing). Press R to retry.",
+            Style::default().fg(theme.muted),
+        ))),
+        Some(providers) => {
+            // In hybrid mode PRIME needs the NVIDIA provider to offer offloading.
+            let offload = providers
+                .iter()
+                .any(|p| p.is_nvidia() && p.can("Source Offload"));
+            if app.current_mode == Some(GraphicsMode::Hybrid) {
+                lines.push(if offload {
+                    Line::from(Span::styled(
+                        "PRIME render offload is available.",
+                        Style::default().fg(theme.success),
+                    ))
+                } else {
+                    Line::from(Span::styled(
+                        "No NVIDIA offload provider: PRIME offload is not wired up.",
+                        Style::default().fg(theme.warning),
+                    ))
+                });
+            }
+            for provider in providers {
+                lines.push(Line::from(vec![
+                    Span::styled(
+                        format!("{} {}", provider.index, provider.name),
+                        Style::default()
+                            .fg(theme.accent)
+                            .add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(
+                        format!(
+                            "  {} · {} crtcs, {} outputs, {} associated",
+                            provider.capabilities.join(", "),
+                            provider.crtcs,
+                            provider.outputs,
+                            provider.associated
+                        ),
+                        Style::default().fg(theme.muted),
+                    ),
+                ]));
+            }
+        }
+    }
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false });
+    frame.render_widget(paragraph, area);
+}
+
+fn render_services(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    let block = bordered(app, Borders::ALL)
+        .title(" Suspend/Resume Services ")
+        .title_style(Style::default().fg(theme.accent))
+        .border_style(Style::default().fg(theme.border))
+        .padding(Padding::new(2, 2, 1, 1));
+
+    let mut lines: Vec<Line> = app
+        .services
         .iter()
-        .flat_map(|(key, action)| {
-            vec![
+        .map(|service| {
+            let (on, color) = match service.state {
+                ServiceState::Enabled => (true, theme.success),
+                ServiceState::Disabled => (false, theme.warning),
+                ServiceState::Missing => (false, theme.muted),
+                ServiceState::Other(_) => (false, theme.muted),
+            };
+            let marker = status_marker(app, on);
+            let activity = if service.active { ", active" } else { "" };
+            Line::from(vec![
+                Span::styled(marker, Style::default().fg(color)),
                 Span::styled(
-                    format!(" {} ", key),
-                    Style::default()
-                        .fg(theme.accent)
-                        .add_modifier(Modifier::BOLD),
+                    format!("{:<30}", service.name),
+                    Style::default().fg(theme.fg),
                 ),
-                Span::styled(format!("{} ", action), Style::default().fg(theme.muted)),
-                Span::styled("│", Style::default().fg(theme.border)),
-            ]
+                Span::styled(
+                    format!("{}{}", service.state.label(), activity),
+                    Style::default().fg(color),
+                ),
+            ])
         })
         .collect();
 
-    let line = Line::from(spans);
-    let paragraph = Paragraph::new(line).alignment(Alignment::Center).block(
-        Block::default()
-            .borders(Borders::TOP)
-            .border_style(Style::default().fg(theme.border)),
-    );
+    lines.push(Line::from(""));
+
+    let disabled = app.disabled_suspend_services();
+    let hint = if !app.suspend_services_recommended() {
+        Span::styled(
+            "Recommended when using hybrid mode or RTD3.",
+            Style::default().fg(theme.muted),
+        )
+    } else if !disabled.is_empty() {
+        Span::styled(
+            "Missing services can corrupt the display after resume. Press e to enable them.",
+            Style::default().fg(theme.warning),
+        )
+    } else if app
+        .services
+        .iter()
+        .any(|s| s.state == ServiceState::Missing)
+    {
+        Span::styled(
+            "Some services are not installed. They ship with the NVIDIA driver package.",
+            Style::default().fg(theme.muted),
+        )
+    } else {
+        Span::styled(
+            "All suspend/resume services are enabled.",
+            Style::default().fg(theme.success),
+        )
+    };
+    lines.push(Line::from(hint));
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false });
 
     frame.render_widget(paragraph, area);
 }
 
-fn render_message(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
-    let width = 50.min(area.width.saturating_sub(4));
-    let height = 7;
-    let x = (area.width.saturating_sub(width)) / 2;
-    let y = (area.height.saturating_sub(height)) / 2;
-
-    let popup_area = Rect::new(x, y, width, height);
+fn status_marker(app: &App, on: bool) -> &'static str {
+    match on {
+        true => glyph(app, "● ", "* "),
+        false => glyph(app, "○ ", "- "),
+    }
+}
 
-    frame.render_widget(Clear, popup_area);
+fn render_persistence(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    let block = bordered(app, Borders::ALL)
+        .title(" Persistence ")
+        .title_style(Style::default().fg(theme.accent))
+        .border_style(Style::default().fg(theme.border))
+        .padding(Padding::new(2, 2, 1, 1));
 
-    let (title, border_color, icon) = match app.state {
-        AppState::Success => (" Success ", theme.success, " "),
-        AppState::Error => (" Error ", theme.error, " "),
-        AppState::Loading => {
-            let spinner_icon = app.spinner.frame(app.spinner_frame);
-            return render_loading_popup(frame, app, theme, area, spinner_icon);
+    let (daemon_on, daemon_color, daemon_label) = match &app.persistenced {
+        Some(service) if service.state == ServiceState::Missing => {
+            (false, theme.muted, "not installed".to_string())
         }
-        AppState::ConfirmingSwitch | AppState::ConfirmingReboot => {
-            (" Confirm ", theme.warning, "󰋼 ")
+        Some(service) => {
+            let activity = if service.active { "active" } else { "inactive" };
+            let color = if service.is_on() {
+                theme.success
+            } else {
+                theme.muted
+            };
+            (
+                service.is_on(),
+                color,
+                format!("{}, {}", service.state.label(), activity),
+            )
         }
-        AppState::Normal => return,
+        None => (false, theme.muted, "unknown".to_string()),
     };
 
-    let block = Block::default()
-        .title(title)
-        .title_style(
-            Style::default()
-                .fg(border_color)
-                .add_modifier(Modifier::BOLD),
-        )
-        .borders(Borders::ALL)
-        .border_style(Style::default().fg(border_color))
-        .style(Style::default().bg(theme.bg));
-
-    let inner = block.inner(popup_area);
-    frame.render_widget(block, popup_area);
+    let (mode_on, mode_color, mode_label) = match app.persistence_mode {
+        Some(true) => (true, theme.success, "enabled"),
+        Some(false) => (false, theme.muted, "disabled"),
+        None => (false, theme.muted, "unavailable (driver not loaded)"),
+    };
 
-    let content = vec![
-        Line::from(""),
+    let mut lines = vec![
         Line::from(vec![
-            Span::styled(icon, Style::default().fg(border_color)),
-            Span::styled(&app.message, Style::default().fg(theme.fg)),
+            Span::styled(
+                status_marker(app, daemon_on),
+                Style::default().fg(daemon_color),
+            ),
+            Span::styled(
+                format!("{:<30}", "nvidia-persistenced.service"),
+                Style::default().fg(theme.fg),
+            ),
+            Span::styled(daemon_label, Style::default().fg(daemon_color)),
+        ]),
+        Line::from(vec![
+            Span::styled(status_marker(app, mode_on), Style::default().fg(mode_color)),
+            Span::styled(
+                format!("{:<30}", "Persistence mode"),
+                Style::default().fg(theme.fg),
+            ),
+            Span::styled(mode_label, Style::default().fg(mode_color)),
         ]),
         Line::from(""),
-        Line::from(Span::styled(
-            match app.state {
-                AppState::ConfirmingSwitch | AppState::ConfirmingReboot => {
-                    "y/Enter: Yes  |  n/Esc: No"
-                }
-                _ => "Press any key to continue",
-            },
-            Style::default().fg(theme.muted),
-        )),
     ];
 
-    let paragraph = Paragraph::new(content).alignment(Alignment::Center);
+    if app.persistence_conflicts_with_rtd3() {
+        lines.push(Line::from(Span::styled(
+            "Persistence keeps the dGPU awake and defeats RTD3 power savings in hybrid mode.",
+            Style::default().fg(theme.warning),
+        )));
+    } else {
+        lines.push(Line::from(Span::styled(
+            "Persistence speeds up GPU start-up but prevents the dGPU from powering down.",
+            Style::default().fg(theme.muted),
+        )));
+    }
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false });
 
-    frame.render_widget(paragraph, inner);
+    frame.render_widget(paragraph, area);
 }
 
-fn render_loading_popup(
-    frame: &mut Frame,
+fn render_kernel_params(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    let block = bordered(app, Borders::ALL)
+        .title(" Kernel Command Line ")
+        .title_style(Style::default().fg(theme.accent))
+        .border_style(Style::default().fg(theme.border))
+        .padding(Padding::new(2, 2, 1, 1));
+
+    let mut lines = Vec::new();
+
+    let mode = app.selected_mode();
+    let issues = system::kernel_param_issues(&app.kernel_params, mode);
+    if issues.is_empty() {
+        lines.push(Line::from(Span::styled(
+            format!("No known conflicts with {} mode.", mode),
+            Style::default().fg(theme.success),
+        )));
+    }
+    for issue in issues {
+        lines.push(Line::from(vec![
+            Span::styled(glyph(app, " ", "! "), Style::default().fg(theme.error)),
+            Span::styled(
+                issue,
+                Style::default()
+                    .fg(theme.error)
+                    .add_modifier(theme.emphasis(Modifier::BOLD)),
+            ),
+        ]));
+    }
+
+    lines.push(Line::from(""));
+
+    if app.kernel_params.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "No NVIDIA-related parameters set.",
+            Style::default().fg(theme.muted),
+        )));
+    }
+
+    for param in &app.kernel_params {
+        lines.push(Line::from(Span::styled(
+            param.display(),
+            Style::default()
+                .fg(theme.accent)
+                .add_modifier(Modifier::BOLD),
+        )));
+        if let Some(description) = param.description() {
+            lines.push(Line::from(Span::styled(
+                format!("  {}", description),
+                Style::default().fg(theme.muted),
+            )));
+        }
+    }
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false });
+
+    frame.render_widget(paragraph, area);
+}
+
+fn render_modprobe(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    let block = bordered(app, Borders::ALL)
+        .title(" modprobe.d ")
+        .title_style(Style::default().fg(theme.accent))
+        .border_style(Style::default().fg(theme.border))
+        .padding(Padding::new(2, 2, 1, 1));
+
+    let mut lines = Vec::new();
+
+    let mode = app.selected_mode();
+    let conflicts = system::modprobe_conflicts(&app.modprobe_entries, mode);
+    if conflicts.is_empty() {
+        lines.push(Line::from(Span::styled(
+            format!("No conflicts with {} mode.", mode),
+            Style::default().fg(theme.success),
+        )));
+    }
+    for conflict in conflicts {
+        lines.push(Line::from(vec![
+            Span::styled(glyph(app, " ", "! "), Style::default().fg(theme.error)),
+            Span::styled(
+                conflict,
+                Style::default()
+                    .fg(theme.error)
+                    .add_modifier(theme.emphasis(Modifier::BOLD)),
+            ),
+        ]));
+    }
+
+    lines.push(Line::from(""));
+
+    if app.modprobe_entries.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "No nvidia/nouveau entries found.",
+            Style::default().fg(theme.muted),
+        )));
+    }
+
+    for entry in &app.modprobe_entries {
+        lines.push(Line::from(vec![
+            Span::styled(
+                entry.line.as_str(),
+                Style::default()
+                    .fg(theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(
+                format!("  {}", entry.location()),
+                Style::default().fg(theme.muted),
+            ),
+        ]));
+    }
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false });
+
+    frame.render_widget(paragraph, area);
+}
+
+#[cfg(feature = "monitor")]
+fn render_monitor(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    let area = Layout::default()
+        .constraints([Constraint::Min(3)])
+        .margin(1)
+        .split(area)[0];
+
+    let note = if app.current_mode == Some(GraphicsMode::Integrated) {
+        "The dGPU is off in integrated mode; showing the saved history.".to_string()
+    } else if app.history.samples.is_empty() {
+        "Waiting for the first sample...".to_string()
+    } else {
+        let oldest = app.history.samples.front().map_or(0, |s| s.time);
+        let newest = app.history.samples.back().map_or(0, |s| s.time);
+        format!(
+            "{} samples over the last {} min, kept across restarts",
+            app.history.samples.len(),
+            newest.saturating_sub(oldest).div_ceil(60)
+        )
+    };
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(5),
+            Constraint::Percentage(50),
+            Constraint::Percentage(50),
+        ])
+        .split(area);
+
+    frame.render_widget(
+        Paragraph::new(Span::styled(note, Style::default().fg(theme.muted))),
+        chunks[0],
+    );
+    frame.render_widget(Paragraph::new(comparison_line(app, theme)), chunks[1]);
+    render_clocks(frame, app, theme, chunks[2]);
+    render_history_chart(
+        frame,
+        app,
+        theme,
+        chunks[3],
+        "Temperature",
+        "°C",
+        &app.history.temperatures(),
+    );
+    render_history_chart(
+        frame,
+        app,
+        theme,
+        chunks[4],
+        "Utilization",
+        "%",
+        &app.history.utilizations(),
+    );
+}
+
+// Averages before and after the last switch; lower temperature and power
+// read as an improvement.
+#[cfg(feature = "monitor")]
+fn comparison_line<'a>(app: &App, theme: &Theme) -> Line<'a> {
+    let muted = Style::default().fg(theme.muted);
+    let comparison = app
+        .boot_time
+        .and_then(|boot| monitor::compare(&app.history, &app.switches, boot));
+    let Some(comparison) = comparison else {
+        return Line::from(Span::styled(
+            "Before/after: needs samples from both sides of a switch",
+            muted,
+        ));
+    };
+    let (before, after) = (comparison.before, comparison.after);
+    let mode = |stats: monitor::ModeStats| match stats.mode {
+        Some(mode) => Span::styled(
+            mode.to_string(),
+            Style::default().fg(theme.mode_color(&mode)),
+        ),
+        None => Span::styled("before", muted),
+    };
+    let change = |label: &str, from: u32, to: u32, unit: &str, lower_is_better: bool| {
+        let delta = i64::from(to) - i64::from(from);
+        let color = match delta.signum() {
+            0 => theme.fg,
+            _ if !lower_is_better => theme.fg,
+            -1 => theme.success,
+            _ => theme.warning,
+        };
+        vec![
+            Span::styled(format!("  {} ", label), muted),
+            Span::styled(
+                format!("{}→{}{} ", from, to, unit),
+                Style::default().fg(theme.fg),
+            ),
+            Span::styled(format!("({:+})", delta), Style::default().fg(color)),
+        ]
+    };
+
+    let mut spans = vec![
+        Span::styled("Avg ", muted),
+        mode(before),
+        Span::styled(" → ", muted),
+        mode(after),
+        Span::styled(":", muted),
+    ];
+    spans.extend(change(
+        "temp",
+        before.temperature,
+        after.temperature,
+        "°C",
+        true,
+    ));
+    spans.extend(change(
+        "load",
+        before.utilization,
+        after.utilization,
+        "%",
+        false,
+    ));
+    if let (Some(from), Some(to)) = (before.power, after.power) {
+        spans.extend(change("power", from, to, " W", true));
+    }
+    Line::from(spans)
+}
+
+#[cfg(feature = "monitor")]
+fn render_clocks(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    let gpu = app.gpu_info.as_ref();
+    let label = |text: &'static str| Span::styled(text, Style::default().fg(theme.muted));
+    let value = |text: String| Span::styled(text, Style::default().fg(theme.fg));
+    let mut lines = Vec::new();
+    let link = gpu.and_then(|gpu| gpu.link.as_ref());
+    if link.is_some() || app.dgpu_power.is_some() {
+        let mut spans = vec![
+            label("PCIe     "),
+            value(link.map_or("n/a".to_string(), |link| link.display())),
+        ];
+        if let Some(power) = &app.dgpu_power {
+            let color = if power.suspended() {
+                theme.success
+            } else {
+                theme.warning
+            };
+            spans.push(label("   "));
+            spans.push(Span::styled(power.badge(), Style::default().fg(color)));
+        }
+        lines.push(Line::from(spans));
+    }
+    lines.push(gamemode_line(app, theme));
+    let Some(clocks) = gpu.and_then(|gpu| gpu.clocks.as_ref()) else {
+        frame.render_widget(Paragraph::new(lines), area);
+        return;
+    };
+    lines.push(Line::from(vec![
+        label("Graphics "),
+        value(format!("{} / {} MHz", clocks.graphics, clocks.max_graphics)),
+        label("   Memory "),
+        value(format!("{} / {} MHz", clocks.memory, clocks.max_memory)),
+        label("   P-state "),
+        value(clocks.pstate.clone()),
+    ]));
+    let percent = |value: Option<u32>| value.map_or("n/a".to_string(), |v| format!("{}%", v));
+    lines.push(Line::from(vec![
+        label("Encoder  "),
+        value(percent(gpu.and_then(|gpu| gpu.encoder))),
+        label("   Decoder "),
+        value(percent(gpu.and_then(|gpu| gpu.decoder))),
+    ]));
+    if clocks.stuck_high(gpu.and_then(|gpu| gpu.utilization)) {
+        lines.push(Line::from(Span::styled(
+            format!(
+                "{} Idle but stuck in {}: something is keeping the dGPU awake",
+                app.icons.warning, clocks.pstate
+            ),
+            Style::default().fg(theme.warning),
+        )));
+    }
+    frame.render_widget(Paragraph::new(lines), area);
+}
+
+// Whether GameMode is on and which of its games asked for the dGPU.
+#[cfg(feature = "monitor")]
+fn gamemode_line<'a>(app: &App, theme: &Theme) -> Line<'a> {
+    let mut spans = vec![Span::styled("GameMode ", Style::default().fg(theme.muted))];
+    let Some(gamemode) = &app.gamemode else {
+        spans.push(Span::styled(
+            "not installed",
+            Style::default().fg(theme.muted),
+        ));
+        return Line::from(spans);
+    };
+    if !gamemode.active {
+        spans.push(Span::styled("idle", Style::default().fg(theme.fg)));
+    } else {
+        spans.push(Span::styled("active", Style::default().fg(theme.success)));
+        for (i, client) in gamemode.clients.iter().enumerate() {
+            spans.push(Span::styled(
+                if i == 0 { ": " } else { ", " },
+                Style::default().fg(theme.muted),
+            ));
+            spans.push(Span::styled(
+                client.name.clone(),
+                Style::default().fg(theme.fg),
+            ));
+            if client.offload {
+                spans.push(Span::styled(
+                    " (requested the dGPU)",
+                    Style::default().fg(theme.warning),
+                ));
+            }
+        }
+    }
+    if gamemode.gpu_optimisations {
+        spans.push(Span::styled(
+            "   GPU optimisations on",
+            Style::default().fg(theme.muted),
+        ));
+    }
+    Line::from(spans)
+}
+
+#[cfg(feature = "monitor")]
+fn render_history_chart(
+    frame: &mut Frame,
+    app: &App,
+    theme: &Theme,
+    area: Rect,
+    label: &str,
+    unit: &str,
+    values: &[u64],
+) {
+    let title = match (values.last(), values.iter().min(), values.iter().max()) {
+        (Some(last), Some(min), Some(max)) => format!(
+            " {} {}{}  (min {}{}, max {}{}) ",
+            label, last, unit, min, unit, max, unit
+        ),
+        _ => format!(" {} ", label),
+    };
+    let block = bordered(app, Borders::ALL)
+        .title(title)
+        .title_style(Style::default().fg(theme.accent))
+        .border_style(Style::default().fg(theme.border));
+    let inner = block.inner(area);
+    let style = Style::default().fg(theme.mode_color(&GraphicsMode::Nvidia));
+
+    // Newest samples on the right, as many as fit.
+    match app.config.monitor.chart {
+        ChartStyle::Block => {
+            let visible = &values[values.len().saturating_sub(inner.width as usize)..];
+            let sparkline = Sparkline::default()
+                .block(block)
+                .data(visible)
+                .max(100)
+                .style(style);
+            frame.render_widget(sparkline, area);
+        }
+        ChartStyle::Braille => {
+            let columns = inner.width as usize * 2;
+            let points: Vec<(f64, f64)> = values[values.len().saturating_sub(columns)..]
+                .iter()
+                .enumerate()
+                .map(|(i, &value)| (i as f64, value as f64))
+                .collect();
+            let dataset = Dataset::default()
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Bar)
+                .style(style)
+                .data(&points);
+            let chart = Chart::new(vec![dataset])
+                .block(block)
+                .x_axis(Axis::default().bounds([0.0, columns.saturating_sub(1).max(1) as f64]))
+                .y_axis(Axis::default().bounds([0.0, 100.0]));
+            frame.render_widget(chart, area);
+        }
+    }
+}
+
+fn render_logs(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    let area = Layout::default()
+        .constraints([Constraint::Min(3)])
+        .margin(1)
+        .split(area)[0];
+
+    let title = match &app.log_path {
+        Some(path) => format!(" {} ", path.display()),
+        None => " Application Log ".to_string(),
+    };
+    let block = bordered(app, Borders::ALL)
+        .title(title)
+        .title_style(Style::default().fg(theme.accent))
+        .border_style(Style::default().fg(theme.border))
+        .padding(Padding::horizontal(1));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let show_search = app.log_search_active || !app.log_search.is_empty();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(1),
+            Constraint::Length(if show_search { 1 } else { 0 }),
+        ])
+        .split(inner);
+
+    app.log_view_height.set(chunks[0].height as usize);
+
+    if app.log_lines.is_empty() {
+        let paragraph = Paragraph::new(Span::styled(
+            "No log entries yet.",
+            Style::default().fg(theme.muted),
+        ));
+        frame.render_widget(paragraph, chunks[0]);
+    } else {
+        let lines: Vec<Line> = app.log_lines[app.visible_log_range()]
+            .iter()
+            .map(|line| {
+                let (color, modifier) = if line.contains(" ERROR ") {
+                    (theme.error, theme.emphasis(Modifier::BOLD))
+                } else if line.contains(" WARN ") {
+                    (theme.warning, Modifier::empty())
+                } else {
+                    (theme.fg, Modifier::empty())
+                };
+                let matched = if app.log_line_matches(line) {
+                    Style::default()
+                        .bg(theme.selection_bg)
+                        .add_modifier(theme.emphasis(Modifier::REVERSED))
+                } else {
+                    Style::default().bg(theme.bg)
+                };
+                let split = if line.is_char_boundary(19) { 19 } else { 0 };
+                let (time, rest) = line.split_at(split);
+                Line::from(vec![
+                    Span::styled(time, matched.fg(theme.muted)),
+                    Span::styled(rest, matched.fg(color).add_modifier(modifier)),
+                ])
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines), chunks[0]);
+    }
+
+    if show_search {
+        let mut spans = vec![Span::styled("/", Style::default().fg(theme.accent))];
+        spans.extend(text_with_cursor(
+            app.log_search.value(),
+            app.log_search_active.then_some(app.log_search.cursor()),
+            theme,
+        ));
+        let search = Line::from(spans);
+        frame.render_widget(Paragraph::new(search), chunks[1]);
+    }
+}
+
+fn render_footer(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    let fixed = |keys: &[(&str, &'static str)]| -> Vec<(String, &'static str)> {
+        keys.iter().map(|&(k, a)| (k.to_string(), a)).collect()
+    };
+    let keys = match app.state {
+        AppState::Normal if app.active_tab == AppTab::Logs && app.log_search_active => {
+            fixed(&[("Enter", "Search"), ("Esc", "Cancel")])
+        }
+        AppState::Normal => app
+            .keymap
+            .hints(app.active_tab, |action| app.action_available(action)),
+        AppState::Input => match app.input.as_ref().map(|prompt| &prompt.widget) {
+            Some(InputWidget::Select(_)) => {
+                fixed(&[("↑↓/jk", "Choose"), ("Enter", "Confirm"), ("Esc", "Cancel")])
+            }
+            Some(InputWidget::Number(_)) => {
+                fixed(&[("↑↓", "Adjust"), ("Enter", "Confirm"), ("Esc", "Cancel")])
+            }
+            _ => fixed(&[("Enter", "Confirm"), ("Esc", "Cancel")]),
+        },
+        AppState::ConfirmingProcesses => fixed(&[
+            ("↑↓/jk", "Select"),
+            ("x", "Terminate"),
+            ("X", "Terminate All"),
+            ("c/Enter", "Continue"),
+            ("n/Esc", "Cancel"),
+        ]),
+        AppState::ConfirmingReboot if app.initramfs_tool.is_some() => fixed(&[
+            ("y/Enter", "Yes"),
+            ("i", "Rebuild Initramfs"),
+            ("n/Esc", "No"),
+        ]),
+        AppState::ConfirmingSwitch | AppState::ConfirmingReboot => {
+            fixed(&[("y/Enter", "Yes"), ("n/Esc", "No")])
+        }
+        AppState::Error if app.retry.is_some() => fixed(&[
+            ("r", "Retry"),
+            ("i", "Report Issue"),
+            ("Any key", "Dismiss"),
+        ]),
+        AppState::Error => fixed(&[("i", "Report Issue"), ("Any key", "Dismiss")]),
+        AppState::Help => fixed(&[("↑↓/jk", "Topic"), ("Any key", "Close")]),
+        AppState::About => fixed(&[
+            ("o", "Open Config Dir"),
+            ("s", "Open State Dir"),
+            ("y", "Copy Paths"),
+            ("Any key", "Close"),
+        ]),
+        AppState::Success | AppState::OptionDetails => fixed(&[("Any key", "Close")]),
+        AppState::ConfigProblems => fixed(&[("Any key", "Continue")]),
+        AppState::ThemeEditor => fixed(&[
+            ("↑↓/jk", "Color"),
+            ("←→/hl", "Palette"),
+            ("+/-", "Lighter/Darker"),
+            ("r", "Revert"),
+            ("Enter", "Save"),
+            ("Esc", "Cancel"),
+        ]),
+        #[cfg(feature = "overclock")]
+        AppState::Overclock => fixed(&[
+            ("↑↓/jk", "Clock"),
+            ("←→/hl", "Adjust"),
+            ("r", "Reset to Stock"),
+            ("Enter", "Apply"),
+            ("Esc", "Close"),
+        ]),
+        AppState::Loading => Vec::new(),
+    };
+
+    // Drop hints that don't fit, but keep the last one (Quit) visible.
+    let hint_width = |(key, action): &(String, &str)| key.chars().count() + action.len() + 4;
+    let mut keys = keys;
+    while keys.len() > 1 && keys.iter().map(hint_width).sum::<usize>() > area.width as usize {
+        keys.remove(keys.len() - 2);
+    }
+
+    let spans: Vec<Span> = keys
+        .iter()
+        .flat_map(|(key, action)| {
+            vec![
+                Span::styled(
+                    format!(" {} ", key),
+                    Style::default()
+                        .fg(theme.accent)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(format!("{} ", action), Style::default().fg(theme.muted)),
+                Span::styled(
+                    app.icons.separator.as_str(),
+                    Style::default().fg(theme.border),
+                ),
+            ]
+        })
+        .collect();
+
+    let mut lines = vec![Line::from(spans)];
+    if let Some(tip) = app.tip() {
+        lines.push(Line::from(Span::styled(
+            format!("Tip: {}", tip),
+            Style::default()
+                .fg(theme.muted)
+                .add_modifier(Modifier::ITALIC),
+        )));
+    }
+    let paragraph = Paragraph::new(lines)
+        .alignment(Alignment::Center)
+        .block(bordered(app, Borders::TOP).border_style(Style::default().fg(theme.border)));
+
+    frame.render_widget(paragraph, area);
+}
+
+fn render_message(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    // Only popups drawn with Popup scroll; the others leave this at zero.
+    app.popup_max_scroll.set(0);
+
+    let (title, border_color, icon) = match app.state {
+        AppState::Success => ("Success", theme.success, glyph(app, " ", "").to_string()),
+        AppState::Error => ("Error", theme.error, glyph(app, " ", "! ").to_string()),
+        AppState::Loading => {
+            let spinner_icon = app.spinner_icon();
+            return render_loading_popup(frame, app, theme, area, spinner_icon);
+        }
+        AppState::ConfirmingProcesses => {
+            return render_processes_popup(frame, app, theme, area);
+        }
+        AppState::Input => {
+            return render_input_popup(frame, app, theme, area);
+        }
+        AppState::About => {
+            return render_about_popup(frame, app, theme, area);
+        }
+        AppState::OptionDetails => {
+            return render_option_details(frame, app, theme, area);
+        }
+        AppState::Help => {
+            return render_help(frame, app, theme, area);
+        }
+        AppState::ConfigProblems => {
+            return render_config_problems(frame, app, theme, area);
+        }
+        AppState::ThemeEditor => {
+            if let Some(editor) = &app.theme_editor {
+                render_theme_editor(frame, app, editor, area);
+            }
+            return;
+        }
+        #[cfg(feature = "overclock")]
+        AppState::Overclock => {
+            if let Some(panel) = &app.overclock {
+                render_overclock(frame, app, panel, theme, area);
+            }
+            return;
+        }
+        AppState::ConfirmingSwitch | AppState::ConfirmingReboot => {
+            ("Confirm", theme.warning, format!("{} ", app.icons.info))
+        }
+        AppState::Normal => return,
+    };
+
+    let mut message_lines = app.message.lines();
+    let headline = message_lines.next().unwrap_or_default();
+    let mut content = vec![
+        Line::from(""),
+        Line::from(vec![
+            Span::styled(icon, Style::default().fg(border_color)),
+            Span::styled(headline, Style::default().fg(theme.fg)),
+        ]),
+    ];
+    for detail in message_lines {
+        content.push(
+            Line::from(Span::styled(detail, Style::default().fg(theme.muted)))
+                .alignment(Alignment::Left),
+        );
+    }
+    content.push(Line::from(""));
+
+    let keys = match app.state {
+        AppState::ConfirmingSwitch | AppState::ConfirmingReboot => "y/Enter: Yes  |  n/Esc: No",
+        AppState::Error if app.retry.is_some() => {
+            "r: Retry  |  i: Report issue  |  Other keys: Dismiss"
+        }
+        AppState::Error => "i: Report issue  |  Any other key: Dismiss",
+        _ => "Press any key to continue",
+    };
+
+    Popup::new(title, border_color, content)
+        .keys(keys)
+        .centered()
+        .render(frame, app, theme, area);
+}
+
+// A popup sized to its content: as wide as its longest line within
+// min_width..max_width, and as tall as the wrapped text up to the screen.
+// What doesn't fit scrolls by popup_scroll above the pinned key hint line.
+struct Popup<'a> {
+    title: String,
+    color: Color,
+    lines: Vec<Line<'a>>,
+    keys: Option<&'a str>,
+    min_width: u16,
+    max_width: u16,
+    alignment: Alignment,
+    // A line kept in view instead of following popup_scroll: the selected
+    // row of a list, or the newest output line.
+    reveal: Option<usize>,
+}
+
+impl<'a> Popup<'a> {
+    fn new(title: impl Into<String>, color: Color, lines: Vec<Line<'a>>) -> Self {
+        Self {
+            title: title.into(),
+            color,
+            lines,
+            keys: None,
+            min_width: 40,
+            max_width: 70,
+            alignment: Alignment::Left,
+            reveal: None,
+        }
+    }
+
+    fn keys(mut self, keys: &'a str) -> Self {
+        self.keys = Some(keys);
+        self
+    }
+
+    fn width(mut self, min_width: u16, max_width: u16) -> Self {
+        self.min_width = min_width;
+        self.max_width = max_width;
+        self
+    }
+
+    fn centered(mut self) -> Self {
+        self.alignment = Alignment::Center;
+        self
+    }
+
+    fn reveal(mut self, line: usize) -> Self {
+        self.reveal = Some(line);
+        self
+    }
+
+    fn render(self, frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+        // Borders plus the padding on either side.
+        const CHROME: u16 = 4;
+        let alignment = self.alignment;
+        let wrapped = |lines: Vec<Line<'a>>| {
+            Paragraph::new(lines)
+                .alignment(alignment)
+                .wrap(Wrap { trim: false })
+        };
+
+        let content_width = self
+            .lines
+            .iter()
+            .map(Line::width)
+            .chain(self.keys.map(UnicodeWidthStr::width))
+            .chain([self.title.width() + 2])
+            .max()
+            .unwrap_or(0);
+        let width = (content_width as u16)
+            .saturating_add(CHROME)
+            .clamp(self.min_width, self.max_width.max(self.min_width))
+            .min(area.width.saturating_sub(4));
+        let text_width = width.saturating_sub(CHROME).max(1);
+
+        let reveal_height = self.reveal.filter(|_| !self.lines.is_empty()).map(|line| {
+            let end = line.min(self.lines.len() - 1);
+            wrapped(self.lines[..=end].to_vec()).line_count(text_width)
+        });
+        let body = wrapped(self.lines);
+        let body_height = body.line_count(text_width);
+        let keys_height = |keys: &str| {
+            Paragraph::new(keys)
+                .wrap(Wrap { trim: false })
+                .line_count(text_width)
+        };
+        let max_height = area.height.saturating_sub(2) as usize;
+        let scrollable = reveal_height.is_none()
+            && body_height + self.keys.map_or(0, keys_height) + 2 > max_height;
+        let keys = self.keys.map(|keys| {
+            if scrollable {
+                format!("↑↓/jk: Scroll  |  {}", keys)
+            } else {
+                keys.to_string()
+            }
+        });
+        let keys_height = keys.as_deref().map_or(0, keys_height);
+        let height = (body_height + keys_height + 2).min(max_height) as u16;
+        let x = area.x + (area.width.saturating_sub(width)) / 2;
+        let y = area.y + (area.height.saturating_sub(height)) / 2;
+        let popup_area = Rect::new(x, y, width, height);
+
+        frame.render_widget(Clear, popup_area);
+
+        let block = popup_block(app)
+            .title(format!(" {} ", self.title))
+            .title_style(Style::default().fg(self.color).add_modifier(Modifier::BOLD))
+            .border_style(Style::default().fg(self.color))
+            .padding(Padding::horizontal(1))
+            .style(Style::default().bg(theme.bg));
+
+        let inner = block.inner(popup_area);
+        frame.render_widget(block, popup_area);
+
+        let [body_area, keys_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(keys_height as u16)])
+                .areas(inner);
+        let max_scroll = body_height.saturating_sub(body_area.height as usize);
+        let scroll = match reveal_height {
+            Some(height) => height.saturating_sub(body_area.height as usize),
+            None => {
+                app.popup_max_scroll.set(max_scroll);
+                app.popup_scroll
+            }
+        }
+        .min(max_scroll);
+        frame.render_widget(body.scroll((scroll as u16, 0)), body_area);
+
+        if let Some(keys) = keys {
+            frame.render_widget(
+                Paragraph::new(Span::styled(keys, Style::default().fg(theme.muted)))
+                    .alignment(Alignment::Center)
+                    .wrap(Wrap { trim: false }),
+                keys_area,
+            );
+        }
+
+        if max_scroll > 0 {
+            let mut scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                .style(Style::default().fg(self.color));
+            if app.icons.set == IconSet::Ascii {
+                scrollbar = scrollbar
+                    .begin_symbol(None)
+                    .end_symbol(None)
+                    .track_symbol(Some("|"))
+                    .thumb_symbol("#");
+            }
+            let mut state = ScrollbarState::new(max_scroll).position(scroll);
+            frame.render_stateful_widget(
+                scrollbar,
+                popup_area.inner(Margin::new(0, 1)),
+                &mut state,
+            );
+        }
+    }
+}
+
+fn render_processes_popup(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    let mut content = vec![
+        Line::from(vec![
+            Span::styled(
+                format!("{} ", app.icons.info),
+                Style::default().fg(theme.warning),
+            ),
+            Span::styled(&app.message, Style::default().fg(theme.fg)),
+        ]),
+        Line::from(""),
+    ];
+
+    for (i, process) in app.gpu_processes.iter().enumerate() {
+        let is_selected = i == app.selected_process_index;
+        let (selector, row) = if is_selected {
+            (
+                format!("{} ", app.icons.selector),
+                Style::default()
+                    .bg(theme.selection_bg)
+                    .add_modifier(theme.emphasis(Modifier::REVERSED)),
+            )
+        } else {
+            ("  ".to_string(), Style::default().bg(theme.bg))
+        };
+        content.push(Line::from(vec![
+            Span::styled(selector, row.fg(theme.accent)),
+            Span::styled(format!("{:>7} ", process.pid), row.fg(theme.muted)),
+            Span::styled(&process.name, row.fg(theme.fg)),
+            Span::styled(
+                format!(" ({} MiB)", process.used_memory),
+                row.fg(theme.muted),
+            ),
+        ]));
+    }
+    content.push(Line::from(""));
+
+    let selected = app.selected_process_index + 2;
+    Popup::new("dGPU In Use", theme.warning, content)
+        .keys("x: Terminate  |  X: Terminate all  |  c/Enter: Continue  |  n/Esc: Cancel")
+        .width(40, 80)
+        .reveal(selected)
+        .render(frame, app, theme, area);
+}
+
+fn bordered(app: &App, borders: Borders) -> Block<'static> {
+    match border_type(app.config.ui.border) {
+        Some(border_type) => Block::default().borders(borders).border_type(border_type),
+        None => Block::default(),
+    }
+}
+
+// Popups float over the panels, so they keep an outline even with `border = "none"`.
+fn popup_block(app: &App) -> Block<'static> {
+    Block::default()
+        .borders(Borders::ALL)
+        .border_type(border_type(app.config.ui.border).unwrap_or(BorderType::Plain))
+}
+
+fn border_type(style: BorderStyle) -> Option<BorderType> {
+    match style {
+        BorderStyle::Plain => Some(BorderType::Plain),
+        BorderStyle::Rounded => Some(BorderType::Rounded),
+        BorderStyle::Thick => Some(BorderType::Thick),
+        BorderStyle::None => None,
+    }
+}
+
+fn text_with_cursor<'a>(value: &'a str, cursor: Option<usize>, theme: &Theme) -> Vec<Span<'a>> {
+    let text = Style::default().fg(theme.fg);
+    let Some(cursor) = cursor else {
+        return vec![Span::styled(value, text)];
+    };
+
+    let split = value
+        .char_indices()
+        .nth(cursor)
+        .map(|(i, _)| i)
+        .unwrap_or(value.len());
+    let (before, rest) = value.split_at(split);
+    let mut chars = rest.chars();
+    let under = chars.next().map(String::from).unwrap_or_else(|| " ".into());
+    vec![
+        Span::styled(before, text),
+        Span::styled(under, text.add_modifier(Modifier::REVERSED)),
+        Span::styled(chars.as_str(), text),
+    ]
+}
+
+fn render_input_popup(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    let Some(prompt) = &app.input else {
+        return;
+    };
+
+    let mut content = vec![Line::from("")];
+    let shown;
+    match &prompt.widget {
+        InputWidget::Text(input) => {
+            shown = input.display();
+            let mut spans = vec![Span::styled("> ", Style::default().fg(theme.accent))];
+            spans.extend(text_with_cursor(&shown, Some(input.cursor()), theme));
+            content.push(Line::from(spans));
+        }
+        InputWidget::Number(input) => {
+            let mut spans = vec![Span::styled("> ", Style::default().fg(theme.accent))];
+            spans.extend(text_with_cursor(input.text(), Some(input.cursor()), theme));
+            spans.push(Span::styled(
+                format!("  ({})", input.range_hint()),
+                Style::default().fg(theme.muted),
+            ));
+            content.push(Line::from(spans));
+        }
+        InputWidget::Select(list) => {
+            for (i, item) in list.items().iter().enumerate() {
+                let selected = i == list.selected();
+                let (marker, style) = if selected {
+                    (
+                        format!("{} ", app.icons.selector),
+                        Style::default()
+                            .fg(theme.accent)
+                            .bg(theme.selection_bg)
+                            .add_modifier(Modifier::BOLD | theme.emphasis(Modifier::REVERSED)),
+                    )
+                } else {
+                    ("  ".to_string(), Style::default().fg(theme.fg))
+                };
+                content.push(Line::from(Span::styled(
+                    format!("{}{}", marker, item),
+                    style,
+                )));
+            }
+        }
+    }
+
+    content.push(Line::from(""));
+    content.push(match &prompt.error {
+        Some(error) => Line::from(Span::styled(
+            error,
+            Style::default()
+                .fg(theme.error)
+                .add_modifier(theme.emphasis(Modifier::BOLD)),
+        )),
+        None => Line::from(""),
+    });
+
+    let mut popup = Popup::new(prompt.title.as_str(), theme.accent, content)
+        .keys("Enter: Confirm  |  Esc: Cancel")
+        .width(50, 70);
+    if let InputWidget::Select(list) = &prompt.widget {
+        popup = popup.reveal(list.selected() + 1);
+    }
+    popup.render(frame, app, theme, area);
+}
+
+fn render_about_popup(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    let Some(about) = &app.about else {
+        return;
+    };
+
+    let unknown = || "not detected".to_string();
+    let directory = |dir: &Option<PathBuf>| {
+        dir.as_ref()
+            .map_or_else(|| "unknown".to_string(), |dir| dir.display().to_string())
+    };
+    let session = match (&about.session_type, &about.desktop) {
+        (Some(session), Some(desktop)) => format!("{} ({})", session, desktop),
+        (Some(session), None) => session.clone(),
+        (None, Some(desktop)) => desktop.clone(),
+        (None, None) => "unknown".to_string(),
+    };
+    let rows = [
+        ("envy-tui", env!("CARGO_PKG_VERSION").to_string()),
+        (
+            "envycontrol",
+            about.envycontrol_version.clone().unwrap_or_else(unknown),
+        ),
+        (
+            "supergfxctl",
+            about.supergfx_version.clone().unwrap_or_else(unknown),
+        ),
+        ("Backend", "envycontrol".to_string()),
+        ("Session", session),
+        ("Config", directory(&about.config_dir)),
+        ("State", directory(&about.state_dir)),
+        (
+            "System",
+            about
+                .distro
+                .clone()
+                .unwrap_or_else(|| "unknown".to_string()),
+        ),
+        ("License", env!("CARGO_PKG_LICENSE").to_string()),
+        ("Source", "github.com/tassiovirginio/envy-tui".to_string()),
+    ];
+
+    let mut content = vec![Line::from("")];
+    for (label, value) in rows {
+        content.push(Line::from(vec![
+            Span::styled(format!("{:<13}", label), Style::default().fg(theme.muted)),
+            Span::styled(value, Style::default().fg(theme.fg)),
+        ]));
+    }
+    content.push(Line::from(""));
+
+    Popup::new("About", theme.accent, content)
+        .keys("o/s: Open config/state  |  y: Copy  |  Other keys: Close")
+        .width(60, 80)
+        .render(frame, app, theme, area);
+}
+
+fn render_option_details(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    let Some(option) = app.options.get(app.selected_option_index) else {
+        return;
+    };
+    let kind = option.kind;
+
+    let mut content = vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            kind.explanation(),
+            Style::default().fg(theme.fg),
+        )),
+        Line::from(""),
+    ];
+    if let Some(flag) = kind.flag() {
+        content.push(Line::from(vec![
+            Span::styled("envycontrol: ", Style::default().fg(theme.muted)),
+            Span::styled(flag, Style::default().fg(theme.accent)),
+        ]));
+        content.push(Line::from(""));
+    }
+    let session = app.session_type.as_deref().unwrap_or_default();
+    for (session_type, caveat) in kind.caveats() {
+        let current = session.eq_ignore_ascii_case(session_type);
+        let label = if *session_type == "x11" {
+            "X11"
+        } else {
+            "Wayland"
+        };
+        let label = if current {
+            format!("{} (this session): ", label)
+        } else {
+            format!("{}: ", label)
+        };
+        content.push(Line::from(vec![
+            Span::styled(
+                label,
+                Style::default()
+                    .fg(if current { theme.warning } else { theme.muted })
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(*caveat, Style::default().fg(theme.fg)),
+        ]));
+    }
+    content.push(Line::from(""));
+
+    Popup::new(option.label.as_str(), theme.accent, content)
+        .keys("Press any key to close")
+        .render(frame, app, theme, area);
+}
+
+fn render_config_problems(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    const NOTE: &str =
+        "Defaults are used where a setting couldn't be read. Saved fixes load automatically.";
+    let location_width = app
+        .config_problems
+        .iter()
+        .map(|problem| problem.location().len())
+        .max()
+        .unwrap_or(0);
+
+    let mut lines = vec![Line::from("")];
+    for problem in &app.config_problems {
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{:<width$}  ", problem.location(), width = location_width),
+                Style::default().fg(theme.accent),
+            ),
+            Span::styled(problem.message.as_str(), Style::default().fg(theme.fg)),
+        ]));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        NOTE,
+        Style::default().fg(theme.muted),
+    )));
+
+    Popup::new("Config Problems", theme.warning, lines)
+        .width(40, 80)
+        .render(frame, app, theme, area);
+}
+
+fn render_theme_editor(frame: &mut Frame, app: &App, editor: &ThemeEditor, area: Rect) {
+    const PALETTE_COLUMNS: usize = 10;
+    const SLOTS_WIDTH: usize = 38;
+    const PREVIEW_WIDTH: usize = 46;
+    let theme = &editor.theme;
+
+    let slots: Vec<Line> = theme::SLOTS
+        .iter()
+        .enumerate()
+        .map(|(i, key)| {
+            let color = theme.color(key).unwrap_or_default();
+            let (marker, name_style) = if i == editor.selected {
+                (
+                    "▶ ",
+                    Style::default().fg(theme.fg).add_modifier(Modifier::BOLD),
+                )
+            } else {
+                ("  ", Style::default().fg(theme.muted))
+            };
+            Line::from(vec![
+                Span::styled(marker, Style::default().fg(theme.accent)),
+                Span::styled(format!("{:<17}", key), name_style),
+                Span::styled("██ ", Style::default().fg(color)),
+                Span::styled(theme::color_name(color), name_style),
+                Span::styled(
+                    if editor.is_changed(key) { " *" } else { "" },
+                    Style::default().fg(theme.pending),
+                ),
+            ])
+        })
+        .collect();
+
+    let current = editor.color();
+    let mut right = vec![Line::from(Span::styled(
+        format!("Palette for {}", editor.key()),
+        Style::default()
+            .fg(theme.accent)
+            .add_modifier(Modifier::BOLD),
+    ))];
+    for row in ThemeEditor::palette().chunks(PALETTE_COLUMNS) {
+        right.push(Line::from(
+            row.iter()
+                .flat_map(|&color| {
+                    let (open, close) = if color == current {
+                        ("[", "]")
+                    } else {
+                        (" ", " ")
+                    };
+                    [
+                        Span::styled(open, Style::default().fg(theme.fg)),
+                        Span::styled("██", Style::default().fg(color)),
+                        Span::styled(close, Style::default().fg(theme.fg)),
+                    ]
+                })
+                .collect::<Vec<_>>(),
+        ));
+    }
+    right.push(Line::from(""));
+
+    // The preview box is drawn line by line so the popup can size itself.
+    let border = Style::default().fg(theme.border_focused);
+    let set = border_type(app.config.ui.border)
+        .unwrap_or(BorderType::Plain)
+        .to_border_set();
+    let modes: Vec<Span> = GraphicsMode::ALL
+        .iter()
+        .map(|mode| {
+            Span::styled(
+                format!("● {}  ", mode),
+                Style::default().fg(theme.mode_color(mode)),
+            )
+        })
+        .collect();
+    let preview = [
+        Line::from(modes),
+        Line::from(Span::styled(
+            "▶ Selected row",
+            Style::default().fg(theme.fg).bg(theme.selection_bg),
+        )),
+        Line::from(vec![
+            Span::styled("Success  ", Style::default().fg(theme.success)),
+            Span::styled("Error  ", Style::default().fg(theme.error)),
+            Span::styled("Warning  ", Style::default().fg(theme.warning)),
+            Span::styled("Pending", Style::default().fg(theme.pending)),
+        ]),
+        Line::from(vec![
+            Span::styled("Text  ", Style::default().fg(theme.fg)),
+            Span::styled("muted text  ", Style::default().fg(theme.muted)),
+            Span::styled("────────", Style::default().fg(theme.border)),
+        ]),
+    ];
+    right.push(Line::from(vec![
+        Span::styled(set.top_left, border),
+        Span::styled(" Preview ", Style::default().fg(theme.accent)),
+        Span::styled(set.horizontal_top.repeat(PREVIEW_WIDTH - 9), border),
+        Span::styled(set.top_right, border),
+    ]));
+    for line in preview {
+        let padding = PREVIEW_WIDTH.saturating_sub(line.width());
+        let mut spans = vec![Span::styled(set.vertical_left, border)];
+        spans.extend(line.spans);
+        spans.push(Span::raw(" ".repeat(padding)));
+        spans.push(Span::styled(set.vertical_right, border));
+        right.push(Line::from(spans));
+    }
+    right.push(Line::from(vec![
+        Span::styled(set.bottom_left, border),
+        Span::styled(set.horizontal_bottom.repeat(PREVIEW_WIDTH), border),
+        Span::styled(set.bottom_right, border),
+    ]));
+
+    let rows = slots.len().max(right.len());
+    let mut slots = slots.into_iter();
+    let mut right = right.into_iter();
+    let mut lines = vec![Line::from("")];
+    for _ in 0..rows {
+        let slot = slots.next().unwrap_or_default();
+        let padding = SLOTS_WIDTH.saturating_sub(slot.width());
+        let mut spans = slot.spans;
+        spans.push(Span::raw(" ".repeat(padding)));
+        spans.extend(right.next().unwrap_or_default().spans);
+        lines.push(Line::from(spans));
+    }
+
+    Popup::new("Theme Editor", theme.accent, lines)
+        .width(40, 90)
+        .reveal(editor.selected + 1)
+        .render(frame, app, theme, area);
+}
+
+#[cfg(feature = "overclock")]
+fn render_overclock(
+    frame: &mut Frame,
     app: &App,
+    panel: &OverclockPanel,
     theme: &Theme,
     area: Rect,
-    spinner_icon: &str,
 ) {
-    let width = 40.min(area.width.saturating_sub(4));
-    let height = 5;
-    let x = (area.width.saturating_sub(width)) / 2;
-    let y = (area.height.saturating_sub(height)) / 2;
+    let danger = Style::default()
+        .fg(theme.error)
+        .add_modifier(Modifier::BOLD);
+    let mut lines = vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            format!(
+                "{}Danger: unstable clocks can crash the driver, freeze the session",
+                glyph(app, "⚠ ", "! ")
+            ),
+            danger,
+        )),
+        Line::from(Span::styled(
+            "or corrupt rendering. Raise offsets a step at a time and test each.",
+            danger,
+        )),
+        Line::from(Span::styled(
+            "Offsets are not saved: they last until the X server restarts.",
+            Style::default().fg(theme.muted),
+        )),
+        Line::from(""),
+    ];
+    for (i, clock) in Clock::ALL.into_iter().enumerate() {
+        let offset = panel.offsets.get(clock);
+        let applied = panel.applied.get(clock);
+        let (marker, style) = if i == panel.selected {
+            (
+                format!("{} ", app.icons.selector),
+                Style::default()
+                    .fg(theme.fg)
+                    .add_modifier(Modifier::BOLD | theme.emphasis(Modifier::REVERSED)),
+            )
+        } else {
+            ("  ".to_string(), Style::default().fg(theme.muted))
+        };
+        lines.push(Line::from(vec![
+            Span::styled(marker, Style::default().fg(theme.accent)),
+            Span::styled(format!("{:<22}{:>+5} MHz", clock.label(), offset), style),
+            Span::styled(
+                if offset != applied {
+                    format!("  (applied {:+})", applied)
+                } else {
+                    String::new()
+                },
+                Style::default().fg(theme.pending),
+            ),
+        ]));
+        lines.push(Line::from(Span::styled(
+            format!(
+                "  {:<22}-{} to +{}, step {}",
+                "",
+                clock.limit(),
+                clock.limit(),
+                clock.step()
+            ),
+            Style::default().fg(theme.muted),
+        )));
+    }
+    Popup::new("Overclocking", theme.error, lines)
+        .width(40, 72)
+        .reveal(5 + panel.selected * 2)
+        .render(frame, app, theme, area);
+}
 
+fn render_help(frame: &mut Frame, app: &App, theme: &Theme, area: Rect) {
+    let width = 90.min(area.width.saturating_sub(4));
+    let height = 22.min(area.height.saturating_sub(2));
+    let x = area.x + (area.width.saturating_sub(width)) / 2;
+    let y = area.y + (area.height.saturating_sub(height)) / 2;
     let popup_area = Rect::new(x, y, width, height);
 
     frame.render_widget(Clear, popup_area);
 
-    let block = Block::default()
-        .title(" Loading ")
+    let block = popup_block(app)
+        .title(" Help ")
         .title_style(
             Style::default()
                 .fg(theme.accent)
                 .add_modifier(Modifier::BOLD),
         )
-        .borders(Borders::ALL)
         .border_style(Style::default().fg(theme.accent))
+        .padding(Padding::new(1, 1, 1, 0))
         .style(Style::default().bg(theme.bg));
 
     let inner = block.inner(popup_area);
     frame.render_widget(block, popup_area);
 
-    let content = vec![
+    let [topics_area, page_area] =
+        Layout::horizontal([Constraint::Length(30), Constraint::Min(0)]).areas(inner);
+
+    let topics: Vec<Line> = help::PAGES
+        .iter()
+        .enumerate()
+        .map(|(i, page)| {
+            if i == app.help_page {
+                Line::from(vec![
+                    Span::styled(
+                        format!("{} ", app.icons.selector),
+                        Style::default().fg(theme.accent),
+                    ),
+                    Span::styled(
+                        page.title,
+                        Style::default().fg(theme.fg).add_modifier(Modifier::BOLD),
+                    ),
+                ])
+            } else {
+                Line::from(Span::styled(
+                    format!("  {}", page.title),
+                    Style::default().fg(theme.muted),
+                ))
+            }
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(topics), topics_area);
+
+    let page = &help::PAGES[app.help_page.min(help::PAGES.len() - 1)];
+    let mut lines = vec![
+        Line::from(Span::styled(
+            page.title,
+            Style::default()
+                .fg(theme.accent)
+                .add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+    ];
+    lines.extend(
+        page.body
+            .lines()
+            .map(|line| Line::from(Span::styled(line, Style::default().fg(theme.fg)))),
+    );
+    frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), page_area);
+}
+
+fn render_loading_popup(
+    frame: &mut Frame,
+    app: &App,
+    theme: &Theme,
+    area: Rect,
+    spinner_icon: &str,
+) {
+    let output = &app.progress_lines;
+    let checklist = phase_checklist(app, theme, spinner_icon);
+
+    let mut content = vec![
         Line::from(""),
         Line::from(vec![
             Span::styled(
@@ -427,8 +2269,684 @@ fn render_loading_popup(
             Span::styled(&app.message, Style::default().fg(theme.fg)),
         ]),
     ];
+    if !checklist.is_empty() {
+        content.push(Line::from(""));
+        content.extend(checklist);
+    }
+    if !output.is_empty() {
+        content.push(Line::from(""));
+    }
+    for line in output {
+        content.push(
+            Line::from(Span::styled(line, Style::default().fg(theme.muted)))
+                .alignment(Alignment::Left),
+        );
+    }
+    content.push(Line::from(""));
+
+    // A fixed width while output streams in, so the popup doesn't jump.
+    let width = if output.is_empty() { 40 } else { 70 };
+    let newest = content.len() - 1;
+    Popup::new("Loading", theme.accent, content)
+        .width(width, 70)
+        .centered()
+        .reveal(newest)
+        .render(frame, app, theme, area);
+}
+
+// Once envycontrol reports a known step, every step up to the current one is
+// listed: done, skipped (never reported) or in progress.
+fn phase_checklist<'a>(app: &App, theme: &Theme, spinner_icon: &'a str) -> Vec<Line<'a>> {
+    let Some(&current) = app.phases.last() else {
+        return Vec::new();
+    };
+    let current_index = Phase::ALL.iter().position(|&p| p == current).unwrap_or(0);
+
+    Phase::ALL
+        .iter()
+        .enumerate()
+        .map(|(index, &phase)| {
+            let (icon, style) = if phase == current {
+                (
+                    spinner_icon,
+                    Style::default()
+                        .fg(theme.accent)
+                        .add_modifier(Modifier::BOLD),
+                )
+            } else if app.phases.contains(&phase) {
+                (app.icons.check.as_str(), Style::default().fg(theme.success))
+            } else if index < current_index {
+                ("-", Style::default().fg(theme.muted))
+            } else {
+                (glyph(app, "·", "."), Style::default().fg(theme.muted))
+            };
+            Line::from(vec![
+                Span::styled(format!("{} ", icon), style),
+                Span::styled(phase.label(), style),
+            ])
+            .alignment(Alignment::Left)
+        })
+        .collect()
+}
+
+// The snapshots include the tab bar, so they need the Monitor tab.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{AboutInfo, InputPurpose, Retry};
+    use crate::config::Profile;
+    use crate::distro::Immutable;
+    use crate::envycontrol::{Clocks, GpuInfo, GpuProcess, InstallMethod, PcieLink, SwitchChanges};
+    use crate::host::Sandbox;
+    use crate::input::{InputWidget, NumberInput, TextInput};
+    #[cfg(feature = "monitor")]
+    use crate::monitor::{GameMode, GameModeClient, Sample};
+    #[cfg(feature = "overclock")]
+    use crate::overclock::Offsets;
+    use crate::validate::Problem;
+    use crossterm::event::KeyCode;
+    use ratatui::{backend::TestBackend, Terminal};
+    use std::path::PathBuf;
+    use std::time::{Duration, Instant};
+
+    // Without the monitor feature the tab bar has no Monitor tab, so those
+    // builds compare against their own snapshots.
+    macro_rules! assert_snapshot {
+        ($value:expr) => {{
+            let mut settings = insta::Settings::clone_current();
+            if cfg!(not(feature = "monitor")) {
+                settings.set_snapshot_suffix("no_monitor");
+            }
+            settings.bind(|| insta::assert_snapshot!($value));
+        }};
+    }
+
+    // Fixed inputs so snapshots don't depend on the machine running the tests.
+    fn app(mode: GraphicsMode) -> App {
+        let mut app = App::new();
+        app.sandbox = Sandbox::None;
+        app.immutable = None;
+        app.log_path = Some(PathBuf::from(
+            "/home/user/.local/state/envy-tui/envy-tui.log",
+        ));
+        app.envycontrol_version = Some("3.5.1".to_string());
+        app.current_mode = Some(mode);
+        app.selected_mode_index = GraphicsMode::ALL
+            .iter()
+            .position(|&m| m == mode)
+            .unwrap_or(0);
+        app.refresh_options();
+        if mode != GraphicsMode::Integrated {
+            app.gpu_info = Some(GpuInfo {
+                name: "NVIDIA GeForce RTX 3060 Laptop GPU".to_string(),
+                temperature: "45°C".to_string(),
+                memory_used: "512".to_string(),
+                memory_total: "6144".to_string(),
+                utilization: Some(3),
+                power_draw: Some(11),
+                encoder: Some(0),
+                decoder: Some(12),
+                clocks: Some(Clocks {
+                    graphics: 1410,
+                    memory: 7000,
+                    max_graphics: 2100,
+                    max_memory: 7001,
+                    pstate: "P0".to_string(),
+                }),
+                link: Some(PcieLink {
+                    generation: 4,
+                    max_generation: 4,
+                    width: 8,
+                    max_width: 16,
+                }),
+            });
+        }
+        app
+    }
+
+    fn draw(app: &App) -> String {
+        draw_sized(app, 100, 30)
+    }
+
+    fn draw_sized(app: &App, width: u16, height: u16) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal
+            .draw(|frame| render(frame, app, &Theme::default()))
+            .unwrap();
+        terminal.backend().to_string()
+    }
+
+    #[test]
+    fn normal_integrated() {
+        assert_snapshot!(draw(&app(GraphicsMode::Integrated)));
+    }
+
+    #[test]
+    fn normal_hybrid() {
+        assert_snapshot!(draw(&app(GraphicsMode::Hybrid)));
+    }
+
+    #[test]
+    fn normal_nvidia() {
+        assert_snapshot!(draw(&app(GraphicsMode::Nvidia)));
+    }
+
+    #[test]
+    fn normal_unknown_mode() {
+        let mut app = app(GraphicsMode::Hybrid);
+        app.current_mode = None;
+        app.gpu_info = None;
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn options_panel_focused() {
+        let mut app = app(GraphicsMode::Nvidia);
+        app.active_panel = AppPanel::Options;
+        app.force_comp = true;
+        app.coolbits_enabled = true;
+        app.refresh_options();
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn compact_layout() {
+        let mut app = app(GraphicsMode::Hybrid);
+        app.compact = true;
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn integrated_hardware() {
+        let mut app = app(GraphicsMode::Integrated);
+        app.gpu_info = None;
+        app.dgpu_hardware = Some(system::PciGpu {
+            name: "GA106M [GeForce RTX 3060 Mobile / Max-Q]".to_string(),
+            id: "10de:2560".to_string(),
+        });
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn dgpu_suspended() {
+        let mut app = app(GraphicsMode::Hybrid);
+        app.dgpu_power = Some(system::DgpuPower {
+            status: "suspended".to_string(),
+            suspended_ms: 5_400_000,
+            active_ms: 600_000,
+        });
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn narrow_header() {
+        let mut app = app(GraphicsMode::Hybrid);
+        app.compact = true;
+        assert_snapshot!(draw_sized(&app, 60, 24));
+    }
+
+    #[test]
+    fn envycontrol_missing() {
+        let mut app = app(GraphicsMode::Hybrid);
+        app.envycontrol_installed = false;
+        app.current_mode = None;
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn outdated_envycontrol_from_aur() {
+        let mut app = app(GraphicsMode::Hybrid);
+        app.envycontrol_version = Some("3.3.0".to_string());
+        app.envycontrol_install = Some(InstallMethod::Pacman {
+            package: "envycontrol".to_string(),
+            aur: true,
+        });
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn system_tab() {
+        let mut app = app(GraphicsMode::Hybrid);
+        app.active_tab = AppTab::System;
+        app.providers = Some(system::parse_providers(
+            "Providers: number : 2\n\
+             Provider 0: id: 0x1b8 cap: 0x1, Source Output crtcs: 4 outputs: 5 associated providers: 1 name:modesetting\n\
+             Provider 1: id: 0x1f2 cap: 0x6, Sink Output, Source Offload crtcs: 4 outputs: 4 associated providers: 1 name:NVIDIA-G0\n",
+        ));
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn logs_tab() {
+        let mut app = app(GraphicsMode::Hybrid);
+        app.active_tab = AppTab::Logs;
+        app.set_log_lines(vec![
+            "2026-01-01 10:00:00 INFO Running: envycontrol -s nvidia --verbose".to_string(),
+            "2026-01-01 10:00:02 INFO Switched to nvidia mode".to_string(),
+        ]);
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn confirming_processes() {
+        let mut app = app(GraphicsMode::Nvidia);
+        app.warn_gpu_processes(
+            GraphicsMode::Integrated,
+            vec![GpuProcess {
+                pid: 4242,
+                name: "firefox".to_string(),
+                used_memory: "120".to_string(),
+            }],
+        );
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn confirming_switch() {
+        let mut app = app(GraphicsMode::Hybrid);
+        app.confirm_switch(GraphicsMode::Nvidia);
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn help() {
+        let mut app = app(GraphicsMode::Hybrid);
+        app.state = AppState::Help;
+        app.help_page = 1;
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn theme_editor() {
+        let mut app = app(GraphicsMode::Hybrid);
+        let mut editor = ThemeEditor::new(Theme::default());
+        editor.select_next();
+        editor.select_next();
+        editor.cycle(true);
+        app.theme_editor = Some(editor);
+        app.state = AppState::ThemeEditor;
+        assert_snapshot!(draw(&app));
+    }
+
+    #[cfg(feature = "overclock")]
+    #[test]
+    fn overclock_panel() {
+        let mut app = app(GraphicsMode::Nvidia);
+        let applied = Offsets {
+            graphics: 60,
+            memory: 0,
+        };
+        app.overclock_unlocked = true;
+        let mut panel = OverclockPanel::new(applied);
+        panel.select_next();
+        panel.adjust(true);
+        panel.adjust(true);
+        app.overclock = Some(panel);
+        app.state = AppState::Overclock;
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn option_details() {
+        let mut app = app(GraphicsMode::Nvidia);
+        app.active_panel = AppPanel::Options;
+        app.selected_option_index = 1;
+        app.session_type = Some("wayland".to_string());
+        app.state = AppState::OptionDetails;
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn config_problems() {
+        let mut app = app(GraphicsMode::Hybrid);
+        let problem = |file, line, message: &str| Problem {
+            file,
+            line,
+            message: message.to_string(),
+        };
+        app.config_problems = vec![
+            problem("config.toml", Some(5), "Unknown setting 'boder' in [ui]"),
+            problem(
+                "config.toml",
+                Some(12),
+                "coolbits = 40 is out of range (0-31)",
+            ),
+            problem("config.toml", Some(21), "Unknown action 'quitt' in [keys]"),
+            problem(
+                "theme.toml",
+                Some(2),
+                "Invalid color for 'accent': expected \"#rrggbb\" or a color name",
+            ),
+        ];
+        app.state = AppState::ConfigProblems;
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn powermizer_option() {
+        let mut app = app(GraphicsMode::Nvidia);
+        app.powermizer_max_performance = Some(true);
+        app.refresh_options();
+        app.active_panel = AppPanel::Options;
+        app.selected_option_index = 2;
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn ascii_icons() {
+        let mut app = app(GraphicsMode::Hybrid);
+        app.icons = crate::icons::Icons::preset(crate::config::IconSet::Ascii);
+        app.reboot_pending = Some(GraphicsMode::Nvidia);
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn busy_gpu_utilization() {
+        let mut app = app(GraphicsMode::Nvidia);
+        if let Some(gpu) = app.gpu_info.as_mut() {
+            gpu.utilization = Some(97);
+        }
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        terminal
+            .draw(|frame| render(frame, &app, &Theme::default()))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        let percent = buffer
+            .content()
+            .iter()
+            .position(|cell| cell.symbol() == "%")
+            .unwrap();
+        assert_eq!(buffer.content()[percent].fg, Theme::default().error);
+        assert_snapshot!(terminal.backend().to_string());
+    }
+
+    #[test]
+    fn monochrome() {
+        let mut app = app(GraphicsMode::Hybrid);
+        app.monochrome = true;
+        app.icons = crate::icons::Icons::preset(crate::config::IconSet::Ascii);
+        app.active_tab = AppTab::System;
+        app.services = vec![
+            system::ServiceStatus {
+                name: "nvidia-suspend.service",
+                state: ServiceState::Enabled,
+                active: false,
+            },
+            system::ServiceStatus {
+                name: "nvidia-resume.service",
+                state: ServiceState::Disabled,
+                active: false,
+            },
+        ];
+        app.persistence_mode = Some(true);
+        app.kernel_params = system::parse_kernel_cmdline("quiet nomodeset nvidia-drm.modeset=1");
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        terminal
+            .draw(|frame| render(frame, &app, &Theme::monochrome()))
+            .unwrap();
+        // The active tab is reversed instead of colored.
+        let buffer = terminal.backend().buffer();
+        let tab = buffer
+            .content()
+            .iter()
+            .position(|cell| cell.symbol() == "S" && cell.modifier.contains(Modifier::BOLD))
+            .unwrap();
+        assert!(buffer.content()[tab].modifier.contains(Modifier::REVERSED));
+        assert_snapshot!(terminal.backend().to_string());
+    }
+
+    #[test]
+    fn reboot_pending() {
+        let mut app = app(GraphicsMode::Hybrid);
+        app.reboot_pending = Some(GraphicsMode::Nvidia);
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn confirming_with_games_running() {
+        let mut app = app(GraphicsMode::Hybrid);
+        app.running_games = vec!["Steam".to_string(), "Hades".to_string()];
+        app.confirm_switch(GraphicsMode::Integrated);
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn confirming_suggested_profile() {
+        let mut app = app(GraphicsMode::Hybrid);
+        app.suggested_profile = Some(Profile {
+            mode: GraphicsMode::Nvidia,
+            rtd3: None,
+            force_comp: true,
+            coolbits: Some(24),
+        });
+        app.confirm_switch(GraphicsMode::Nvidia);
+        assert!(!app.force_comp && !app.coolbits_enabled);
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn confirming_reapply() {
+        let mut app = app(GraphicsMode::Integrated);
+        app.confirm_switch(GraphicsMode::Integrated);
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn confirming_reboot() {
+        let mut app = app(GraphicsMode::Nvidia);
+        app.state = AppState::ConfirmingReboot;
+        app.message = "Mode changed successfully! Do you want to reboot now?".to_string();
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn detecting() {
+        let mut app = app(GraphicsMode::Hybrid);
+        app.detecting = true;
+        app.current_mode = None;
+        app.gpu_info = None;
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn switch_summary() {
+        let mut app = app(GraphicsMode::Integrated);
+        app.force_comp = true;
+        let output: Vec<String> = [
+            "INFO: Removed file /etc/modprobe.d/blacklist-nvidia.conf",
+            "INFO: Removed file /etc/X11/xorg.conf",
+            "INFO: Created file /etc/X11/xorg.conf",
+            "INFO: Created file /etc/modprobe.d/nvidia.conf",
+            "Created symlink /etc/systemd/system/multi-user.target.wants/nvidia-persistenced.service.",
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+        app.show_switch_summary(GraphicsMode::Nvidia, &SwitchChanges::parse(&output), &[]);
+        assert_snapshot!(draw(&app));
+    }
 
-    let paragraph = Paragraph::new(content).alignment(Alignment::Center);
+    #[test]
+    fn switch_summary_problems() {
+        let mut app = app(GraphicsMode::Hybrid);
+        let problems = [
+            "/etc/X11/xorg.conf was not written".to_string(),
+            "/etc/modprobe.d/blacklist-nvidia.conf is still there and keeps the driver from loading"
+                .to_string(),
+        ];
+        app.show_switch_summary(GraphicsMode::Nvidia, &SwitchChanges::default(), &problems);
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn about() {
+        let mut app = app(GraphicsMode::Hybrid);
+        app.about = Some(AboutInfo {
+            envycontrol_version: Some("3.5.1".to_string()),
+            supergfx_version: None,
+            session_type: Some("wayland".to_string()),
+            desktop: Some("KDE".to_string()),
+            distro: Some("Arch Linux".to_string()),
+            config_dir: Some(PathBuf::from("/home/user/.config/envy-tui")),
+            state_dir: Some(PathBuf::from("/home/user/.local/state/envy-tui")),
+        });
+        app.state = AppState::About;
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn input() {
+        let mut app = app(GraphicsMode::Nvidia);
+        app.open_input(
+            InputPurpose::CoolbitsValue,
+            "Coolbits",
+            InputWidget::Number(NumberInput::new(28, 0, 31)),
+        );
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn password_input() {
+        let mut app = app(GraphicsMode::Hybrid);
+        let mut text = TextInput::new().masked();
+        for c in "hunter2".chars() {
+            text.handle_key(KeyCode::Char(c));
+        }
+        app.open_input(
+            InputPurpose::Password,
+            "Password for sudo",
+            InputWidget::Text(text),
+        );
+        assert!(!format!("{:?}", app.input).contains("hunter2"));
+        let screen = draw(&app);
+        assert!(screen.contains("> •••••••") && !screen.contains("hunter2"));
+        assert_snapshot!(screen);
+    }
+
+    #[test]
+    fn loading() {
+        let mut app = app(GraphicsMode::Hybrid);
+        app.set_loading("Applying changes...");
+        app.push_progress_line("Removing existing configuration".to_string());
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn loading_reduced_motion() {
+        let mut app = app(GraphicsMode::Hybrid);
+        app.config.ui.reduced_motion = true;
+        app.set_loading("Applying changes...");
+        app.tick_spinner();
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn loading_phases() {
+        let mut app = app(GraphicsMode::Hybrid);
+        app.set_loading("Applying changes...");
+        for line in [
+            "INFO: Removed file /etc/X11/xorg.conf",
+            "INFO: Created file /etc/modprobe.d/nvidia.conf",
+            "INFO: Created file /etc/udev/rules.d/80-nvidia-pm.rules",
+            "Rebuilding the initramfs...",
+        ] {
+            app.push_progress_line(line.to_string());
+        }
+        assert_snapshot!(draw(&app));
+    }
 
-    frame.render_widget(paragraph, inner);
+    #[test]
+    fn success() {
+        let mut app = app(GraphicsMode::Hybrid);
+        app.set_success("Switched to nvidia mode. Please reboot for changes to take effect.");
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn error() {
+        let mut app = app(GraphicsMode::Hybrid);
+        app.set_retryable_error(
+            "Failed to switch mode: authentication dismissed",
+            Retry::Switch(app.switch_options(GraphicsMode::Nvidia)),
+        );
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn long_error_scrolls() {
+        let mut app = app(GraphicsMode::Hybrid);
+        let details: Vec<String> = (1..=30).map(|i| format!("Traceback line {}", i)).collect();
+        app.set_error(&format!("envycontrol failed\n{}", details.join("\n")));
+        app.popup_scroll = 15;
+        assert_snapshot!(draw_sized(&app, 80, 24));
+        assert_eq!(app.popup_max_scroll.get(), 15);
+    }
+
+    #[test]
+    #[cfg(feature = "monitor")]
+    fn monitor_tab() {
+        let mut app = app(GraphicsMode::Hybrid);
+        app.active_tab = AppTab::Monitor;
+        app.dgpu_power = Some(system::DgpuPower {
+            status: "active".to_string(),
+            suspended_ms: 0,
+            active_ms: 3_600_000,
+        });
+        // Switched from hybrid to nvidia and rebooted halfway through.
+        for i in 0..40u32 {
+            let nvidia = i >= 20;
+            app.history.push(Sample {
+                time: 1_700_000_000 + u64::from(i) * 2,
+                temperature: 45 + i % 7 + if nvidia { 9 } else { 0 },
+                utilization: (i * 13) % 100,
+                power: Some(if nvidia { 38 } else { 9 } + i % 3),
+            });
+        }
+        app.switches = vec![
+            (1_699_000_000, GraphicsMode::Hybrid),
+            (1_700_000_030, GraphicsMode::Nvidia),
+        ];
+        app.boot_time = Some(1_700_000_040);
+        app.gamemode = Some(GameMode {
+            active: true,
+            clients: vec![GameModeClient {
+                name: "Hades.exe".to_string(),
+                offload: true,
+            }],
+            gpu_optimisations: false,
+        });
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    #[cfg(feature = "monitor")]
+    fn monitor_tab_braille() {
+        let mut app = app(GraphicsMode::Nvidia);
+        app.active_tab = AppTab::Monitor;
+        app.config.monitor.chart = ChartStyle::Braille;
+        for i in 0..200u32 {
+            app.history.push(Sample {
+                time: 1_700_000_000 + u64::from(i) * 2,
+                temperature: 50 + i % 11,
+                utilization: (i * 7) % 100,
+                power: Some(30),
+            });
+        }
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn footer_tip() {
+        let mut app = app(GraphicsMode::Hybrid);
+        app.last_input = Instant::now() - Duration::from_secs(16);
+        assert_snapshot!(draw(&app));
+    }
+
+    #[test]
+    fn immutable() {
+        let mut app = app(GraphicsMode::Hybrid);
+        app.immutable = Some(Immutable::NixOs);
+        app.set_error(Immutable::NixOs.explanation());
+        assert_snapshot!(draw(&app));
+    }
 }
@@ -0,0 +1,397 @@
+use crate::config::{self, Config, LogLevel};
+use crate::keymap::{self, Action, Keymap};
+use crate::theme::{self, Theme};
+use ratatui::style::Color;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+use toml::de::{DeTable, DeValue, Deserializer};
+use toml::Spanned;
+
+// Every key envy-tui reads, by table. "profiles.*" is any [profiles.<name>].
+const CONFIG_KEYS: &[(&str, &[&str])] = &[
+    (
+        "",
+        &[
+            "skip_confirmations",
+            "envycontrol_path",
+            "profiles",
+            "login",
+            "power",
+            "extra_args",
+            "ui",
+            "monitor",
+            "logs",
+            "icons",
+            "keys",
+        ],
+    ),
+    ("profiles.*", &["mode", "rtd3", "force_comp", "coolbits"]),
+    ("login", &["auto_apply", "rules"]),
+    ("login.rules", &["when", "profile"]),
+    ("power", &["ac", "battery", "auto_apply"]),
+    ("extra_args", &["integrated", "hybrid", "nvidia"]),
+    (
+        "ui",
+        &[
+            "border",
+            "mode_panel_percent",
+            "compact",
+            "max_fps",
+            "tips",
+            "reduced_motion",
+            "monochrome",
+        ],
+    ),
+    ("monitor", &["csv_log", "chart"]),
+    (
+        "logs",
+        &["level", "max_size_kb", "daily", "keep", "max_age_days"],
+    ),
+    (
+        "icons",
+        &[
+            "set",
+            "integrated",
+            "hybrid",
+            "nvidia",
+            "gpu",
+            "temperature",
+            "memory",
+            "utilization",
+            "info",
+            "warning",
+            "current",
+            "pending",
+            "selector",
+            "check",
+            "separator",
+        ],
+    ),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub file: &'static str,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Problem {
+    pub fn location(&self) -> String {
+        match self.line {
+            Some(line) => format!("{}:{}", self.file, line),
+            None => self.file.to_string(),
+        }
+    }
+}
+
+// Everything wrong with config.toml and theme.toml at once, so a typo doesn't
+// just silently fall back to the defaults.
+pub fn check() -> Vec<Problem> {
+    let mut problems = Vec::new();
+    if let Some(contents) = read(config::config_path().as_deref()) {
+        check_config(&contents, &mut problems);
+    }
+    if let Some(contents) = read(theme::theme_path().as_deref()) {
+        check_theme(&contents, &mut problems);
+    }
+    check_env(&mut problems);
+    problems.sort_by_key(|problem| (problem.file, problem.line));
+    problems
+}
+
+// envycontrol is the only backend, so unlike the other ENVY_TUI_* variables
+// this one has no setting to override.
+const ENV_BACKEND: &str = "ENVY_TUI_BACKEND";
+
+fn check_env(problems: &mut Vec<Problem>) {
+    let mut report = |message: String| {
+        problems.push(Problem {
+            file: "environment",
+            line: None,
+            message,
+        })
+    };
+    if let Ok(value) = env::var(config::ENV_NO_CONFIRM) {
+        if config::parse_bool(&value).is_none() {
+            report(format!(
+                "{}={} is ignored: expected 1 or 0",
+                config::ENV_NO_CONFIRM,
+                value
+            ));
+        }
+    }
+    if let Ok(value) = env::var(config::ENV_LOG_LEVEL) {
+        if LogLevel::from_name(&value).is_none() {
+            report(format!(
+                "{}={} is ignored: expected info, warn or error",
+                config::ENV_LOG_LEVEL,
+                value
+            ));
+        }
+    }
+    if let Ok(value) = env::var(ENV_BACKEND) {
+        report(format!(
+            "{}={} is ignored: envycontrol is the only backend; set {} to use another copy",
+            ENV_BACKEND,
+            value,
+            config::ENV_ENVYCONTROL_PATH
+        ));
+    }
+    if env::var_os(theme::ENV_THEME).is_some() {
+        if let Some(path) = theme::theme_path().filter(|path| !path.exists()) {
+            report(format!(
+                "{}: {} doesn't exist (or use \"default\" or \"ansi\")",
+                theme::ENV_THEME,
+                path.display()
+            ));
+        }
+    }
+}
+
+fn read(path: Option<&Path>) -> Option<String> {
+    fs::read_to_string(path?).ok()
+}
+
+struct Checker<'a> {
+    file: &'static str,
+    source: &'a str,
+    problems: &'a mut Vec<Problem>,
+}
+
+impl Checker<'_> {
+    fn report(&mut self, offset: Option<usize>, message: String) {
+        let line = offset.map(|offset| self.source[..offset].matches('\n').count() + 1);
+        if self
+            .problems
+            .iter()
+            .any(|problem| problem.file == self.file && line.is_some() && problem.line == line)
+        {
+            return;
+        }
+        self.problems.push(Problem {
+            file: self.file,
+            line,
+            message,
+        });
+    }
+}
+
+fn check_config(contents: &str, problems: &mut Vec<Problem>) {
+    let mut checker = Checker {
+        file: "config.toml",
+        source: contents,
+        problems,
+    };
+    let table = match DeTable::parse(contents) {
+        Ok(table) => table.into_inner(),
+        Err(e) => {
+            checker.report(e.span().map(|span| span.start), e.message().to_string());
+            return;
+        }
+    };
+    check_table(&mut checker, &table, "");
+
+    let profile_names: Vec<&str> = table
+        .get("profiles")
+        .and_then(|profiles| profiles.get_ref().as_table())
+        .map(|profiles| {
+            profiles
+                .keys()
+                .map(|name| name.get_ref().as_ref())
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut references = Vec::new();
+    if let Some(power) = table
+        .get("power")
+        .and_then(|power| power.get_ref().as_table())
+    {
+        for key in ["ac", "battery"] {
+            references.extend(power.get(key));
+        }
+    }
+    if let Some(rules) = table
+        .get("login")
+        .and_then(|login| login.get_ref().get("rules"))
+        .and_then(|rules| rules.get_ref().as_array())
+    {
+        references.extend(
+            rules
+                .iter()
+                .filter_map(|rule| rule.get_ref().get("profile")),
+        );
+    }
+    // Remaps that leave two actions on one key, reported at either remap.
+    if let Some(keys) = table.get("keys").and_then(|keys| keys.get_ref().as_table()) {
+        let mut overrides = BTreeMap::new();
+        let mut offsets = BTreeMap::new();
+        for (name, keys) in keys {
+            let Ok(action) = Action::deserialize(toml::Value::String(name.get_ref().to_string()))
+            else {
+                continue;
+            };
+            let keys = keys.get_ref().as_array().into_iter().flatten();
+            overrides.insert(
+                action,
+                keys.filter_map(|key| key.get_ref().as_str().map(str::to_string))
+                    .collect(),
+            );
+            offsets.insert(action, name.span().start);
+        }
+        if let Ok(keymap) = Keymap::from_config(&overrides) {
+            for (a, b, message) in keymap.clashes() {
+                checker.report(offsets.get(&a).or(offsets.get(&b)).copied(), message);
+            }
+        }
+    }
+
+    for reference in references {
+        if let Some(name) = reference.get_ref().as_str() {
+            if !profile_names.contains(&name) {
+                checker.report(
+                    Some(reference.span().start),
+                    format!("No profile named '{}' in [profiles]", name),
+                );
+            }
+        }
+    }
+
+    // Wrong value types, e.g. a string where a number belongs. Deserializing
+    // stops at the first error, so each top-level entry is checked on its own.
+    for (key, value) in &table {
+        let mut single = DeTable::new();
+        single.insert(key.clone(), value.clone());
+        let deserializer = Deserializer::from(Spanned::new(0..contents.len(), single));
+        if let Err(e) = Config::deserialize(deserializer) {
+            checker.report(e.span().map(|span| span.start), e.message().to_string());
+        }
+    }
+}
+
+fn check_table(checker: &mut Checker, table: &DeTable, path: &str) {
+    let known = CONFIG_KEYS
+        .iter()
+        .find(|(table, _)| *table == path)
+        .map(|(_, keys)| *keys);
+    for (key, value) in table {
+        let name: &str = key.get_ref();
+        let offset = Some(key.span().start);
+        match path {
+            "keys" => check_binding(checker, name, offset, value.get_ref()),
+            "profiles" => match value.get_ref().as_table() {
+                Some(profile) => check_table(checker, profile, "profiles.*"),
+                None => checker.report(offset, format!("Profile '{}' must be a table", name)),
+            },
+            _ if !known.is_some_and(|keys| keys.contains(&name)) => {
+                let message = if path.is_empty() {
+                    format!("Unknown setting '{}'", name)
+                } else {
+                    format!("Unknown setting '{}' in [{}]", name, path)
+                };
+                checker.report(offset, message);
+            }
+            "profiles.*" => check_profile_value(checker, name, value),
+            _ => {
+                let child = if path.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{}.{}", path, name)
+                };
+                match value.get_ref() {
+                    DeValue::Table(table) => check_table(checker, table, &child),
+                    DeValue::Array(items) if child == "login.rules" => {
+                        for item in items {
+                            if let Some(table) = item.get_ref().as_table() {
+                                check_table(checker, table, &child);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+fn check_profile_value(checker: &mut Checker, key: &str, value: &Spanned<DeValue>) {
+    let range = match key {
+        "rtd3" => 0..=3,
+        "coolbits" => 0..=31,
+        _ => return,
+    };
+    let number = value
+        .get_ref()
+        .as_integer()
+        .and_then(|n| i64::from_str_radix(&n.as_str().replace('_', ""), n.radix()).ok());
+    if let Some(number) = number.filter(|n| !range.contains(n)) {
+        checker.report(
+            Some(value.span().start),
+            format!(
+                "{} = {} is out of range ({}-{})",
+                key,
+                number,
+                range.start(),
+                range.end()
+            ),
+        );
+    }
+}
+
+fn check_binding(checker: &mut Checker, action: &str, offset: Option<usize>, keys: &DeValue) {
+    if Action::deserialize(toml::Value::String(action.to_string())).is_err() {
+        checker.report(offset, format!("Unknown action '{}' in [keys]", action));
+        return;
+    }
+    for key in keys.as_array().into_iter().flatten() {
+        if let Some(Err(e)) = key.get_ref().as_str().map(keymap::parse_key) {
+            checker.report(Some(key.span().start), e.to_string());
+        }
+    }
+}
+
+fn check_theme(contents: &str, problems: &mut Vec<Problem>) {
+    let mut checker = Checker {
+        file: "theme.toml",
+        source: contents,
+        problems,
+    };
+    let table = match DeTable::parse(contents) {
+        Ok(table) => table.into_inner(),
+        Err(e) => {
+            checker.report(e.span().map(|span| span.start), e.message().to_string());
+            return;
+        }
+    };
+    for (key, value) in &table {
+        let name: &str = key.get_ref();
+        let offset = Some(value.span().start);
+        let value = value.get_ref();
+        let problem = match name {
+            "palette" => (!matches!(value.as_str(), Some("default" | "ansi")))
+                .then(|| "palette must be \"default\" or \"ansi\"".to_string()),
+            "accent_follows_mode" => {
+                (!value.is_bool()).then(|| "accent_follows_mode must be true or false".to_string())
+            }
+            _ if Theme::default().slot(name).is_none() => {
+                Some(format!("Unknown theme key '{}'", name))
+            }
+            _ => value
+                .as_str()
+                .and_then(|v| v.parse::<Color>().ok())
+                .is_none()
+                .then(|| {
+                    format!(
+                        "Invalid color for '{}': expected \"#rrggbb\" or a color name",
+                        name
+                    )
+                }),
+        };
+        if let Some(message) = problem {
+            checker.report(offset, message);
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use roxmltree::{Document, Node, ParsingOptions};
+
+// An element of `nvidia-smi -q -x` output with its text and child elements.
+// Attributes are not kept.
+#[derive(Debug, Default)]
+pub struct Element {
+    pub name: String,
+    pub text: String,
+    pub children: Vec<Element>,
+}
+
+impl Element {
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    // Text of a descendant by slash-separated path, e.g. "clocks/sm_clock".
+    // nvidia-smi reports unsupported fields as "N/A", which counts as missing.
+    pub fn text(&self, path: &str) -> Option<&str> {
+        let mut element = self;
+        for name in path.split('/') {
+            element = element.child(name)?;
+        }
+        let text = element.text.trim();
+        (!text.is_empty() && text != "N/A").then_some(text)
+    }
+
+    // The leading number of a value with a unit, e.g. "45 C" or "6144 MiB".
+    pub fn number(&self, path: &str) -> Option<u32> {
+        self.text(path)?.split_whitespace().next()?.parse().ok()
+    }
+}
+
+// roxmltree does the parsing; the tree is copied out so it can outlive the
+// nvidia-smi output it was read from.
+pub fn parse(input: &str) -> Option<Element> {
+    let options = ParsingOptions {
+        // nvidia-smi names its DTD in a DOCTYPE line.
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let document = Document::parse_with_options(input, options).ok()?;
+    Some(element(document.root_element()))
+}
+
+fn element(node: Node) -> Element {
+    Element {
+        name: node.tag_name().name().to_string(),
+        text: node
+            .children()
+            .filter(Node::is_text)
+            .filter_map(|child| child.text())
+            .collect(),
+        children: node
+            .children()
+            .filter(Node::is_element)
+            .map(element)
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Trimmed from a 470 driver: DTD v11, no attributes on <gpu>.
+    const OLD_DRIVER: &str = r#"<?xml version="1.0" ?>
+<!DOCTYPE nvidia_smi_log SYSTEM "nvsmi_device_v11.dtd">
+<nvidia_smi_log>
+	<driver_version>470.256.02</driver_version>
+	<gpu>
+		<product_name>GeForce GTX 1050</product_name>
+		<performance_state>P8</performance_state>
+		<temperature>
+			<gpu_temp>45 C</gpu_temp>
+		</temperature>
+		<clocks>
+			<sm_clock>139 MHz</sm_clock>
+		</clocks>
+		<processes>
+		</processes>
+	</gpu>
+</nvidia_smi_log>
+"#;
+
+    // Trimmed from a 550 driver: DTD v12, attributes, N/A fields and
+    // processes.
+    const NEW_DRIVER: &str = r#"<?xml version="1.0" ?>
+<!DOCTYPE nvidia_smi_log SYSTEM "nvsmi_device_v12.dtd">
+<nvidia_smi_log>
+	<timestamp>Thu Jan  1 10:00:00 2026</timestamp>
+	<driver_version>550.78</driver_version>
+	<attached_gpus>1</attached_gpus>
+	<gpu id="00000000:01:00.0">
+		<product_name>NVIDIA GeForce RTX 3060 Laptop GPU</product_name>
+		<persistence_mode>Disabled</persistence_mode>
+		<encoder_stats>
+			<session_count>0</session_count>
+		</encoder_stats>
+		<utilization>
+			<gpu_util>3 %</gpu_util>
+			<encoder_util>N/A</encoder_util>
+		</utilization>
+		<processes>
+			<process_info>
+				<pid>1234</pid>
+				<type>C</type>
+				<process_name>/usr/bin/python3</process_name>
+				<used_memory>512 MiB</used_memory>
+			</process_info>
+			<process_info>
+				<pid>5678</pid>
+				<type>G</type>
+				<process_name>/usr/lib/Xorg</process_name>
+				<used_memory>4 MiB</used_memory>
+			</process_info>
+		</processes>
+	</gpu>
+</nvidia_smi_log>
+"#;
+
+    #[test]
+    fn old_driver_output() {
+        let log = parse(OLD_DRIVER).unwrap();
+        assert_eq!(log.name, "nvidia_smi_log");
+        assert_eq!(log.text("driver_version"), Some("470.256.02"));
+        let gpu = log.child("gpu").unwrap();
+        assert_eq!(gpu.text("product_name"), Some("GeForce GTX 1050"));
+        assert_eq!(gpu.number("temperature/gpu_temp"), Some(45));
+        assert_eq!(gpu.number("clocks/sm_clock"), Some(139));
+        assert_eq!(gpu.children("processes").count(), 1);
+        assert_eq!(gpu.text("processes"), None);
+    }
+
+    #[test]
+    fn new_driver_output() {
+        let log = parse(NEW_DRIVER).unwrap();
+        let gpu = log.child("gpu").unwrap();
+        assert_eq!(
+            gpu.text("product_name"),
+            Some("NVIDIA GeForce RTX 3060 Laptop GPU")
+        );
+        assert_eq!(gpu.number("utilization/gpu_util"), Some(3));
+        assert_eq!(gpu.text("utilization/encoder_util"), None);
+        let processes: Vec<_> = gpu
+            .child("processes")
+            .unwrap()
+            .children("process_info")
+            .map(|process| process.number("pid"))
+            .collect();
+        assert_eq!(processes, [Some(1234), Some(5678)]);
+    }
+
+    #[test]
+    fn entities_and_comments() {
+        let log = parse(
+            "<log><!-- <b>not an element</b> --><name>Tom &amp; Jerry &lt;3&gt; &quot;x&quot; &apos;y&apos;</name><escaped>&amp;lt;</escaped></log>",
+        )
+        .unwrap();
+        assert_eq!(log.children.len(), 2);
+        assert_eq!(log.text("name"), Some("Tom & Jerry <3> \"x\" 'y'"));
+        assert_eq!(log.text("escaped"), Some("&lt;"));
+    }
+
+    #[test]
+    fn cdata_and_character_references() {
+        let log = parse("<log><name><![CDATA[a <b> & c]]></name><deg>45&#160;&#x43;</deg></log>")
+            .unwrap();
+        assert_eq!(log.text("name"), Some("a <b> & c"));
+        assert_eq!(log.text("deg"), Some("45\u{a0}C"));
+    }
+
+    #[test]
+    fn self_closing_tags() {
+        let log = parse(r#"<log><empty/><spaced attr="1" /><value>1</value></log>"#).unwrap();
+        let names: Vec<_> = log
+            .children
+            .iter()
+            .map(|child| child.name.as_str())
+            .collect();
+        assert_eq!(names, ["empty", "spaced", "value"]);
+        assert_eq!(log.text("empty"), None);
+        assert_eq!(log.number("value"), Some(1));
+    }
+
+    #[test]
+    fn truncated_or_malformed_input() {
+        let cut = &NEW_DRIVER[..NEW_DRIVER.find("</processes>").unwrap()];
+        assert!(parse(cut).is_none());
+        assert!(parse("<log><gpu>1</log>").is_none());
+        assert!(parse("<log></gpu></log>").is_none());
+        assert!(parse("<log><name").is_none());
+        assert!(parse("<log><!-- never closed").is_none());
+        assert!(parse("").is_none());
+        assert!(parse("nvidia-smi has failed").is_none());
+    }
+}